@@ -71,14 +71,7 @@ impl MotionDetector {
                 continue;
             }
 
-            let roi_frame = if let Some(ref roi) = self.config.roi {
-                self.apply_roi(&frame, roi)?
-            } else {
-                frame.try_clone()?
-            };
-
-            let mask = self.algorithm.process_frame(&roi_frame)?;
-            let motion_rects = self.algorithm.detect_motion(&mask, self.config.min_area)?;
+            let motion_rects = self.detect(&frame)?;
 
             if !motion_rects.is_empty() {
                 if motion_start.is_none() {
@@ -109,6 +102,21 @@ impl MotionDetector {
         Ok(motion_segments)
     }
 
+    /// Run the detector on one frame and return the moving regions
+    ///
+    /// Frames must be fed in order; the background model is updated on every call.
+    /// `frame_skip` is not applied here.
+    pub fn detect(&mut self, frame: &Mat) -> Result<Vec<Rect>, AnalysisError> {
+        let roi_frame = if let Some(ref roi) = self.config.roi {
+            self.apply_roi(frame, roi)?
+        } else {
+            frame.try_clone()?
+        };
+
+        let mask = self.algorithm.process_frame(&roi_frame)?;
+        self.algorithm.detect_motion(&mask, self.config.min_area)
+    }
+
     fn apply_roi(&self, frame: &Mat, roi: &RegionOfInterest) -> Result<Mat, AnalysisError> {
         let rect = Rect::new(roi.x, roi.y, roi.width, roi.height);
         let roi_mat = Mat::roi(frame, rect)?;
@@ -157,6 +157,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             video_creation_mode: Some("skip".to_string()), // Extract only, no video
             processing_mode: Some("sequential".to_string()),
             hardware_acceleration: HardwareAccelConfig::default(),
+            encoding: Default::default(),
+            resolution_policy: Default::default(),
+            scan: Default::default(),
         };
 
         // Write config to temp file
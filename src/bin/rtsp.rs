@@ -3,6 +3,7 @@
 //! This example demonstrates how to use the RTSP capture module programmatically.
 //! Run with: cargo run --bin rtsp
 
+use media_core::RtspUrl;
 use media_core::rtsp::capture::RTSPCapture;
 use std::env;
 use std::thread;
@@ -40,7 +41,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         true,  // run_once = true for example
     )?;
 
-    println!("   URL: {}", rtsp_url);
+    println!("   URL: {}", RtspUrl::parse(&rtsp_url));
     println!("   Output: {}", output_dir);
     println!("   Mode: FFmpeg (stream copy)");
 
@@ -70,7 +71,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("   Cameras: {} streams", rtsp_urls.len());
     for (i, url) in rtsp_urls.iter().enumerate() {
-        println!("   - Camera {}: {}", i + 1, url);
+        println!("   - Camera {}: {}", i + 1, RtspUrl::parse(url));
     }
     println!("   Output: {}", output_dir_multi);
 
@@ -133,7 +134,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         true, // run_once
     )?;
 
-    println!("   URL: {}", rtsp_url);
+    println!("   URL: {}", RtspUrl::parse(&rtsp_url));
     println!("   Output: {}", output_dir_opencv);
     println!("   Mode: OpenCV (custom FPS)");
     println!("   FPS: {}", custom_fps);
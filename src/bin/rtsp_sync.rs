@@ -5,11 +5,11 @@
 
 use media_core::rtsp_sync::{
     FFmpegUtils, HLSSyncConfig, LatencyMonitor, LatencyMonitorConfig, Mode, RtspSyncConfig,
-    StreamProcessor,
+    ShutdownToken, StreamProcessor, stop_child,
 };
+use media_core::{RtspUrl, StatsRegistry};
 use std::env;
 use std::process::Child;
-use std::thread;
 use std::time::Duration;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -20,6 +20,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // ============================================
     // 📝 CONFIGURATION
     // ============================================
+    // Ctrl-C / SIGTERM stop recordings gracefully
+    let shutdown = ShutdownToken::from_signals();
+
     let rtsp_urls = vec![
         env::var("RTSP_URL_1").unwrap_or_else(|_| "rtsp://127.0.0.1:8554/stream1".to_string()),
         env::var("RTSP_URL_2").unwrap_or_else(|_| "rtsp://127.0.0.1:8554/stream2".to_string()),
@@ -34,7 +37,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("-------------------------------------");
 
     for url in &rtsp_urls {
-        println!("   Probing: {}", url);
+        println!("   Probing: {}", RtspUrl::parse(url));
         match FFmpegUtils::get_stream_metadata(url) {
             Ok(metadata) => {
                 println!("   ✅ Status: {}", metadata.status);
@@ -53,7 +56,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // ============================================
     // 📊 2. Latency Monitoring
     // ============================================
-    // Latency monitor checks PTS drift across streams.
+    // Latency monitor measures jitter, drift and frame gaps on one
+    // connection per stream; latency needs RTCP sender reports.
     println!("📊 2. Latency Monitoring (single check)");
     println!("----------------------------------------");
 
@@ -63,11 +67,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         true, // display PTS
     );
 
-    // Run a single check instead of continuous loop
+    // Measure for one interval instead of continuous loop
     let latency_data = monitor.run_once();
     println!("   Checked {} streams", latency_data.len());
     for (url, info) in latency_data.iter() {
-        println!("   - {}: latency={:.3}s", url, info.latency as f64 / 1000.0);
+        match info.latency {
+            Some(latency) => print!("   - {}: latency={:.3}s", url, latency as f64 / 1000.0),
+            None => print!("   - {}: latency unknown (no RTCP time)", url),
+        }
+        println!(", jitter={:.2}ms, gaps={}", info.jitter_ms, info.frame_gaps);
     }
     println!();
 
@@ -96,7 +104,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         latency_monitor: LatencyMonitorConfig {
             monitor_interval_ms: 5000,
             display_pts: true,
+            ..Default::default()
         },
+        ..Default::default()
     };
 
     let processor = StreamProcessor::new(config);
@@ -122,14 +132,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Start FFmpeg recording processes for each stream
     let mut recording_processes: Vec<Child> = vec![];
+    let mut monitors = vec![];
+    let stats = StatsRegistry::new();
 
     for (i, url) in rtsp_urls.iter().enumerate() {
         let stream_dir = format!("output/rtsp_sync/stream_{}", i + 1);
         std::fs::create_dir_all(&stream_dir)?;
 
-        println!("   📹 Stream {}: {}", i + 1, url);
-        match FFmpegUtils::start_recording(url, &stream_dir, false) {
-            Ok(child) => {
+        println!("   📹 Stream {}: {}", i + 1, RtspUrl::parse(url));
+        // The monitor drains ffmpeg's progress and error output
+        let monitor = stats.monitor(&RtspUrl::parse(url));
+        match FFmpegUtils::start_recording(url, &stream_dir, false, Some(monitor)) {
+            Ok((child, monitor)) => {
+                monitors.extend(monitor);
                 recording_processes.push(child);
                 println!("      ✅ Recording started");
             }
@@ -139,16 +154,29 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    // Wait for demo duration
+    // Wait for demo duration (Ctrl-C ends it early)
     if !recording_processes.is_empty() {
         println!("\n   ⏱️  Recording for {} seconds...", demo_duration_secs);
-        thread::sleep(Duration::from_secs(demo_duration_secs));
+        shutdown.sleep(Duration::from_secs(demo_duration_secs));
 
-        // Stop all recording processes
+        // Stop all recording processes, letting FFmpeg finalize the MP4 files
         println!("   🛑 Stopping recordings...");
         for mut child in recording_processes {
-            let _ = child.kill();
-            let _ = child.wait();
+            let _ = stop_child(&mut child, Duration::from_secs(5));
+        }
+        for monitor in monitors {
+            let stats = monitor.finish();
+            println!(
+                "   📈 {}: {} frames, {} dropped, {} error line(s)",
+                stats.camera,
+                stats.frame,
+                stats.drop_frames,
+                stats.connection_refused
+                    + stats.unauthorized
+                    + stats.timeouts
+                    + stats.non_monotonic_dts
+                    + stats.other_errors
+            );
         }
         println!("   ✅ Recording demo completed\n");
     } else {
@@ -178,6 +206,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             playlist_size: 10,
         },
         latency_monitor: LatencyMonitorConfig::default(),
+        ..Default::default()
     };
 
     println!("   Mode: {:?}", sync_config.mode);
@@ -190,7 +219,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("   ℹ️  This requires active RTSP streams.");
 
     // Uncomment to actually run sync mode:
-    // let processor = StreamProcessor::new(sync_config);
+    // let processor = StreamProcessor::new(sync_config).with_shutdown(shutdown.clone());
     // processor.process_streams()?;
 
     // ============================================
@@ -226,6 +255,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("  - Latency:   Monitor PTS and latency");
     println!("  - Recording: Record streams to files");
     println!("  - Sync:      Synchronized HLS streaming");
+    println!("  - Verify:    Check that the HLS playlists line up");
+    println!("  - Mosaic:    Compose all streams into one grid video");
     println!("\nUsage:");
     println!("  1. Set RTSP_URL_1, RTSP_URL_2 environment variables");
     println!("  2. Create a config file or use RtspSyncConfig::default()");
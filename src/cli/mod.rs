@@ -6,6 +6,7 @@ mod hls;
 mod metadata;
 mod process;
 mod rtsp;
+mod segments;
 mod streaming;
 mod usage;
 mod video_process;
@@ -16,8 +17,9 @@ pub use benchmark::run_benchmark_mode;
 pub use config::run_config_mode;
 pub use hls::run_hls_mode;
 pub use metadata::run_metadata_mode;
-pub use process::{run_extraction_mode, run_process_mode};
+pub use process::{run_extraction_mode, run_process_mode, run_watch_mode};
 pub use rtsp::run_rtsp_mode;
+pub use segments::run_segments_mode;
 pub use streaming::run_streaming_mode;
 pub use usage::print_usage;
 pub use video_process::run_video_process_mode;
@@ -15,6 +15,7 @@ pub fn run_process_mode(config_path: &str) -> Result<(), Box<dyn Error>> {
             println!("📊 Processing Statistics:");
             println!("   • Files processed: {}", stats.files_processed);
             println!("   • Files failed: {}", stats.files_failed);
+            println!("   • Files skipped: {}", stats.files_skipped);
             println!("   • Success rate: {:.2}%", stats.success_rate());
             println!("   • Processing time: {:?}", stats.processing_time);
 
@@ -56,6 +57,9 @@ pub fn run_extraction_mode(input_path: &str, output_path: &str) -> Result<(), Bo
         video_creation_mode: Some("skip".to_string()), // CRITICAL: Skip video creation
         processing_mode: Some("sequential".to_string()),
         hardware_acceleration: HardwareAccelConfig::default(),
+        encoding: Default::default(),
+        resolution_policy: Default::default(),
+        scan: Default::default(),
     };
 
     let mut temp_file = NamedTempFile::new()?;
@@ -78,3 +82,19 @@ pub fn run_extraction_mode(input_path: &str, output_path: &str) -> Result<(), Bo
 
     Ok(())
 }
+
+pub fn run_watch_mode(config_path: &str) -> Result<(), Box<dyn Error>> {
+    use media_core::ShutdownToken;
+    use media_core::process::FolderWatcher;
+
+    println!("🎬 Starting Watch Mode...");
+    println!("📄 Using config file: {}", config_path);
+
+    let mut watcher = FolderWatcher::from_config_file(config_path)?;
+    println!("📊 Status file: {}", watcher.status_path().display());
+
+    // Runs until Ctrl-C or SIGTERM
+    watcher.run(&ShutdownToken::from_signals())?;
+
+    Ok(())
+}
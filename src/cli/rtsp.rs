@@ -1,4 +1,4 @@
-use media_core::{CaptureConfig, RTSPCapture, SavingOption};
+use media_core::{CaptureConfig, RTSPCapture, RetentionManager, SegmentIndex, ShutdownToken};
 use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
@@ -11,53 +11,62 @@ pub fn run_rtsp_mode() -> Result<(), Box<dyn Error>> {
     let reader = BufReader::new(config_file);
     let config: CaptureConfig = serde_json::from_reader(reader)?;
 
+    // Ctrl-C / SIGTERM stop every camera and let ffmpeg finish its segment
+    let shutdown = ShutdownToken::from_signals();
     let mut handles = vec![];
 
-    let (urls_to_process, show_preview_for_list) = match config.saving_option {
-        SavingOption::Single => (vec![config.rtsp_url.clone()], config.show_preview),
-        SavingOption::List => (config.rtsp_url_list.clone(), false),
-        SavingOption::Both => {
-            let mut urls = vec![config.rtsp_url.clone()];
-            urls.extend(config.rtsp_url_list.clone());
-            (urls, false)
+    // `cameras` if present, otherwise the legacy URL fields; aliases and credentials applied
+    let cameras = config.camera_settings()?;
+
+    println!("📡 Processing {} RTSP stream(s)...", cameras.len());
+
+    // Old segments are deleted in the background while cameras record
+    let retention_stop = ShutdownToken::new();
+    let mut retention_handle = None;
+    if config.retention.is_enabled() {
+        let mut retention = RetentionManager::new(
+            &config.retention,
+            config.retention.audit_path(&config.output_directory),
+        );
+        for camera in &cameras {
+            let camera_id = camera.url.camera_id();
+            let policy = config.retention.policy_for(&camera_id);
+            retention.add_camera(camera_id, camera.camera_dir(), policy);
         }
-    };
+        retention_handle = Some(retention.spawn(retention_stop.clone()));
+    }
 
-    println!("📡 Processing {} RTSP stream(s)...", urls_to_process.len());
+    let segment_index = config
+        .segment_index
+        .enabled
+        .then(|| SegmentIndex::new(config.segment_index.index_path(&config.output_directory)));
 
-    for url in urls_to_process {
-        let output_dir = config.output_directory.clone();
-        let show_preview = if config.rtsp_url == url {
-            show_preview_for_list
-        } else {
-            false
-        };
-        let segment_duration = config.saved_time_duration;
-        let use_fps = config.use_fps;
-        let fps = config.fps;
-        let hls_config = Some(config.hls.clone());
+    for camera in cameras {
+        let supervisor_config = config.supervisor.clone();
+        let shutdown = shutdown.clone();
+        let segment_index = segment_index.clone();
+        let ffmpeg_log = config.ffmpeg_log.clone();
 
-        let handle = thread::spawn(move || {
-            match RTSPCapture::new(
-                url.clone(),
-                output_dir,
-                show_preview,
-                segment_duration,
-                use_fps,
-                fps,
-                hls_config,
-                false,
-            ) {
-                Ok(mut capture) => {
-                    println!("📹 Processing stream: {}", url);
-                    if let Err(e) = capture.process_stream() {
-                        eprintln!("❌ Error processing stream {}: {:?}", url, e);
-                    }
+        let handle = thread::spawn(move || match RTSPCapture::from_camera(&camera, false) {
+            Ok(capture) => {
+                let mut capture = capture
+                    .with_supervisor_config(supervisor_config)
+                    .with_shutdown(shutdown)
+                    .with_ffmpeg_log(ffmpeg_log);
+                if let Some(index) = segment_index {
+                    capture = capture.with_segment_index(index);
                 }
-                Err(e) => {
-                    eprintln!("❌ Failed to create RTSP capture for {}: {:?}", url, e);
+                println!("📹 Processing stream: {}", camera.url);
+                if let Err(e) = capture.process_stream() {
+                    eprintln!("❌ Error processing stream {}: {:?}", camera.url, e);
                 }
             }
+            Err(e) => {
+                eprintln!(
+                    "❌ Failed to create RTSP capture for {}: {:?}",
+                    camera.url, e
+                );
+            }
         });
         handles.push(handle);
     }
@@ -65,7 +74,15 @@ pub fn run_rtsp_mode() -> Result<(), Box<dyn Error>> {
     for handle in handles {
         handle.join().unwrap();
     }
+    retention_stop.trigger();
+    if let Some(handle) = retention_handle {
+        handle.join().unwrap();
+    }
 
-    println!("✅ RTSP stream capture completed!");
+    if shutdown.is_triggered() {
+        println!("🛑 RTSP stream capture stopped");
+    } else {
+        println!("✅ RTSP stream capture completed!");
+    }
     Ok(())
 }
@@ -0,0 +1,112 @@
+//! CLI handler for looking up and exporting recorded segments.
+
+use media_core::{CaptureConfig, SegmentIndex, parse_local_time};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+fn print_segments_usage() {
+    println!("Usage:");
+    println!("    media_core segments list <camera> <from> <to> [--index <file>] [--json]");
+    println!("    media_core segments export <camera> <from> <to> <output.mp4> [--index <file>]");
+    println!();
+    println!("Times are local, e.g. \"2024-05-01 13:00:00\" or RFC 3339.");
+    println!("The index defaults to the one configured in config.json.");
+}
+
+/// Run segment lookup / export mode
+pub fn run_segments_mode(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let positional: Vec<&String> = {
+        let mut skip_next = false;
+        args.iter()
+            .filter(|arg| {
+                if skip_next {
+                    skip_next = false;
+                    return false;
+                }
+                if *arg == "--index" {
+                    skip_next = true;
+                    return false;
+                }
+                !arg.starts_with("--")
+            })
+            .collect()
+    };
+
+    let (command, expected) = match positional.first().map(|s| s.as_str()) {
+        Some("list") => ("list", 4),
+        Some("export") => ("export", 5),
+        _ => {
+            print_segments_usage();
+            return Ok(());
+        }
+    };
+    if positional.len() < expected {
+        println!("Error: Missing arguments for 'segments {}'", command);
+        print_segments_usage();
+        return Ok(());
+    }
+
+    let camera = positional[1].as_str();
+    let from = parse_local_time(positional[2])
+        .ok_or_else(|| format!("Invalid start time: {}", positional[2]))?;
+    let to = parse_local_time(positional[3])
+        .ok_or_else(|| format!("Invalid end time: {}", positional[3]))?;
+    if to <= from {
+        return Err("The end time must be after the start time".into());
+    }
+
+    let index = SegmentIndex::new(index_path(args)?);
+
+    if command == "export" {
+        let output = Path::new(positional[4].as_str());
+        println!(
+            "🎬 Exporting {} from {} to {} into {}",
+            camera,
+            from,
+            to,
+            output.display()
+        );
+        index.export(camera, from, to, output)?;
+        return Ok(());
+    }
+
+    let records = index.lookup(camera, from, to)?;
+    if args.iter().any(|arg| arg == "--json") {
+        println!("{}", serde_json::to_string_pretty(&records)?);
+        return Ok(());
+    }
+
+    if records.is_empty() {
+        println!("No segments for {} between {} and {}", camera, from, to);
+        return Ok(());
+    }
+    println!("📼 {} segment(s) for {}:", records.len(), camera);
+    for record in &records {
+        println!(
+            "   {} → {}  {:>7.1}s  {:>10} bytes  {:<6} {}{}",
+            record.start.format("%Y-%m-%d %H:%M:%S"),
+            record.end.format("%H:%M:%S"),
+            record.duration_secs,
+            record.bytes,
+            record.codec,
+            record.path.display(),
+            if record.has_gaps { "  (gaps)" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+/// `--index <file>`, or the index configured in config.json
+fn index_path(args: &[String]) -> Result<PathBuf, Box<dyn Error>> {
+    if let Some(position) = args.iter().position(|arg| arg == "--index") {
+        let path = args.get(position + 1).ok_or("Missing file after --index")?;
+        return Ok(PathBuf::from(path));
+    }
+
+    let config_file = File::open("config.json")
+        .map_err(|e| format!("No --index given and config.json can't be read: {}", e))?;
+    let config: CaptureConfig = serde_json::from_reader(BufReader::new(config_file))?;
+    Ok(config.segment_index.index_path(&config.output_directory))
+}
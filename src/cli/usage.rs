@@ -7,8 +7,11 @@ pub fn print_usage() {
     println!("MODES:");
     println!("    rtsp                              Run RTSP stream capture mode");
     println!("    process <config_file>             Run video processing mode");
+    println!("    process watch <config_file>       Watch input folders and process new files");
     println!("    hls <input_file> <output_dir>     Convert video to HLS VOD format");
     println!("    hls --config <config_file>        Convert using JSON config file");
+    println!("    segments list <camera> <from> <to>  List recorded segments in a time range");
+    println!("    segments export <camera> <from> <to> <out.mp4>  Export a time range as one MP4");
     println!("    metadata <file_path> [--json]     Extract media file metadata");
     println!("    analysis motion <video> <output>  Run motion detection");
     println!("    analysis similarity <dir> <out>   Run image similarity analysis");
@@ -22,8 +25,10 @@ pub fn print_usage() {
     println!("EXAMPLES:");
     println!("    ./media_core rtsp                           # Capture RTSP streams");
     println!("    ./media_core process video_config.json      # Process videos");
+    println!("    ./media_core process watch config.json      # Process files as they arrive");
     println!("    ./media_core hls video.mp4 hls_output/      # Convert MP4 to HLS");
     println!("    ./media_core hls --config hls_config.json   # Convert using config");
+    println!("    ./media_core segments export lobby \"2024-05-01 13:00\" \"2024-05-01 13:10\" clip.mp4");
     println!("    ./media_core metadata video.mp4             # Show video metadata");
     println!("    ./media_core metadata image.jpg --json      # Output as JSON");
     println!("    ./media_core analysis motion video.mp4 out/ # Detect motion");
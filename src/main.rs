@@ -26,6 +26,13 @@ fn main() -> Result<(), Box<dyn Error>> {
                     return Ok(());
                 }
                 cli::run_extraction_mode(&args[3], &args[4])?;
+            } else if args[2] == "watch" {
+                if args.len() < 4 {
+                    println!("Error: Watch mode requires a config file path");
+                    println!("Usage: cargo run process watch <config_file_path>");
+                    return Ok(());
+                }
+                cli::run_watch_mode(&args[3])?;
             } else {
                 cli::run_process_mode(&args[2])?;
             }
@@ -33,6 +40,9 @@ fn main() -> Result<(), Box<dyn Error>> {
         "hls" => {
             cli::run_hls_mode(&args[2..])?;
         }
+        "segments" => {
+            cli::run_segments_mode(&args[2..])?;
+        }
         "metadata" => {
             cli::run_metadata_mode(&args[2..])?;
         }
@@ -1,5 +1,8 @@
 use super::hw_accel::HardwareAccelConfig;
-use crate::process::types::{FileFormat, ProcessingMode, get_default_supported_formats};
+use crate::process::types::{
+    AudioFormat, FileFormat, ImageFormat, ProcessingMode, StreamOutput, VideoFormat,
+    get_default_supported_formats,
+};
 use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::Write;
@@ -15,13 +18,106 @@ pub struct VideoExtractionConfig {
     pub frame_interval: usize,
     pub extraction_mode: String,
     pub create_summary_per_thread: Option<bool>,
-    /// Mode for video creation: "direct", "temp_frames", or "skip" (extraction only)
+    /// Mode for video creation: "direct", "temp_frames", "stream", or "skip" (extraction only)
     pub video_creation_mode: Option<String>,
     pub processing_mode: Option<String>,
 
     /// Hardware acceleration configuration
     #[serde(default)]
     pub hardware_acceleration: HardwareAccelConfig,
+
+    /// Encoder settings for "temp_frames" and "stream" video creation
+    #[serde(default)]
+    pub encoding: VideoEncodingConfig,
+
+    /// How "direct" video creation handles inputs whose size differs from the output
+    #[serde(default)]
+    pub resolution_policy: ResolutionPolicy,
+
+    /// Directory scan filters (recursion, globs, size and modification time)
+    #[serde(flatten)]
+    pub scan: ScanOptions,
+}
+
+/// Filters applied by `VideoScanner` to input directories
+///
+/// Fields are read from the top level of the video config. Explicitly listed
+/// input files are not filtered.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ScanOptions {
+    /// Descend into subdirectories
+    pub recursive: bool,
+    /// Deepest subdirectory level searched when `recursive` (0 = input directory only)
+    pub max_depth: Option<usize>,
+    /// Keep only files whose name or relative path matches one of these globs
+    pub include_globs: Vec<String>,
+    /// Skip files and directories whose name or relative path matches one of these globs
+    pub exclude_globs: Vec<String>,
+    /// Skip files smaller than this
+    pub min_size_bytes: Option<u64>,
+    /// Keep only files modified at or after this time (RFC 3339 or `YYYY-MM-DD`)
+    pub modified_after: Option<String>,
+    /// Keep only files modified before this time (RFC 3339 or `YYYY-MM-DD`)
+    pub modified_before: Option<String>,
+}
+
+/// Policy for frames whose size differs from the output video size
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionPolicy {
+    /// Scale to fit inside the output and pad with black bars
+    Letterbox,
+    /// Stretch to the output size
+    ScaleToFit,
+    /// Scale to cover the output and cut the centered region
+    Crop,
+    /// Leave out inputs with a different size
+    #[default]
+    SkipMismatched,
+    /// Stop with an error on the first mismatched input
+    Fail,
+}
+
+/// ffmpeg encoder settings for created videos
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct VideoEncodingConfig {
+    /// ffmpeg video encoder, e.g. "libx264" or "libx265"
+    pub codec: String,
+    /// Constant rate factor (lower is higher quality)
+    pub crf: u8,
+    /// Encoder preset, e.g. "veryfast" (encoder default if unset)
+    pub preset: Option<String>,
+}
+
+impl Default for VideoEncodingConfig {
+    fn default() -> Self {
+        Self {
+            codec: "libx264".to_string(),
+            crf: 23,
+            preset: None,
+        }
+    }
+}
+
+impl VideoEncodingConfig {
+    /// Output-side ffmpeg arguments for this encoder
+    pub fn to_ffmpeg_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-c:v".to_string(),
+            self.codec.clone(),
+            "-crf".to_string(),
+            self.crf.to_string(),
+        ];
+        if let Some(preset) = &self.preset {
+            args.push("-preset".to_string());
+            args.push(preset.clone());
+        }
+        args.push("-pix_fmt".to_string());
+        args.push("yuv420p".to_string());
+        args
+    }
 }
 
 impl Default for VideoExtractionConfig {
@@ -38,6 +134,9 @@ impl Default for VideoExtractionConfig {
             video_creation_mode: Some("standard".to_string()),
             processing_mode: Some("sequential".to_string()),
             hardware_acceleration: HardwareAccelConfig::default(),
+            encoding: VideoEncodingConfig::default(),
+            resolution_policy: ResolutionPolicy::default(),
+            scan: ScanOptions::default(),
         }
     }
 }
@@ -51,6 +150,18 @@ pub struct ProcessConfig {
     pub processing_mode: ProcessingMode,
     pub supported_formats: Vec<FileFormat>,
     pub video_config: Option<VideoExtractionConfig>,
+
+    /// Conversion settings applied by `FileHandler` in file/batch/directory modes
+    #[serde(default)]
+    pub conversion: ConversionOptions,
+
+    /// Capture settings used by `ProcessingMode::StreamProcess`
+    #[serde(default)]
+    pub stream: StreamProcessOptions,
+
+    /// Settings for the watch-folder daemon (`media_core process watch`)
+    #[serde(default)]
+    pub watch: WatchOptions,
 }
 
 impl Default for ProcessConfig {
@@ -62,6 +173,9 @@ impl Default for ProcessConfig {
             processing_mode: ProcessingMode::default(),
             supported_formats: get_default_supported_formats(),
             video_config: Some(VideoExtractionConfig::default()),
+            conversion: ConversionOptions::default(),
+            stream: StreamProcessOptions::default(),
+            watch: WatchOptions::default(),
         }
     }
 }
@@ -74,9 +188,13 @@ pub struct ProcessingOptions {
     pub create_output_directory: bool,
     pub overwrite_existing: bool,
     pub max_file_size_mb: Option<u64>,
+    /// Time budget for each input file
     pub timeout_seconds: Option<u64>,
     pub parallel_processing: bool,
     pub backup_original: bool,
+    /// Time budget for the whole run (unlimited if unset)
+    #[serde(default)]
+    pub job_timeout_seconds: Option<u64>,
 }
 
 impl Default for ProcessingOptions {
@@ -90,6 +208,121 @@ impl Default for ProcessingOptions {
             timeout_seconds: Some(300),   // 5 minutes default timeout
             parallel_processing: false,
             backup_original: false,
+            job_timeout_seconds: None,
+        }
+    }
+}
+
+/// Capture settings for stream processing
+///
+/// The input path is a stream URL (rtsp://, http://, file://) or a local
+/// video file. Capture stops at whichever limit is reached first, or at the
+/// end of the input.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamProcessOptions {
+    /// Write segmented MP4 files or individual frames
+    pub output: StreamOutput,
+    /// Maximum capture duration in seconds
+    pub max_duration_seconds: Option<u64>,
+    /// Maximum number of frames to write
+    pub max_frames: Option<u64>,
+    /// Length of each MP4 segment in seconds
+    pub segment_duration_seconds: u64,
+    /// Save every Nth decoded frame in frames mode
+    pub frame_interval: usize,
+}
+
+impl Default for StreamProcessOptions {
+    fn default() -> Self {
+        Self {
+            output: StreamOutput::default(),
+            max_duration_seconds: Some(60),
+            max_frames: None,
+            segment_duration_seconds: 60,
+            frame_interval: 1,
+        }
+    }
+}
+
+/// Settings for the watch-folder daemon
+///
+/// The watched folders are `video_config.input_directories` (or `input_path`
+/// without a video config). A file is processed once it has been seen with the
+/// same size and mtime in two polls at least `stable_seconds` apart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchOptions {
+    /// Seconds between directory polls
+    pub poll_interval_seconds: u64,
+    /// Seconds a file must stay unchanged before it is processed
+    pub stable_seconds: u64,
+    /// Where processed inputs are moved (`<watched dir>/done` if unset)
+    pub done_directory: Option<String>,
+    /// Where failed inputs are moved (`<watched dir>/failed` if unset)
+    pub failed_directory: Option<String>,
+    /// Status file path (`<output_path>/watch_status.json` if unset)
+    pub status_file: Option<String>,
+    /// Number of recent results kept in the status file
+    pub status_history: usize,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval_seconds: 5,
+            stable_seconds: 10,
+            done_directory: None,
+            failed_directory: None,
+            status_file: None,
+            status_history: 100,
+        }
+    }
+}
+
+/// Conversion settings for the generic file processor
+///
+/// Every field is optional. A file whose branch has nothing configured is
+/// copied through unchanged; otherwise it is converted with ffmpeg.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConversionOptions {
+    /// Target container for video files (keeps the source format if unset)
+    pub target_video_format: Option<VideoFormat>,
+    /// Target format for audio files (keeps the source format if unset)
+    pub target_audio_format: Option<AudioFormat>,
+    /// Target format for image files (keeps the source format if unset)
+    pub target_image_format: Option<ImageFormat>,
+    /// Video encoder passed to ffmpeg, e.g. "libx264" (format default if unset)
+    pub video_codec: Option<String>,
+    /// Audio encoder passed to ffmpeg, e.g. "aac" (format default if unset)
+    pub audio_codec: Option<String>,
+    /// Video bitrate, e.g. "2M"
+    pub video_bitrate: Option<String>,
+    /// Audio bitrate, e.g. "128k"
+    pub audio_bitrate: Option<String>,
+    /// Resize applied to video and image files
+    pub resize: Option<ResizeOptions>,
+}
+
+/// Output dimensions for video and image conversion
+///
+/// A missing dimension is derived from the other one, keeping the aspect ratio.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResizeOptions {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl ResizeOptions {
+    /// Build the ffmpeg `scale` filter for this resize, if any dimension is set
+    pub fn to_scale_filter(&self) -> Option<String> {
+        match (self.width, self.height) {
+            (None, None) => None,
+            (w, h) => Some(format!(
+                "scale={}:{}",
+                w.map(|v| v.to_string()).unwrap_or_else(|| "-2".to_string()),
+                h.map(|v| v.to_string()).unwrap_or_else(|| "-2".to_string())
+            )),
         }
     }
 }
@@ -1,6 +1,8 @@
 //! Convenience functions for creating processors with common configurations
 
-use crate::process::config::{ProcessConfig, ProcessingOptions};
+use crate::process::config::{
+    ConversionOptions, ProcessConfig, ProcessingOptions, StreamProcessOptions, WatchOptions,
+};
 use crate::process::processor::Processor;
 use crate::process::types::{
     FileFormat, ProcessError, ProcessingMode, VideoFormat, get_default_supported_formats,
@@ -18,6 +20,9 @@ pub fn create_processor(
         processing_mode: ProcessingMode::SingleFile,
         supported_formats: get_default_supported_formats(),
         video_config: None,
+        conversion: ConversionOptions::default(),
+        stream: StreamProcessOptions::default(),
+        watch: WatchOptions::default(),
     };
 
     Processor::new(config)
@@ -36,6 +41,9 @@ pub fn create_processor_with_options(
         processing_mode: ProcessingMode::SingleFile,
         supported_formats: get_default_supported_formats(),
         video_config: None,
+        conversion: ConversionOptions::default(),
+        stream: StreamProcessOptions::default(),
+        watch: WatchOptions::default(),
     };
 
     Processor::new(config)
@@ -54,6 +62,9 @@ pub fn create_processor_with_mode(
         processing_mode: mode,
         supported_formats: get_default_supported_formats(),
         video_config: None,
+        conversion: ConversionOptions::default(),
+        stream: StreamProcessOptions::default(),
+        watch: WatchOptions::default(),
     };
 
     Processor::new(config)
@@ -77,6 +88,9 @@ pub fn create_video_processor() -> Result<Processor, ProcessError> {
             FileFormat::Video(VideoFormat::Mov),
         ],
         video_config: None,
+        conversion: ConversionOptions::default(),
+        stream: StreamProcessOptions::default(),
+        watch: WatchOptions::default(),
     };
 
     Processor::new(config)
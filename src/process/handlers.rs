@@ -1,15 +1,21 @@
-use crate::process::config::ProcessingOptions;
+use crate::process::config::{ConversionOptions, ProcessingOptions};
+use crate::process::sniff::SniffedFormat;
+use crate::process::timeout::{Deadline, run_with_deadline};
 use crate::process::types::{
     AudioFormat, DocumentFormat, FileFormat, ImageFormat, ProcessError, VideoFormat,
 };
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 /// Handler for file-specific operations
 pub struct FileHandler;
 
 impl FileHandler {
-    /// Detect file format based on extension
+    /// Detect file format from content, falling back to the extension
+    ///
+    /// Documents are recognized by extension only. Media files are sniffed
+    /// by their leading bytes; see `SniffedFormat::detect`.
     pub fn detect_file_format(file_path: &Path) -> Result<FileFormat, ProcessError> {
         let extension = file_path
             .extension()
@@ -17,6 +23,12 @@ impl FileHandler {
             .unwrap_or("")
             .to_lowercase();
 
+        if !matches!(extension.as_str(), "txt" | "json" | "xml" | "csv")
+            && let Some(sniffed) = SniffedFormat::detect(file_path)
+        {
+            return Ok(sniffed.to_file_format());
+        }
+
         match extension.as_str() {
             "mp4" | "avi" | "mkv" | "mov" | "webm" => match extension.as_str() {
                 "mp4" => Ok(FileFormat::Video(VideoFormat::Mp4)),
@@ -54,17 +66,97 @@ impl FileHandler {
         }
     }
 
+    /// Resolve the output path for a file, using the target format's extension
+    /// when a conversion target is configured for its branch
+    pub fn resolve_output_path(
+        output_file: &Path,
+        format: &FileFormat,
+        conversion: &ConversionOptions,
+    ) -> PathBuf {
+        let target_extension = match format {
+            FileFormat::Video(_) => conversion
+                .target_video_format
+                .as_ref()
+                .map(|f| f.extension()),
+            FileFormat::Audio(_) => conversion
+                .target_audio_format
+                .as_ref()
+                .map(|f| f.extension()),
+            FileFormat::Image(_) => conversion
+                .target_image_format
+                .as_ref()
+                .map(|f| f.extension()),
+            FileFormat::Document(_) => None,
+        };
+
+        match target_extension {
+            Some(ext) => output_file.with_extension(ext),
+            None => output_file.to_path_buf(),
+        }
+    }
+
+    /// Build the ffmpeg arguments that convert `input_file` into `output_file`
+    ///
+    /// Returns `None` when nothing is configured for the file's branch, in which
+    /// case the file is copied through unchanged.
+    pub fn build_conversion_args(
+        input_file: &Path,
+        output_file: &Path,
+        format: &FileFormat,
+        options: &ProcessingOptions,
+        conversion: &ConversionOptions,
+    ) -> Option<Vec<String>> {
+        match format {
+            FileFormat::Video(source) => {
+                Self::build_video_args(input_file, output_file, source, options, conversion)
+            }
+            FileFormat::Audio(source) => {
+                Self::build_audio_args(input_file, output_file, source, options, conversion)
+            }
+            FileFormat::Image(source) => {
+                Self::build_image_args(input_file, output_file, source, options, conversion)
+            }
+            FileFormat::Document(_) => None,
+        }
+    }
+
     /// Process file based on format
+    ///
+    /// ffmpeg conversions are killed with `ProcessError::Timeout` once
+    /// `deadline` passes.
     pub fn process_file_by_format(
         input_file: &Path,
         output_file: &Path,
         format: &FileFormat,
         options: &ProcessingOptions,
+        conversion: &ConversionOptions,
+        deadline: &Deadline,
     ) -> Result<(), ProcessError> {
         match format {
-            FileFormat::Video(_) => Self::process_video_file(input_file, output_file, options),
-            FileFormat::Audio(_) => Self::process_audio_file(input_file, output_file, options),
-            FileFormat::Image(_) => Self::process_image_file(input_file, output_file, options),
+            FileFormat::Video(_) => Self::process_video_file(
+                input_file,
+                output_file,
+                format,
+                options,
+                conversion,
+                deadline,
+            ),
+            FileFormat::Audio(_) => Self::process_audio_file(
+                input_file,
+                output_file,
+                format,
+                options,
+                conversion,
+                deadline,
+            ),
+            FileFormat::Image(_) => Self::process_image_file(
+                input_file,
+                output_file,
+                format,
+                options,
+                conversion,
+                deadline,
+            ),
             FileFormat::Document(_) => {
                 Self::process_document_file(input_file, output_file, options)
             }
@@ -75,7 +167,10 @@ impl FileHandler {
     fn process_video_file(
         input_file: &Path,
         output_file: &Path,
+        format: &FileFormat,
         options: &ProcessingOptions,
+        conversion: &ConversionOptions,
+        deadline: &Deadline,
     ) -> Result<(), ProcessError> {
         if options.verbose_logging {
             println!(
@@ -84,18 +179,25 @@ impl FileHandler {
             );
         }
 
-        // Simple copy operation for generic processor
-        fs::copy(input_file, output_file)
-            .map_err(|e| ProcessError::IoError(format!("Failed to copy video file: {}", e)))?;
-
-        Ok(())
+        match Self::build_conversion_args(input_file, output_file, format, options, conversion) {
+            Some(args) => Self::run_ffmpeg(&args, input_file, deadline),
+            None => {
+                fs::copy(input_file, output_file).map_err(|e| {
+                    ProcessError::IoError(format!("Failed to copy video file: {}", e))
+                })?;
+                Ok(())
+            }
+        }
     }
 
     /// Process audio files
     fn process_audio_file(
         input_file: &Path,
         output_file: &Path,
+        format: &FileFormat,
         options: &ProcessingOptions,
+        conversion: &ConversionOptions,
+        deadline: &Deadline,
     ) -> Result<(), ProcessError> {
         if options.verbose_logging {
             println!(
@@ -104,17 +206,25 @@ impl FileHandler {
             );
         }
 
-        fs::copy(input_file, output_file)
-            .map_err(|e| ProcessError::IoError(format!("Failed to copy audio file: {}", e)))?;
-
-        Ok(())
+        match Self::build_conversion_args(input_file, output_file, format, options, conversion) {
+            Some(args) => Self::run_ffmpeg(&args, input_file, deadline),
+            None => {
+                fs::copy(input_file, output_file).map_err(|e| {
+                    ProcessError::IoError(format!("Failed to copy audio file: {}", e))
+                })?;
+                Ok(())
+            }
+        }
     }
 
     /// Process image files
     fn process_image_file(
         input_file: &Path,
         output_file: &Path,
+        format: &FileFormat,
         options: &ProcessingOptions,
+        conversion: &ConversionOptions,
+        deadline: &Deadline,
     ) -> Result<(), ProcessError> {
         if options.verbose_logging {
             println!(
@@ -123,13 +233,20 @@ impl FileHandler {
             );
         }
 
-        fs::copy(input_file, output_file)
-            .map_err(|e| ProcessError::IoError(format!("Failed to copy image file: {}", e)))?;
-
-        Ok(())
+        match Self::build_conversion_args(input_file, output_file, format, options, conversion) {
+            Some(args) => Self::run_ffmpeg(&args, input_file, deadline),
+            None => {
+                fs::copy(input_file, output_file).map_err(|e| {
+                    ProcessError::IoError(format!("Failed to copy image file: {}", e))
+                })?;
+                Ok(())
+            }
+        }
     }
 
     /// Process document files
+    ///
+    /// Documents have no conversion targets, so they are always copied.
     fn process_document_file(
         input_file: &Path,
         output_file: &Path,
@@ -147,4 +264,194 @@ impl FileHandler {
 
         Ok(())
     }
+
+    /// Build ffmpeg arguments for a video conversion
+    fn build_video_args(
+        input_file: &Path,
+        output_file: &Path,
+        source: &VideoFormat,
+        options: &ProcessingOptions,
+        conversion: &ConversionOptions,
+    ) -> Option<Vec<String>> {
+        let target = conversion
+            .target_video_format
+            .clone()
+            .unwrap_or_else(|| source.clone());
+        let scale_filter = conversion.resize.as_ref().and_then(|r| r.to_scale_filter());
+
+        if target == *source
+            && conversion.video_codec.is_none()
+            && conversion.audio_codec.is_none()
+            && conversion.video_bitrate.is_none()
+            && conversion.audio_bitrate.is_none()
+            && scale_filter.is_none()
+        {
+            return None;
+        }
+
+        let mut args = Self::base_args(input_file, options);
+
+        let video_codec = conversion
+            .video_codec
+            .clone()
+            .unwrap_or_else(|| target.default_video_codec().to_string());
+        args.push("-c:v".to_string());
+        args.push(video_codec);
+        if let Some(bitrate) = &conversion.video_bitrate {
+            args.push("-b:v".to_string());
+            args.push(bitrate.clone());
+        }
+        if let Some(filter) = scale_filter {
+            args.push("-vf".to_string());
+            args.push(filter);
+        }
+        args.push("-pix_fmt".to_string());
+        args.push("yuv420p".to_string());
+
+        let audio_codec = conversion
+            .audio_codec
+            .clone()
+            .unwrap_or_else(|| target.default_audio_codec().to_string());
+        args.push("-c:a".to_string());
+        args.push(audio_codec);
+        if let Some(bitrate) = &conversion.audio_bitrate {
+            args.push("-b:a".to_string());
+            args.push(bitrate.clone());
+        }
+
+        if matches!(target, VideoFormat::Mp4 | VideoFormat::Mov) {
+            args.push("-movflags".to_string());
+            args.push("+faststart".to_string());
+        }
+
+        args.push(output_file.to_string_lossy().to_string());
+        Some(args)
+    }
+
+    /// Build ffmpeg arguments for an audio conversion
+    fn build_audio_args(
+        input_file: &Path,
+        output_file: &Path,
+        source: &AudioFormat,
+        options: &ProcessingOptions,
+        conversion: &ConversionOptions,
+    ) -> Option<Vec<String>> {
+        let target = conversion
+            .target_audio_format
+            .clone()
+            .unwrap_or_else(|| source.clone());
+
+        if target == *source
+            && conversion.audio_codec.is_none()
+            && conversion.audio_bitrate.is_none()
+        {
+            return None;
+        }
+
+        let mut args = Self::base_args(input_file, options);
+
+        // Drop embedded cover art so it does not become a video stream
+        args.push("-vn".to_string());
+
+        let audio_codec = conversion
+            .audio_codec
+            .clone()
+            .unwrap_or_else(|| target.default_audio_codec().to_string());
+        args.push("-c:a".to_string());
+        args.push(audio_codec);
+        if let Some(bitrate) = &conversion.audio_bitrate {
+            args.push("-b:a".to_string());
+            args.push(bitrate.clone());
+        }
+
+        args.push(output_file.to_string_lossy().to_string());
+        Some(args)
+    }
+
+    /// Build ffmpeg arguments for an image conversion
+    fn build_image_args(
+        input_file: &Path,
+        output_file: &Path,
+        source: &ImageFormat,
+        options: &ProcessingOptions,
+        conversion: &ConversionOptions,
+    ) -> Option<Vec<String>> {
+        let target = conversion
+            .target_image_format
+            .clone()
+            .unwrap_or_else(|| source.clone());
+        let scale_filter = conversion.resize.as_ref().and_then(|r| r.to_scale_filter());
+
+        if target == *source && scale_filter.is_none() {
+            return None;
+        }
+
+        let mut args = Self::base_args(input_file, options);
+
+        if let Some(filter) = scale_filter {
+            args.push("-vf".to_string());
+            args.push(filter);
+        }
+
+        match target {
+            // Keep every frame so animated GIFs stay animated
+            ImageFormat::Gif => {}
+            ImageFormat::Jpg => {
+                args.push("-q:v".to_string());
+                args.push("2".to_string());
+                args.push("-frames:v".to_string());
+                args.push("1".to_string());
+            }
+            ImageFormat::Png
+            | ImageFormat::Bmp
+            | ImageFormat::Webp
+            | ImageFormat::Tiff
+            | ImageFormat::Heif => {
+                args.push("-frames:v".to_string());
+                args.push("1".to_string());
+            }
+        }
+
+        args.push(output_file.to_string_lossy().to_string());
+        Some(args)
+    }
+
+    /// Common leading ffmpeg arguments
+    fn base_args(input_file: &Path, options: &ProcessingOptions) -> Vec<String> {
+        vec![
+            "-hide_banner".to_string(),
+            "-loglevel".to_string(),
+            "error".to_string(),
+            if options.overwrite_existing {
+                "-y".to_string()
+            } else {
+                "-n".to_string()
+            },
+            "-i".to_string(),
+            input_file.to_string_lossy().to_string(),
+        ]
+    }
+
+    /// Run ffmpeg with the given arguments
+    fn run_ffmpeg(
+        args: &[String],
+        input_file: &Path,
+        deadline: &Deadline,
+    ) -> Result<(), ProcessError> {
+        let output = run_with_deadline(
+            Command::new("ffmpeg").args(args),
+            deadline,
+            &format!("ffmpeg conversion of {}", input_file.display()),
+        )?;
+
+        if !output.status.success() {
+            return Err(ProcessError::ProcessingFailed(format!(
+                "ffmpeg conversion failed for {}: {}",
+                input_file.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(())
+    }
 }
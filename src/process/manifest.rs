@@ -0,0 +1,296 @@
+//! Persistent job manifest for resumable batch processing
+//!
+//! The manifest is a JSON file in the output directory that records every
+//! input a job has seen (path, size, mtime, content hash and status). A rerun
+//! of the same job skips inputs that completed and have not changed since,
+//! retries inputs that failed, and rebuilds `ProcessingStats` for the whole job.
+
+use crate::process::stats::ProcessingStats;
+use crate::process::types::ProcessError;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// File name of the manifest inside the output directory
+pub const MANIFEST_FILE_NAME: &str = "job_manifest.json";
+
+/// Status of a single input in the manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum JobStatus {
+    Pending,
+    Completed,
+    Failed,
+}
+
+/// Manifest record for one input file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    /// Modification time in seconds since the Unix epoch
+    pub mtime: u64,
+    /// SHA-256 of the file content (only recorded for completed inputs)
+    pub hash: Option<String>,
+    pub status: JobStatus,
+    pub attempts: u32,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub updated_at: String,
+}
+
+/// Job manifest stored as `job_manifest.json` in the output directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobManifest {
+    pub created_at: String,
+    pub updated_at: String,
+    pub entries: BTreeMap<String, ManifestEntry>,
+    #[serde(skip)]
+    manifest_path: PathBuf,
+}
+
+impl JobManifest {
+    /// Load the manifest from `output_dir`, or start an empty one
+    pub fn load_or_create(output_dir: &Path) -> Result<Self, ProcessError> {
+        let manifest_path = output_dir.join(MANIFEST_FILE_NAME);
+
+        if manifest_path.exists() {
+            let data = fs::read_to_string(&manifest_path).map_err(|e| {
+                ProcessError::IoError(format!(
+                    "Failed to read job manifest {}: {}",
+                    manifest_path.display(),
+                    e
+                ))
+            })?;
+            let mut manifest: JobManifest = serde_json::from_str(&data).map_err(|e| {
+                ProcessError::ConfigurationError(format!(
+                    "Invalid job manifest {}: {}",
+                    manifest_path.display(),
+                    e
+                ))
+            })?;
+            manifest.manifest_path = manifest_path;
+            return Ok(manifest);
+        }
+
+        let now = Local::now().to_rfc3339();
+        Ok(Self {
+            created_at: now.clone(),
+            updated_at: now,
+            entries: BTreeMap::new(),
+            manifest_path,
+        })
+    }
+
+    /// Location of the manifest file
+    pub fn path(&self) -> &Path {
+        &self.manifest_path
+    }
+
+    /// Look up the record for an input
+    pub fn entry(&self, input: &Path) -> Option<&ManifestEntry> {
+        self.entries.get(&Self::key(input))
+    }
+
+    /// Move the record of `from` to `to`, e.g. from a temporary copy to the
+    /// file it was made from
+    ///
+    /// Attempts already recorded for `to` are carried over. Returns the moved
+    /// record, or `None` when `from` has none.
+    pub fn move_entry(&mut self, from: &Path, to: &Path) -> Option<&ManifestEntry> {
+        let mut entry = self.entries.remove(&Self::key(from))?;
+        let key = Self::key(to);
+        if let Some(previous) = self.entries.get(&key) {
+            entry.attempts += previous.attempts;
+        }
+        entry.path = key.clone();
+        self.entries.insert(key.clone(), entry);
+        self.entries.get(&key)
+    }
+
+    /// Decide whether an input must be (re)processed
+    ///
+    /// Completed inputs are skipped unless `overwrite_existing` is set or the
+    /// file changed. The content hash is only recomputed when size or mtime
+    /// differ from the record, so touching a file does not force a rerun.
+    pub fn needs_processing(
+        &mut self,
+        input: &Path,
+        overwrite_existing: bool,
+    ) -> Result<bool, ProcessError> {
+        if overwrite_existing {
+            return Ok(true);
+        }
+
+        let key = Self::key(input);
+        let Some(entry) = self.entries.get_mut(&key) else {
+            return Ok(true);
+        };
+        if entry.status != JobStatus::Completed {
+            return Ok(true);
+        }
+
+        let (size, mtime) = file_size_and_mtime(input)?;
+        if entry.size == size && entry.mtime == mtime {
+            return Ok(false);
+        }
+
+        let hash = hash_file(input)?;
+        if entry.size == size && entry.hash.as_deref() == Some(hash.as_str()) {
+            // Content unchanged, only the timestamp moved
+            entry.mtime = mtime;
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /// Record a successfully processed input
+    pub fn record_success(
+        &mut self,
+        input: &Path,
+        output: Option<&Path>,
+    ) -> Result<(), ProcessError> {
+        let (size, mtime) = file_size_and_mtime(input)?;
+        let hash = hash_file(input)?;
+        let entry = self.entry_mut(input, size, mtime);
+        entry.hash = Some(hash);
+        entry.status = JobStatus::Completed;
+        entry.attempts += 1;
+        entry.output = output.map(|p| p.to_string_lossy().to_string());
+        entry.error = None;
+        Ok(())
+    }
+
+    /// Record a failed input so the next run retries it
+    pub fn record_failure(&mut self, input: &Path, error: &str) {
+        let (size, mtime) = file_size_and_mtime(input).unwrap_or((0, 0));
+        let entry = self.entry_mut(input, size, mtime);
+        entry.hash = None;
+        entry.status = JobStatus::Failed;
+        entry.attempts += 1;
+        entry.error = Some(error.to_string());
+    }
+
+    /// Write the manifest atomically (temp file + rename)
+    pub fn save(&mut self) -> Result<(), ProcessError> {
+        self.updated_at = Local::now().to_rfc3339();
+
+        if let Some(parent) = self.manifest_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                ProcessError::IoError(format!("Failed to create manifest directory: {}", e))
+            })?;
+        }
+
+        let json = serde_json::to_string_pretty(self).map_err(|e| {
+            ProcessError::ProcessingFailed(format!("Failed to serialize job manifest: {}", e))
+        })?;
+        let temp_path = self.manifest_path.with_extension("json.tmp");
+        fs::write(&temp_path, json)
+            .map_err(|e| ProcessError::IoError(format!("Failed to write job manifest: {}", e)))?;
+        fs::rename(&temp_path, &self.manifest_path)
+            .map_err(|e| ProcessError::IoError(format!("Failed to replace job manifest: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Overwrite the counters of `stats` with totals for the whole job
+    ///
+    /// Timing fields and frame counts are left untouched.
+    pub fn apply_to_stats(&self, stats: &mut ProcessingStats) {
+        stats.files_processed = 0;
+        stats.files_failed = 0;
+        stats.total_size_processed = 0;
+        stats.errors.clear();
+
+        for entry in self.entries.values() {
+            match entry.status {
+                JobStatus::Completed => stats.add_processed_file(entry.size),
+                JobStatus::Failed => stats.add_failed_file(format!(
+                    "Failed to process {}: {}",
+                    entry.path,
+                    entry.error.as_deref().unwrap_or("unknown error")
+                )),
+                JobStatus::Pending => {}
+            }
+        }
+    }
+
+    /// Rebuild job-wide statistics from the manifest
+    pub fn to_stats(&self) -> ProcessingStats {
+        let mut stats = ProcessingStats::new();
+        self.apply_to_stats(&mut stats);
+        stats
+    }
+
+    fn entry_mut(&mut self, input: &Path, size: u64, mtime: u64) -> &mut ManifestEntry {
+        let key = Self::key(input);
+        let now = Local::now().to_rfc3339();
+        let entry = self
+            .entries
+            .entry(key.clone())
+            .or_insert_with(|| ManifestEntry {
+                path: key,
+                size,
+                mtime,
+                hash: None,
+                status: JobStatus::Pending,
+                attempts: 0,
+                output: None,
+                error: None,
+                updated_at: now.clone(),
+            });
+        entry.size = size;
+        entry.mtime = mtime;
+        entry.updated_at = now;
+        entry
+    }
+
+    fn key(input: &Path) -> String {
+        fs::canonicalize(input)
+            .unwrap_or_else(|_| input.to_path_buf())
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+fn file_size_and_mtime(path: &Path) -> Result<(u64, u64), ProcessError> {
+    let metadata = fs::metadata(path).map_err(|e| {
+        ProcessError::IoError(format!(
+            "Failed to get metadata for {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok((metadata.len(), mtime))
+}
+
+/// SHA-256 of a file's content as a hex string
+pub fn hash_file(path: &Path) -> Result<String, ProcessError> {
+    let mut file = File::open(path)
+        .map_err(|e| ProcessError::IoError(format!("Failed to open {}: {}", path.display(), e)))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| {
+            ProcessError::IoError(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -9,26 +9,36 @@ pub mod config;
 pub mod factories;
 pub mod handlers;
 pub mod hw_accel;
+pub mod manifest;
 pub mod processor;
+pub mod sniff;
 pub mod stats;
+pub mod timeout;
 pub mod types;
 pub mod validation;
 pub mod video;
+pub mod watch;
 pub mod workers;
 
 // Re-export commonly used items for convenience
 pub use config::{
-    ProcessConfig, ProcessingOptions, VideoExtractionConfig, generate_default_config,
+    ConversionOptions, ProcessConfig, ProcessingOptions, ResizeOptions, ResolutionPolicy,
+    ScanOptions, StreamProcessOptions, VideoEncodingConfig, VideoExtractionConfig, WatchOptions,
+    generate_default_config,
 };
 pub use factories::{
     create_processor, create_processor_with_mode, create_processor_with_options,
     create_video_processor,
 };
 pub use hw_accel::{HardwareAccelConfig, HardwareAcceleratedCapture};
+pub use manifest::{JobManifest, JobStatus, ManifestEntry};
 pub use processor::Processor;
+pub use sniff::{MediaKind, SniffedFormat};
 pub use stats::ProcessingStats;
+pub use timeout::{Deadline, TimeBudget};
 pub use types::{
     AudioFormat, DocumentFormat, FileFormat, ImageFormat, ProcessError, ProcessingMode,
-    VideoFormat, get_default_supported_formats,
+    StreamOutput, VideoFormat, get_default_supported_formats,
 };
 pub use video::VideoProcessor;
+pub use watch::{FolderWatcher, WatchEvent, WatchStatus};
@@ -6,15 +6,19 @@ use std::time::Duration;
 
 use crate::process::config::ProcessConfig;
 use crate::process::handlers::FileHandler;
+use crate::process::manifest::{JobManifest, MANIFEST_FILE_NAME};
 use crate::process::stats::ProcessingStats;
-use crate::process::types::{FileFormat, ProcessError, ProcessingMode};
+use crate::process::timeout::TimeBudget;
+use crate::process::types::{FileFormat, ProcessError, ProcessingMode, StreamOutput};
 use crate::process::validation::ProcessValidator;
 use crate::process::video::VideoProcessor;
+use crate::rtsp::{RTSPCapture, RtspUrl};
 
 /// Main processor struct for handling process operations
 pub struct Processor {
     config: ProcessConfig,
     stats: ProcessingStats,
+    budget: TimeBudget,
 }
 
 impl Processor {
@@ -41,6 +45,7 @@ impl Processor {
         Ok(Self {
             config,
             stats: ProcessingStats::new(),
+            budget: TimeBudget::unlimited(),
         })
     }
 
@@ -54,8 +59,9 @@ impl Processor {
             println!("Starting process from {} to {}", input_path, output_path);
         }
 
-        // Reset stats for new processing session
+        // Reset stats and start the job clock for new processing session
         self.stats = ProcessingStats::new();
+        self.budget = TimeBudget::from_options(&self.config.processing_options);
 
         // Check input existence & output validity
         ProcessValidator::validate_input(&self.config, input_path)?;
@@ -68,7 +74,9 @@ impl Processor {
 
         // Process based on mode
         match self.config.processing_mode {
-            ProcessingMode::SingleFile => self.process_single_file(input_path, output_path)?,
+            ProcessingMode::SingleFile => {
+                self.process_single_file(input_path, output_path)?;
+            }
             ProcessingMode::BatchFiles => self.process_batch_files(input_path, output_path)?,
             ProcessingMode::DirectoryProcess => self.process_directory(input_path, output_path)?,
             ProcessingMode::StreamProcess => self.process_stream_data(input_path, output_path)?,
@@ -81,6 +89,12 @@ impl Processor {
             println!("Process completed successfully");
             println!("Files processed: {}", self.stats.files_processed);
             println!("Files failed: {}", self.stats.files_failed);
+            if self.stats.files_skipped > 0 {
+                println!("Files skipped: {}", self.stats.files_skipped);
+            }
+            if self.stats.frames_processed > 0 {
+                println!("Frames processed: {}", self.stats.frames_processed);
+            }
             println!("Success rate: {:.2}%", self.stats.success_rate());
             println!("Processing time: {:?}", self.stats.processing_time);
         }
@@ -89,11 +103,14 @@ impl Processor {
     }
 
     /// Process a single file
+    ///
+    /// Returns `false` when the output already exists and `overwrite_existing`
+    /// is off; the input is then counted as skipped rather than failed.
     fn process_single_file(
         &mut self,
         input_path: &str,
         output_path: &str,
-    ) -> Result<(), ProcessError> {
+    ) -> Result<bool, ProcessError> {
         let input_file = Path::new(input_path);
         let output_file = Path::new(output_path);
 
@@ -112,20 +129,37 @@ impl Processor {
             }
         }
 
+        // Determine file format and the output it resolves to
+        let file_format = FileHandler::detect_file_format(input_file)?;
+        let output_file =
+            FileHandler::resolve_output_path(output_file, &file_format, &self.config.conversion);
+
+        // ffmpeg would refuse (-n) and a copy would overwrite, so decide here
+        if output_file.exists() && !self.config.processing_options.overwrite_existing {
+            if self.config.processing_options.verbose_logging {
+                println!("Skipping existing output: {:?}", output_file);
+            }
+            self.stats.add_skipped_file();
+            return Ok(false);
+        }
+
         // Backup original if requested
         if self.config.processing_options.backup_original {
             self.backup_file(input_file)?;
         }
 
-        // Determine file format and process accordingly
-        let file_format = FileHandler::detect_file_format(input_file)?;
+        // Per-file budget starts now, capped by what is left of the job
+        let deadline = self.budget.file_deadline();
+        deadline.check(&format!("Processing {}", input_file.display()))?;
 
         // Use Handler to process
         FileHandler::process_file_by_format(
             input_file,
-            output_file,
+            &output_file,
             &file_format,
             &self.config.processing_options,
+            &self.config.conversion,
+            &deadline,
         )?;
 
         // Update stats
@@ -134,7 +168,7 @@ impl Processor {
             .len();
         self.stats.add_processed_file(file_size);
 
-        Ok(())
+        Ok(true)
     }
 
     /// Process multiple files in batch
@@ -161,6 +195,8 @@ impl Processor {
             let path = entry.path();
 
             if path.is_file() {
+                self.budget.job_deadline().check("Batch job")?;
+
                 let file_name = path.file_name().ok_or_else(|| {
                     ProcessError::ProcessingFailed("Invalid file name".to_string())
                 })?;
@@ -170,7 +206,8 @@ impl Processor {
                     path.to_str().unwrap_or(""),
                     output_file.to_str().unwrap_or(""),
                 ) {
-                    Ok(_) => {
+                    Ok(false) => {}
+                    Ok(true) => {
                         if self.config.processing_options.verbose_logging {
                             println!("Successfully processed: {:?}", path);
                         }
@@ -190,6 +227,9 @@ impl Processor {
     }
 
     /// Process entire directory recursively
+    ///
+    /// Progress is tracked in a job manifest in the output directory, so an
+    /// interrupted run can be resumed. Stats cover the whole job afterwards.
     fn process_directory(
         &mut self,
         input_path: &str,
@@ -198,8 +238,21 @@ impl Processor {
         let input_dir = Path::new(input_path);
         let output_dir = Path::new(output_path);
 
-        self.process_directory_recursive(input_dir, output_dir, input_dir)?;
-        Ok(())
+        let mut manifest = JobManifest::load_or_create(output_dir)?;
+        if self.config.processing_options.verbose_logging && !manifest.entries.is_empty() {
+            println!(
+                "Resuming job from {} ({} recorded inputs)",
+                manifest.path().display(),
+                manifest.entries.len()
+            );
+        }
+
+        let result =
+            self.process_directory_recursive(input_dir, output_dir, input_dir, &mut manifest);
+
+        // Also on job timeout: unprocessed inputs stay out of the manifest for the next run
+        manifest.apply_to_stats(&mut self.stats);
+        result
     }
 
     /// Recursive directory processing helper
@@ -208,6 +261,7 @@ impl Processor {
         current_dir: &Path,
         output_base: &Path,
         input_base: &Path,
+        manifest: &mut JobManifest,
     ) -> Result<(), ProcessError> {
         let entries = fs::read_dir(current_dir)
             .map_err(|e| ProcessError::IoError(format!("Failed to read directory: {}", e)))?;
@@ -219,8 +273,25 @@ impl Processor {
 
             if path.is_dir() {
                 // Recursively process subdirectories
-                self.process_directory_recursive(&path, output_base, input_base)?;
+                self.process_directory_recursive(&path, output_base, input_base, manifest)?;
             } else if path.is_file() {
+                // Never treat our own manifest as an input
+                if path == manifest.path() || path.file_name() == Some(MANIFEST_FILE_NAME.as_ref())
+                {
+                    continue;
+                }
+
+                self.budget.job_deadline().check("Directory job")?;
+
+                if !manifest
+                    .needs_processing(&path, self.config.processing_options.overwrite_existing)?
+                {
+                    if self.config.processing_options.verbose_logging {
+                        println!("Skipping completed: {:?}", path);
+                    }
+                    continue;
+                }
+
                 // Calculate relative path and create corresponding output path
                 let relative_path = path.strip_prefix(input_base).map_err(|e| {
                     ProcessError::ProcessingFailed(format!(
@@ -242,44 +313,104 @@ impl Processor {
                     path.to_str().unwrap_or(""),
                     output_file.to_str().unwrap_or(""),
                 ) {
-                    Ok(_) => {
+                    // Left out of the manifest, so it is checked again next run
+                    Ok(false) => {}
+                    Ok(true) => {
+                        manifest.record_success(&path, Some(&output_file))?;
                         if self.config.processing_options.verbose_logging {
                             println!("Successfully processed: {:?}", path);
                         }
                     }
                     Err(e) => {
                         let error_msg = format!("Failed to process {:?}: {}", path, e);
+                        manifest.record_failure(&path, &e.to_string());
                         self.stats.add_failed_file(error_msg.clone());
                         if self.config.processing_options.verbose_logging {
                             eprintln!("{}", error_msg);
                         }
                     }
                 }
+                manifest.save()?;
             }
         }
 
         Ok(())
     }
 
-    /// Process stream data (placeholder for stream processing)
+    /// Capture a bounded portion of a stream into segments or frames
+    ///
+    /// Uses `RTSPCapture` for the actual capture, so RTSP, HTTP and file URLs
+    /// (as well as plain local video paths) are all accepted as input.
     fn process_stream_data(
         &mut self,
         input_path: &str,
         output_path: &str,
     ) -> Result<(), ProcessError> {
+        let options = self.config.stream.clone();
+
+        // Stream URLs may carry credentials; only the redacted form is logged
+        let source = RtspUrl::parse(input_path);
+
         if self.config.processing_options.verbose_logging {
             println!(
-                "Processing stream data from {} to {}",
-                input_path, output_path
+                "Processing stream data from {} to {} ({:?})",
+                source, output_path, options.output
             );
         }
 
-        // Placeholder implementation for stream processing
-        std::thread::sleep(Duration::from_millis(100));
-
-        self.stats.add_processed_file(0); // Stream data doesn't have traditional file size
-
-        Ok(())
+        let capture = RTSPCapture::new(
+            source.clone(),
+            output_path.to_string(),
+            false,
+            options.segment_duration_seconds,
+            false,
+            30.0,
+            None,
+            false,
+        )
+        .map_err(|e| ProcessError::ProcessingFailed(format!("Failed to create capture: {}", e)))?;
+        // A stalled source is killed when the per-file budget runs out
+        let deadline = self.budget.file_deadline();
+        deadline.check(&format!("Capturing {}", source))?;
+        let mut capture = capture
+            .with_limits(
+                options.max_duration_seconds.map(Duration::from_secs),
+                options.max_frames,
+            )
+            .with_deadline(deadline);
+
+        let result = match options.output {
+            StreamOutput::Segments => capture.record_segments(),
+            StreamOutput::Frames => capture.capture_frames(options.frame_interval),
+        };
+
+        match result {
+            Ok(summary) => {
+                self.stats.add_processed_file(summary.bytes_written);
+                self.stats.add_frames(summary.frames);
+                if self.config.processing_options.verbose_logging {
+                    println!(
+                        "Captured {} frames into {} file(s)",
+                        summary.frames,
+                        summary.files.len()
+                    );
+                }
+                Ok(())
+            }
+            Err(e) => {
+                let error_msg = format!(
+                    "Failed to process stream {}: {}",
+                    source,
+                    source.scrub(&e.to_string())
+                );
+                self.stats.add_failed_file(error_msg.clone());
+                if deadline.is_expired() {
+                    Err(ProcessError::Timeout(error_msg))
+                } else {
+                    Err(ProcessError::ProcessingFailed(error_msg))
+                }
+            }
+        }
     }
 
     /// Backup original file
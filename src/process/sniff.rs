@@ -0,0 +1,290 @@
+//! Content-based media type detection
+//!
+//! Reads the first bytes of a file and matches them against known container
+//! and image signatures. The file extension is only used when the content is
+//! not recognized, and a warning is printed when the two disagree.
+
+use crate::process::types::{AudioFormat, FileFormat, ImageFormat, VideoFormat};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Number of leading bytes read for sniffing
+///
+/// Large enough to see three 188-byte MPEG-TS packets (or 192-byte M2TS packets).
+const SNIFF_LEN: usize = 600;
+
+/// MPEG-TS sync byte
+const TS_SYNC: u8 = 0x47;
+
+/// Start of a Theora identification header (first packet of a Theora stream)
+const THEORA_HEADER: &[u8] = b"\x80theora";
+
+/// Media category of a detected format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Video,
+    Audio,
+    Image,
+}
+
+/// Format recognized from file content (or extension)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SniffedFormat {
+    Mp4,
+    Mov,
+    Mkv,
+    Webm,
+    Avi,
+    MpegTs,
+    /// Ogg with a Theora video stream
+    Ogv,
+    Jpeg,
+    Png,
+    Gif,
+    Bmp,
+    Webp,
+    Tiff,
+    /// HEIF family still images (HEIC, AVIF)
+    Heif,
+    Wav,
+    Flac,
+    Mp3,
+    /// Ogg without a Theora stream
+    Ogg,
+    /// Audio-only MP4 (`M4A `/`M4B ` brands)
+    M4a,
+}
+
+impl SniffedFormat {
+    /// Recognize a format from the leading bytes of a file
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+            return Self::from_brand(&bytes[8..12]);
+        }
+        if bytes.len() >= 8 && matches!(&bytes[4..8], b"moov" | b"mdat" | b"wide" | b"free") {
+            // Older QuickTime files start with an atom other than ftyp
+            return Some(SniffedFormat::Mov);
+        }
+        if bytes.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+            // EBML header; the DocType tells WebM and Matroska apart
+            let header = &bytes[..bytes.len().min(64)];
+            return Some(if header.windows(4).any(|w| w == b"webm") {
+                SniffedFormat::Webm
+            } else {
+                SniffedFormat::Mkv
+            });
+        }
+        if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" {
+            return match &bytes[8..12] {
+                b"AVI " => Some(SniffedFormat::Avi),
+                b"WAVE" => Some(SniffedFormat::Wav),
+                b"WEBP" => Some(SniffedFormat::Webp),
+                _ => None,
+            };
+        }
+        if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            return Some(SniffedFormat::Jpeg);
+        }
+        if bytes.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+            return Some(SniffedFormat::Png);
+        }
+        if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            return Some(SniffedFormat::Gif);
+        }
+        if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+            return Some(SniffedFormat::Tiff);
+        }
+        if bytes.starts_with(b"fLaC") {
+            return Some(SniffedFormat::Flac);
+        }
+        if bytes.starts_with(b"OggS") {
+            // Every stream's first page comes before any data, so a Theora
+            // header near the start means the file carries video
+            let has_theora = bytes
+                .windows(THEORA_HEADER.len())
+                .any(|w| w == THEORA_HEADER);
+            return Some(if has_theora {
+                SniffedFormat::Ogv
+            } else {
+                SniffedFormat::Ogg
+            });
+        }
+        if Self::is_mpeg_ts(bytes, 188, 0) || Self::is_mpeg_ts(bytes, 192, 4) {
+            return Some(SniffedFormat::MpegTs);
+        }
+        if bytes.starts_with(b"ID3") || Self::is_mp3_frame(bytes) {
+            return Some(SniffedFormat::Mp3);
+        }
+        if bytes.len() >= 14 && bytes.starts_with(b"BM") {
+            return Some(SniffedFormat::Bmp);
+        }
+        None
+    }
+
+    /// Map a file extension (any case) to a format
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "mp4" | "m4v" => Some(SniffedFormat::Mp4),
+            "mov" => Some(SniffedFormat::Mov),
+            "mkv" => Some(SniffedFormat::Mkv),
+            "webm" => Some(SniffedFormat::Webm),
+            "avi" => Some(SniffedFormat::Avi),
+            "ts" | "m2ts" | "mts" => Some(SniffedFormat::MpegTs),
+            "jpg" | "jpeg" => Some(SniffedFormat::Jpeg),
+            "png" => Some(SniffedFormat::Png),
+            "gif" => Some(SniffedFormat::Gif),
+            "bmp" => Some(SniffedFormat::Bmp),
+            "webp" => Some(SniffedFormat::Webp),
+            "tif" | "tiff" => Some(SniffedFormat::Tiff),
+            "wav" => Some(SniffedFormat::Wav),
+            "flac" => Some(SniffedFormat::Flac),
+            "heic" | "heif" | "avif" => Some(SniffedFormat::Heif),
+            "mp3" => Some(SniffedFormat::Mp3),
+            "ogv" => Some(SniffedFormat::Ogv),
+            "ogg" | "oga" => Some(SniffedFormat::Ogg),
+            "m4a" | "m4b" => Some(SniffedFormat::M4a),
+            _ => None,
+        }
+    }
+
+    /// Map the major brand of an ISO base media (`ftyp`) file to a format
+    ///
+    /// Unknown brands are not guessed at.
+    fn from_brand(brand: &[u8]) -> Option<Self> {
+        match brand {
+            b"qt  " => Some(SniffedFormat::Mov),
+            b"isom" | b"mp41" | b"mp42" | b"mp4x" | b"avc1" | b"M4V " | b"M4VH" | b"M4VP"
+            | b"dash" | b"f4v " | b"MSNV" => Some(SniffedFormat::Mp4),
+            _ if brand.starts_with(b"iso")
+                || brand.starts_with(b"3gp")
+                || brand.starts_with(b"3g2") =>
+            {
+                Some(SniffedFormat::Mp4)
+            }
+            b"M4A " | b"M4B " | b"M4P " => Some(SniffedFormat::M4a),
+            b"heic" | b"heix" | b"hevc" | b"hevx" | b"mif1" | b"msf1" | b"avif" | b"avis" => {
+                Some(SniffedFormat::Heif)
+            }
+            _ => None,
+        }
+    }
+
+    /// Read the start of a file and recognize its format
+    pub fn from_file(path: &Path) -> Option<Self> {
+        let file = File::open(path).ok()?;
+        let mut buffer = Vec::with_capacity(SNIFF_LEN);
+        file.take(SNIFF_LEN as u64).read_to_end(&mut buffer).ok()?;
+        Self::from_bytes(&buffer)
+    }
+
+    /// Detect the format of a file, falling back to its extension
+    ///
+    /// Content wins when both are known; a warning is printed if the
+    /// extension names a different format. An `.ogv` file is video even when
+    /// its Theora header is beyond the sniffed bytes.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let by_extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_extension);
+
+        match (Self::from_file(path), by_extension) {
+            (Some(SniffedFormat::Ogg), Some(SniffedFormat::Ogv)) => Some(SniffedFormat::Ogv),
+            (Some(content), Some(extension)) => {
+                if !content.same_family(extension) {
+                    eprintln!(
+                        "⚠️ {} has a {:?} extension but its content is {:?}; using {:?}",
+                        path.display(),
+                        extension,
+                        content,
+                        content
+                    );
+                }
+                Some(content)
+            }
+            (Some(content), None) => Some(content),
+            (None, extension) => extension,
+        }
+    }
+
+    /// Whether this is a video, audio or image format
+    pub fn media_kind(&self) -> MediaKind {
+        match self {
+            SniffedFormat::Mp4
+            | SniffedFormat::Mov
+            | SniffedFormat::Mkv
+            | SniffedFormat::Webm
+            | SniffedFormat::Avi
+            | SniffedFormat::MpegTs
+            | SniffedFormat::Ogv => MediaKind::Video,
+            SniffedFormat::Wav
+            | SniffedFormat::Flac
+            | SniffedFormat::Mp3
+            | SniffedFormat::Ogg
+            | SniffedFormat::M4a => MediaKind::Audio,
+            SniffedFormat::Jpeg
+            | SniffedFormat::Png
+            | SniffedFormat::Gif
+            | SniffedFormat::Bmp
+            | SniffedFormat::Webp
+            | SniffedFormat::Tiff
+            | SniffedFormat::Heif => MediaKind::Image,
+        }
+    }
+
+    /// Corresponding process `FileFormat`
+    pub fn to_file_format(self) -> FileFormat {
+        match self {
+            SniffedFormat::Mp4 => FileFormat::Video(VideoFormat::Mp4),
+            SniffedFormat::Mov => FileFormat::Video(VideoFormat::Mov),
+            SniffedFormat::Mkv => FileFormat::Video(VideoFormat::Mkv),
+            SniffedFormat::Webm => FileFormat::Video(VideoFormat::Webm),
+            SniffedFormat::Avi => FileFormat::Video(VideoFormat::Avi),
+            SniffedFormat::MpegTs => FileFormat::Video(VideoFormat::Ts),
+            SniffedFormat::Ogv => FileFormat::Video(VideoFormat::Ogv),
+            SniffedFormat::Jpeg => FileFormat::Image(ImageFormat::Jpg),
+            SniffedFormat::Png => FileFormat::Image(ImageFormat::Png),
+            SniffedFormat::Gif => FileFormat::Image(ImageFormat::Gif),
+            SniffedFormat::Bmp => FileFormat::Image(ImageFormat::Bmp),
+            SniffedFormat::Webp => FileFormat::Image(ImageFormat::Webp),
+            SniffedFormat::Tiff => FileFormat::Image(ImageFormat::Tiff),
+            SniffedFormat::Heif => FileFormat::Image(ImageFormat::Heif),
+            SniffedFormat::Wav => FileFormat::Audio(AudioFormat::Wav),
+            SniffedFormat::Flac => FileFormat::Audio(AudioFormat::Flac),
+            SniffedFormat::Mp3 => FileFormat::Audio(AudioFormat::Mp3),
+            SniffedFormat::Ogg => FileFormat::Audio(AudioFormat::Ogg),
+            SniffedFormat::M4a => FileFormat::Audio(AudioFormat::Aac),
+        }
+    }
+
+    /// Whether the extension alone can't tell video from audio (Ogg may
+    /// carry Theora), so the content has to be read
+    pub fn is_ambiguous(&self) -> bool {
+        matches!(self, SniffedFormat::Ogg)
+    }
+
+    /// MP4/MOV and Matroska/WebM share a container layout, so a swapped
+    /// extension within a pair is not worth a warning
+    fn same_family(self, other: Self) -> bool {
+        use SniffedFormat::*;
+        self == other
+            || matches!(
+                (self, other),
+                (Mp4, Mov) | (Mov, Mp4) | (Mkv, Webm) | (Webm, Mkv) | (Ogg, Ogv) | (Ogv, Ogg)
+            )
+    }
+
+    /// Sync byte at the start of at least three consecutive packets
+    fn is_mpeg_ts(bytes: &[u8], packet_size: usize, offset: usize) -> bool {
+        (0..3).all(|i| bytes.get(offset + i * packet_size) == Some(&TS_SYNC))
+    }
+
+    /// MPEG audio frame header (layer bits must be non-zero, unlike AAC ADTS)
+    fn is_mp3_frame(bytes: &[u8]) -> bool {
+        bytes.len() >= 2
+            && bytes[0] == 0xFF
+            && bytes[1] & 0xE0 == 0xE0
+            && (bytes[1] >> 1) & 0x03 != 0
+    }
+}
@@ -5,7 +5,10 @@ use std::time::{Duration, Instant};
 pub struct ProcessingStats {
     pub files_processed: u64,
     pub files_failed: u64,
+    /// Inputs left alone because their output already existed
+    pub files_skipped: u64,
     pub total_size_processed: u64,
+    pub frames_processed: u64,
     pub processing_time: Duration,
     pub start_time: Instant,
     pub errors: Vec<String>,
@@ -16,7 +19,9 @@ impl ProcessingStats {
         Self {
             files_processed: 0,
             files_failed: 0,
+            files_skipped: 0,
             total_size_processed: 0,
+            frames_processed: 0,
             processing_time: Duration::new(0, 0),
             start_time: Instant::now(),
             errors: Vec::new(),
@@ -28,11 +33,19 @@ impl ProcessingStats {
         self.total_size_processed += file_size;
     }
 
+    pub fn add_frames(&mut self, count: u64) {
+        self.frames_processed += count;
+    }
+
     pub fn add_failed_file(&mut self, error: String) {
         self.files_failed += 1;
         self.errors.push(error);
     }
 
+    pub fn add_skipped_file(&mut self) {
+        self.files_skipped += 1;
+    }
+
     pub fn finalize(&mut self) {
         self.processing_time = self.start_time.elapsed();
     }
@@ -0,0 +1,200 @@
+//! Time budgets for per-file and per-job processing
+//!
+//! `ProcessingOptions::timeout_seconds` bounds each input file and
+//! `ProcessingOptions::job_timeout_seconds` bounds the whole run. Spawned
+//! `ffmpeg` children are killed when the budget runs out; OpenCV loops check
+//! the deadline between frames.
+
+use crate::process::config::ProcessingOptions;
+use crate::process::types::ProcessError;
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Poll interval while waiting on a child process
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A point in time after which work must stop (or no limit at all)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Deadline {
+    expires_at: Option<Instant>,
+}
+
+impl Deadline {
+    /// A deadline that never expires
+    pub fn unlimited() -> Self {
+        Self { expires_at: None }
+    }
+
+    /// A deadline `budget` from now
+    pub fn after(budget: Duration) -> Self {
+        Self {
+            expires_at: Some(Instant::now() + budget),
+        }
+    }
+
+    /// A deadline `seconds` from now, or unlimited for `None`
+    pub fn from_seconds(seconds: Option<u64>) -> Self {
+        match seconds {
+            Some(secs) => Self::after(Duration::from_secs(secs)),
+            None => Self::unlimited(),
+        }
+    }
+
+    /// The earlier of two deadlines
+    pub fn min(self, other: Deadline) -> Self {
+        match (self.expires_at, other.expires_at) {
+            (Some(a), Some(b)) => Self {
+                expires_at: Some(a.min(b)),
+            },
+            (Some(a), None) | (None, Some(a)) => Self {
+                expires_at: Some(a),
+            },
+            (None, None) => Self::unlimited(),
+        }
+    }
+
+    /// Time left before the deadline, `None` when unlimited
+    pub fn remaining(&self) -> Option<Duration> {
+        self.expires_at
+            .map(|at| at.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| Instant::now() >= at)
+    }
+
+    /// Return `ProcessError::Timeout` if the deadline has passed
+    pub fn check(&self, context: &str) -> Result<(), ProcessError> {
+        if self.is_expired() {
+            return Err(ProcessError::Timeout(format!(
+                "{} exceeded its time budget",
+                context
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Per-file and per-job budgets for one processing run
+///
+/// The job clock starts when the budget is created; each call to
+/// `file_deadline` starts a fresh per-file clock capped by the job deadline.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeBudget {
+    per_file: Option<Duration>,
+    job: Deadline,
+}
+
+impl TimeBudget {
+    /// Start the job clock using the limits in `options`
+    pub fn from_options(options: &ProcessingOptions) -> Self {
+        Self {
+            per_file: options.timeout_seconds.map(Duration::from_secs),
+            job: Deadline::from_seconds(options.job_timeout_seconds),
+        }
+    }
+
+    /// No limits
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Deadline for the whole job
+    pub fn job_deadline(&self) -> Deadline {
+        self.job
+    }
+
+    /// Deadline for a file starting now
+    pub fn file_deadline(&self) -> Deadline {
+        match self.per_file {
+            Some(budget) => Deadline::after(budget).min(self.job),
+            None => self.job,
+        }
+    }
+}
+
+/// Run a command to completion, killing it if `deadline` passes first
+///
+/// Behaves like `Command::output()`: stdout and stderr are captured and
+/// returned. On timeout the child is killed and reaped, and
+/// `ProcessError::Timeout` is returned.
+pub fn run_with_deadline(
+    cmd: &mut Command,
+    deadline: &Deadline,
+    context: &str,
+) -> Result<Output, ProcessError> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            ProcessError::ProcessingFailed(format!("Failed to execute {}: {}", context, e))
+        })?;
+
+    let stdout_reader = child.stdout.take().map(spawn_pipe_reader);
+    let stderr_reader = child.stderr.take().map(spawn_pipe_reader);
+
+    let status = wait_with_deadline(&mut child, deadline, context)?;
+
+    Ok(Output {
+        status,
+        stdout: join_pipe_reader(stdout_reader),
+        stderr: join_pipe_reader(stderr_reader),
+    })
+}
+
+/// Wait for a spawned child, killing and reaping it if `deadline` passes first
+pub fn wait_with_deadline(
+    child: &mut Child,
+    deadline: &Deadline,
+    context: &str,
+) -> Result<ExitStatus, ProcessError> {
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok(status),
+            Ok(None) => {
+                if deadline.is_expired() {
+                    kill_and_reap(child);
+                    eprintln!("⚠️ Killed {} after it exceeded its time budget", context);
+                    return Err(ProcessError::Timeout(format!(
+                        "{} exceeded its time budget",
+                        context
+                    )));
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+            Err(e) => {
+                kill_and_reap(child);
+                return Err(ProcessError::ProcessingFailed(format!(
+                    "Failed to wait for {}: {}",
+                    context, e
+                )));
+            }
+        }
+    }
+}
+
+/// Kill a child process and reap it so it does not linger as a zombie
+pub fn kill_and_reap(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Drain a child pipe on a background thread
+pub fn spawn_pipe_reader<R: Read + Send + 'static>(mut pipe: R) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = pipe.read_to_end(&mut buffer);
+        buffer
+    })
+}
+
+/// Collect what a pipe reader thread has read
+pub fn join_pipe_reader(reader: Option<JoinHandle<Vec<u8>>>) -> Vec<u8> {
+    reader
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default()
+}
@@ -10,6 +10,8 @@ pub enum ProcessError {
     IoError(String),
     ConfigurationError(String),
     ValidationError(String),
+    /// A file or job exceeded its `timeout_seconds` budget
+    Timeout(String),
 }
 
 impl fmt::Display for ProcessError {
@@ -20,6 +22,7 @@ impl fmt::Display for ProcessError {
             ProcessError::IoError(msg) => write!(f, "IO error: {}", msg),
             ProcessError::ConfigurationError(msg) => write!(f, "Configuration error: {}", msg),
             ProcessError::ValidationError(msg) => write!(f, "Validation error: {}", msg),
+            ProcessError::Timeout(msg) => write!(f, "Timed out: {}", msg),
         }
     }
 }
@@ -36,6 +39,16 @@ pub enum ProcessingMode {
     StreamProcess,
 }
 
+/// Output produced by `ProcessingMode::StreamProcess`
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum StreamOutput {
+    /// Segmented MP4 files (stream copy, no re-encoding)
+    #[default]
+    Segments,
+    /// Individual JPEG frames
+    Frames,
+}
+
 /// File format types supported by the processor
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FileFormat {
@@ -59,6 +72,43 @@ pub enum VideoFormat {
     Mkv,
     Mov,
     Webm,
+    Ts,
+    Ogv,
+}
+
+impl VideoFormat {
+    /// File extension for this container
+    pub fn extension(&self) -> &'static str {
+        match self {
+            VideoFormat::Mp4 => "mp4",
+            VideoFormat::Avi => "avi",
+            VideoFormat::Mkv => "mkv",
+            VideoFormat::Mov => "mov",
+            VideoFormat::Webm => "webm",
+            VideoFormat::Ts => "ts",
+            VideoFormat::Ogv => "ogv",
+        }
+    }
+
+    /// Default ffmpeg video encoder for this container
+    pub fn default_video_codec(&self) -> &'static str {
+        match self {
+            VideoFormat::Mp4 | VideoFormat::Mkv | VideoFormat::Mov | VideoFormat::Ts => "libx264",
+            VideoFormat::Avi => "mpeg4",
+            VideoFormat::Webm => "libvpx-vp9",
+            VideoFormat::Ogv => "libtheora",
+        }
+    }
+
+    /// Default ffmpeg audio encoder for this container
+    pub fn default_audio_codec(&self) -> &'static str {
+        match self {
+            VideoFormat::Mp4 | VideoFormat::Mkv | VideoFormat::Mov | VideoFormat::Ts => "aac",
+            VideoFormat::Avi => "libmp3lame",
+            VideoFormat::Webm => "libopus",
+            VideoFormat::Ogv => "libvorbis",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -67,6 +117,31 @@ pub enum AudioFormat {
     Wav,
     Flac,
     Aac,
+    Ogg,
+}
+
+impl AudioFormat {
+    /// File extension for this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Aac => "aac",
+            AudioFormat::Ogg => "ogg",
+        }
+    }
+
+    /// Default ffmpeg audio encoder for this format
+    pub fn default_audio_codec(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "libmp3lame",
+            AudioFormat::Wav => "pcm_s16le",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Aac => "aac",
+            AudioFormat::Ogg => "libvorbis",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -75,6 +150,24 @@ pub enum ImageFormat {
     Png,
     Gif,
     Bmp,
+    Webp,
+    Tiff,
+    Heif,
+}
+
+impl ImageFormat {
+    /// File extension for this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpg => "jpg",
+            ImageFormat::Png => "png",
+            ImageFormat::Gif => "gif",
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Tiff => "tiff",
+            ImageFormat::Heif => "heic",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -94,16 +187,22 @@ pub fn get_default_supported_formats() -> Vec<FileFormat> {
         FileFormat::Video(VideoFormat::Mkv),
         FileFormat::Video(VideoFormat::Mov),
         FileFormat::Video(VideoFormat::Webm),
+        FileFormat::Video(VideoFormat::Ts),
+        FileFormat::Video(VideoFormat::Ogv),
         // Audio formats
         FileFormat::Audio(AudioFormat::Mp3),
         FileFormat::Audio(AudioFormat::Wav),
         FileFormat::Audio(AudioFormat::Flac),
         FileFormat::Audio(AudioFormat::Aac),
+        FileFormat::Audio(AudioFormat::Ogg),
         // Image formats
         FileFormat::Image(ImageFormat::Jpg),
         FileFormat::Image(ImageFormat::Png),
         FileFormat::Image(ImageFormat::Gif),
         FileFormat::Image(ImageFormat::Bmp),
+        FileFormat::Image(ImageFormat::Webp),
+        FileFormat::Image(ImageFormat::Tiff),
+        FileFormat::Image(ImageFormat::Heif),
         // Document formats
         FileFormat::Document(DocumentFormat::Txt),
         FileFormat::Document(DocumentFormat::Json),
@@ -32,6 +32,19 @@ impl ProcessValidator {
                     ));
                 }
             }
+            ProcessingMode::StreamProcess => {
+                let stream = &config.stream;
+                if stream.max_duration_seconds.is_none() && stream.max_frames.is_none() {
+                    return Err(ProcessError::ConfigurationError(
+                        "Stream processing requires max_duration_seconds or max_frames".to_string(),
+                    ));
+                }
+                if stream.segment_duration_seconds == 0 {
+                    return Err(ProcessError::ConfigurationError(
+                        "Stream segment duration must be greater than zero".to_string(),
+                    ));
+                }
+            }
             _ => {} // Other modes are flexible
         }
         Ok(())
@@ -49,6 +62,11 @@ impl ProcessValidator {
             ));
         }
 
+        // Stream URLs cannot be checked on the filesystem
+        if config.processing_mode == ProcessingMode::StreamProcess && input_path.contains("://") {
+            return Ok(());
+        }
+
         let path = Path::new(input_path);
         if !path.exists() {
             return Err(ProcessError::InvalidInput(format!(
@@ -7,19 +7,23 @@ use opencv::{
 };
 use rayon::prelude::*;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Instant;
 
 use super::hw_accel::HardwareAcceleratedCapture;
-use crate::process::config::VideoExtractionConfig;
+use crate::process::config::{ProcessingOptions, VideoExtractionConfig};
+use crate::process::manifest::JobManifest;
 use crate::process::stats::ProcessingStats;
+use crate::process::timeout::TimeBudget;
 use crate::process::types::ProcessError;
 
 // Import new worker modules
 use super::workers::creator::VideoCreator;
+use super::workers::encoder::PipeEncoder;
 use super::workers::extractors::FrameExtractor;
+use super::workers::resolution::{FrameAdapter, FramePlan, ResolutionReport};
 use super::workers::scanner::VideoScanner;
 
 /// Video processing functionality
@@ -38,11 +42,11 @@ impl VideoProcessor {
         })?;
 
         // Config parsing logic
-        let video_config =
+        let (video_config, processing_options) =
             match serde_json::from_str::<crate::process::config::ProcessConfig>(&config_data) {
                 Ok(process_config) => {
                     if let Some(vc) = process_config.video_config {
-                        vc
+                        (vc, process_config.processing_options)
                     } else {
                         return Err(ProcessError::ConfigurationError(
                         "Config file is a valid ProcessConfig but missing 'video_config' field."
@@ -53,45 +57,89 @@ impl VideoProcessor {
                 Err(_) => {
                     // Fallback: Try to parse as VideoExtractionConfig directly
                     let deserializer = &mut serde_json::Deserializer::from_str(&config_data);
-                    serde_path_to_error::deserialize(deserializer).map_err(|e| {
+                    let vc = serde_path_to_error::deserialize(deserializer).map_err(|e| {
                         ProcessError::ConfigurationError(format!(
                             "Error parsing config.json at '{}': {}",
                             e.path(),
                             e
                         ))
-                    })?
+                    })?;
+                    (vc, ProcessingOptions::default())
                 }
             };
 
+        Self::run_with_config(video_config, processing_options, stats, start_time)
+    }
+
+    /// Run video extraction with an already parsed configuration
+    ///
+    /// `start_time` is when the job began (used for `stats.processing_time`).
+    pub fn run_with_config(
+        video_config: VideoExtractionConfig,
+        processing_options: ProcessingOptions,
+        stats: &mut ProcessingStats,
+        start_time: Instant,
+    ) -> Result<(), ProcessError> {
         let config = Arc::new(video_config);
+        let budget = TimeBudget::from_options(&processing_options);
         let temp_dirs_created = Arc::new(Mutex::new(Vec::<PathBuf>::new()));
 
         // Use Scanner Worker
         let video_files_by_dir = VideoScanner::scan(&config)?;
 
+        // Resume support: skip directories whose videos all completed unchanged
+        let mut manifest = JobManifest::load_or_create(Path::new(&config.output_directory))?;
+        let mut pending_dirs = Vec::new();
+        for (dir_path, video_list) in video_files_by_dir {
+            let mut needs_run = false;
+            for video in &video_list {
+                if manifest.needs_processing(video, processing_options.overwrite_existing)? {
+                    needs_run = true;
+                    break;
+                }
+            }
+            if needs_run {
+                pending_dirs.push((dir_path, video_list));
+            } else {
+                println!("Skipping completed directory: {}", dir_path);
+            }
+        }
+        let manifest = Arc::new(Mutex::new(manifest));
+
         let processing_mode = config.processing_mode.as_deref().unwrap_or("parallel");
 
-        match processing_mode {
+        let manifest_errors: Vec<String> = match processing_mode {
             "sequential" => {
                 println!("Running in sequential mode.");
-                for (dir_path, video_list) in video_files_by_dir {
-                    if let Err(e) = Self::process_video_directory(
+                let mut manifest_errors = Vec::new();
+                for (dir_path, video_list) in pending_dirs {
+                    let result = Self::process_video_directory(
                         dir_path.clone(),
-                        video_list,
+                        video_list.clone(),
                         Arc::clone(&config),
                         Arc::clone(&temp_dirs_created),
-                    ) {
+                        budget,
+                    );
+                    if let Err(e) = &result {
                         eprintln!("Error processing directory {}: {}", dir_path, e);
-                        stats.add_failed_file(format!("Directory {}: {}", dir_path, e));
+                    }
+                    if let Err(e) =
+                        Self::record_directory_result(&manifest, &dir_path, &video_list, &result)
+                    {
+                        eprintln!("Warning: Failed to update job manifest: {}", e);
+                        manifest_errors.push(format!("Directory {}: {}", dir_path, e));
                     }
                 }
+                manifest_errors
             }
             "parallel" | _ => {
                 println!("Running in parallel mode.");
                 let num_threads = config.num_threads.unwrap_or_else(num_cpus::get);
-                rayon::ThreadPoolBuilder::new()
+                // A pool per job: the global pool can only be built once per
+                // process and is shared with other rayon users
+                let pool = rayon::ThreadPoolBuilder::new()
                     .num_threads(num_threads)
-                    .build_global()
+                    .build()
                     .map_err(|e| {
                         ProcessError::ProcessingFailed(format!(
                             "Failed to build thread pool: {}",
@@ -99,19 +147,45 @@ impl VideoProcessor {
                         ))
                     })?;
 
-                video_files_by_dir
-                    .into_par_iter()
-                    .for_each(|(dir_path, video_list)| {
-                        if let Err(e) = Self::process_video_directory(
-                            dir_path.clone(),
-                            video_list,
-                            Arc::clone(&config),
-                            Arc::clone(&temp_dirs_created),
-                        ) {
-                            eprintln!("Error processing directory in parallel {}: {}", dir_path, e);
-                        }
-                    });
+                pool.install(|| {
+                    pending_dirs
+                        .into_par_iter()
+                        .filter_map(|(dir_path, video_list)| {
+                            let result = Self::process_video_directory(
+                                dir_path.clone(),
+                                video_list.clone(),
+                                Arc::clone(&config),
+                                Arc::clone(&temp_dirs_created),
+                                budget,
+                            );
+                            if let Err(e) = &result {
+                                eprintln!(
+                                    "Error processing directory in parallel {}: {}",
+                                    dir_path, e
+                                );
+                            }
+                            // Failures land in the manifest, which feeds the stats below
+                            Self::record_directory_result(
+                                &manifest,
+                                &dir_path,
+                                &video_list,
+                                &result,
+                            )
+                            .err()
+                            .map(|e| {
+                                eprintln!("Warning: Failed to update job manifest: {}", e);
+                                format!("Directory {}: {}", dir_path, e)
+                            })
+                        })
+                        .collect()
+                })
             }
+        };
+
+        // Stats cover the whole job, including earlier runs
+        manifest.lock().unwrap().apply_to_stats(stats);
+        for error in manifest_errors {
+            stats.add_failed_file(error);
         }
 
         // Cleanup temporary directories
@@ -136,13 +210,38 @@ impl VideoProcessor {
         Ok(())
     }
 
+    /// Record the outcome of a directory in the job manifest and persist it
+    ///
+    /// A directory produces a single output, so all of its videos share the
+    /// directory's result.
+    fn record_directory_result(
+        manifest: &Mutex<JobManifest>,
+        dir_path: &str,
+        video_list: &[PathBuf],
+        result: &Result<(), ProcessError>,
+    ) -> Result<(), ProcessError> {
+        let mut manifest = manifest.lock().unwrap();
+        for video in video_list {
+            match result {
+                Ok(()) => manifest.record_success(video, None)?,
+                Err(e) => manifest.record_failure(video, &format!("Directory {}: {}", dir_path, e)),
+            }
+        }
+        manifest.save()
+    }
+
     /// Process video directory
     fn process_video_directory(
         input_dir_path: String,
         video_list: Vec<PathBuf>,
         config: Arc<VideoExtractionConfig>,
         temp_dirs_created: Arc<Mutex<Vec<PathBuf>>>,
+        budget: TimeBudget,
     ) -> Result<(), ProcessError> {
+        budget
+            .job_deadline()
+            .check(&format!("Video job before directory {}", input_dir_path))?;
+
         let dir_tag = std::path::Path::new(&input_dir_path)
             .file_name()
             .and_then(|s| s.to_str())
@@ -187,7 +286,7 @@ impl VideoProcessor {
 
         // Process based on modes
         if creation_mode == "direct" && config.extraction_mode == "opencv" {
-            Self::process_direct_opencv(&sorted_video_list, &output_video_path, &config)
+            Self::process_direct_opencv(&sorted_video_list, &output_video_path, &config, budget)
         } else if creation_mode == "direct" && use_ffmpeg_extraction {
             Self::process_direct_ffmpeg(
                 &sorted_video_list,
@@ -196,10 +295,20 @@ impl VideoProcessor {
                 &output_base,
                 &dir_tag,
                 temp_dirs_created,
+                budget,
             )
+        } else if creation_mode == "stream" {
+            // Decoded frames are piped straight into ffmpeg (always OpenCV decoding)
+            Self::process_streaming(&sorted_video_list, &output_video_path, &config, budget)
         } else if creation_mode == "skip" || creation_mode == "none" {
             // Extraction only mode
-            Self::process_extraction_only(&sorted_video_list, &config, &output_base, &dir_tag)
+            Self::process_extraction_only(
+                &sorted_video_list,
+                &config,
+                &output_base,
+                &dir_tag,
+                budget,
+            )
         } else {
             // Default temp frames mode
             Self::process_temp_frames(
@@ -209,6 +318,7 @@ impl VideoProcessor {
                 &output_base,
                 &dir_tag,
                 temp_dirs_created,
+                budget,
             )
         }
     }
@@ -220,11 +330,13 @@ impl VideoProcessor {
         video_list: &[PathBuf],
         output_video_path: &PathBuf,
         config: &VideoExtractionConfig,
+        budget: TimeBudget,
     ) -> Result<(), ProcessError> {
         println!("Using memory-efficient direct OpenCV processing.");
         let mut output_writer: Option<videoio::VideoWriter> = None;
         let mut output_frame_size: Option<Size> = None;
         let mut videos_processed_count = 0;
+        let mut report = ResolutionReport::new(output_video_path, &config.resolution_policy);
 
         for (video_index, video_path) in video_list.iter().enumerate() {
             println!(
@@ -234,6 +346,7 @@ impl VideoProcessor {
                 video_list.len(),
                 video_path.display()
             );
+            let deadline = budget.file_deadline();
 
             let mut cap = match HardwareAcceleratedCapture::create_capture(
                 video_path.to_str().unwrap(),
@@ -278,6 +391,8 @@ impl VideoProcessor {
                         video_path.display()
                     );
                     output_frame_size = Some(size);
+                    report.output_width = size.width;
+                    report.output_height = size.height;
 
                     let fourcc = videoio::VideoWriter::fourcc('a', 'v', 'c', '1').map_err(|e| {
                         ProcessError::ProcessingFailed(format!("Failed to create fourcc: {}", e))
@@ -321,7 +436,14 @@ impl VideoProcessor {
                     ProcessError::ProcessingFailed(format!("Failed to get frame count: {}", e))
                 })? as usize;
 
+                let mut mismatch_reported = false;
+
                 for frame_number in (0..total_frames_cv).step_by(config.frame_interval) {
+                    deadline.check(&format!(
+                        "Direct OpenCV processing of {}",
+                        video_path.display()
+                    ))?;
+
                     let mut frame = Mat::default();
                     if !cap
                         .set(videoio::CAP_PROP_POS_FRAMES, frame_number as f64)
@@ -349,20 +471,44 @@ impl VideoProcessor {
                             continue;
                         }
 
-                        // Check frame size
-                        if frame.size().map_err(|e| {
+                        // Adapt frames whose size differs from the writer size
+                        let frame_size = frame.size().map_err(|e| {
                             ProcessError::ProcessingFailed(format!(
                                 "Failed to get frame size: {}",
                                 e
                             ))
-                        })? != expected_size
-                        {
+                        })?;
+                        let plan = FrameAdapter::plan(
+                            frame_size,
+                            expected_size,
+                            &config.resolution_policy,
+                        );
+                        if plan != FramePlan::Unchanged && !mismatch_reported {
                             eprintln!(
-                                "Warning: Frame {} size does not match writer size in video {}. Skipping frame.",
-                                frame_number,
-                                video_path.display()
+                                "Warning: Frame size {:?} in video {} does not match writer size {:?} (policy {:?}).",
+                                frame_size,
+                                video_path.display(),
+                                expected_size,
+                                config.resolution_policy
                             );
-                            continue;
+                            mismatch_reported = true;
+                        }
+                        if plan != FramePlan::Unchanged {
+                            match FrameAdapter::apply(&frame, &plan) {
+                                Ok(Some(adapted)) => {
+                                    report.record(video_path, frame_size, true);
+                                    frame = adapted;
+                                }
+                                Ok(None) => {
+                                    report.record(video_path, frame_size, false);
+                                    continue;
+                                }
+                                Err(e) => {
+                                    let _ = writer.release();
+                                    let _ = fs::remove_file(output_video_path);
+                                    return Err(e);
+                                }
+                            }
                         }
 
                         // Write the frame
@@ -409,6 +555,9 @@ impl VideoProcessor {
                     output_video_path.display()
                 );
             }
+            if let Err(e) = report.save() {
+                eprintln!("Warning: {}", e);
+            }
         } else {
             println!("VideoWriter was never initialized. No output file created.");
         }
@@ -424,6 +573,7 @@ impl VideoProcessor {
         output_base: &PathBuf,
         dir_tag: &str,
         temp_dirs_created: Arc<Mutex<Vec<PathBuf>>>,
+        budget: TimeBudget,
     ) -> Result<(), ProcessError> {
         println!("Using ffmpeg extraction with direct creation.");
 
@@ -457,6 +607,7 @@ impl VideoProcessor {
                 video_index,
                 temp_path.to_str().unwrap(),
                 config.frame_interval,
+                &budget.file_deadline(),
             )?;
         }
 
@@ -465,6 +616,8 @@ impl VideoProcessor {
             temp_path.to_str().unwrap(),
             output_video_path,
             config.output_fps,
+            &config.encoding,
+            &budget.file_deadline(),
         )
     }
 
@@ -476,6 +629,7 @@ impl VideoProcessor {
         output_base: &PathBuf,
         dir_tag: &str,
         temp_dirs_created: Arc<Mutex<Vec<PathBuf>>>,
+        budget: TimeBudget,
     ) -> Result<(), ProcessError> {
         println!("Using temp frames approach.");
 
@@ -510,6 +664,7 @@ impl VideoProcessor {
                     video_index,
                     temp_path.to_str().unwrap(),
                     config.frame_interval,
+                    &budget.file_deadline(),
                 )?;
             } else {
                 FrameExtractor::extract_frames_opencv(
@@ -518,6 +673,7 @@ impl VideoProcessor {
                     temp_path.to_str().unwrap(),
                     config.frame_interval,
                     &config.hardware_acceleration,
+                    &budget.file_deadline(),
                 )?;
             }
         }
@@ -527,15 +683,92 @@ impl VideoProcessor {
             temp_path.to_str().unwrap(),
             output_video_path,
             config.output_fps,
+            &config.encoding,
+            &budget.file_deadline(),
         )
     }
 
+    /// Process by piping decoded frames into an ffmpeg encoder (no temp files)
+    ///
+    /// Samples the same frames as "temp_frames" with OpenCV extraction and
+    /// encodes them with the same settings, but never writes JPEGs to disk.
+    fn process_streaming(
+        video_list: &[PathBuf],
+        output_video_path: &Path,
+        config: &VideoExtractionConfig,
+        budget: TimeBudget,
+    ) -> Result<(), ProcessError> {
+        println!("Using streaming approach (frames piped to ffmpeg).");
+
+        let mut encoder: Option<PipeEncoder> = None;
+
+        for (video_index, video_path) in video_list.iter().enumerate() {
+            println!(
+                "  Thread {:?} streaming from video {}/{}: {}",
+                thread::current().id(),
+                video_index + 1,
+                video_list.len(),
+                video_path.display()
+            );
+
+            let result = FrameExtractor::for_each_frame_opencv(
+                video_path.to_str().unwrap(),
+                config.frame_interval,
+                &config.hardware_acceleration,
+                &budget.file_deadline(),
+                |_, frame| {
+                    if encoder.is_none() {
+                        let size = frame.size().map_err(|e| {
+                            ProcessError::ProcessingFailed(format!(
+                                "Failed to get frame size: {}",
+                                e
+                            ))
+                        })?;
+                        encoder = Some(PipeEncoder::start(
+                            output_video_path,
+                            size,
+                            config.output_fps,
+                            &config.encoding,
+                        )?);
+                    }
+                    match encoder.as_mut() {
+                        Some(encoder) => encoder.write_frame(frame),
+                        None => Ok(()),
+                    }
+                },
+            );
+
+            if let Err(e) = result {
+                if let Some(encoder) = encoder.take() {
+                    encoder.abort();
+                    let _ = fs::remove_file(output_video_path);
+                }
+                return Err(e);
+            }
+        }
+
+        match encoder {
+            Some(encoder) => {
+                encoder.finish(&budget.file_deadline())?;
+                Ok(())
+            }
+            None => {
+                println!(
+                    "No frames decoded. No video will be created at {}",
+                    output_video_path.display()
+                );
+                Ok(())
+            }
+        }
+    }
+
     /// Process using extraction only (no video creation)
     fn process_extraction_only(
         video_list: &[PathBuf],
         config: &VideoExtractionConfig,
         output_base: &PathBuf,
         dir_tag: &str,
+        budget: TimeBudget,
     ) -> Result<(), ProcessError> {
         println!("Using extraction only mode (no video creation).");
 
@@ -560,6 +793,7 @@ impl VideoProcessor {
                     video_index,
                     output_path.to_str().unwrap(),
                     config.frame_interval,
+                    &budget.file_deadline(),
                 )?;
             } else {
                 FrameExtractor::extract_frames_opencv(
@@ -568,6 +802,7 @@ impl VideoProcessor {
                     output_path.to_str().unwrap(),
                     config.frame_interval,
                     &config.hardware_acceleration,
+                    &budget.file_deadline(),
                 )?;
             }
         }
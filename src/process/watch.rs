@@ -0,0 +1,539 @@
+//! Watch-folder daemon for the process pipeline
+//!
+//! `FolderWatcher` polls the watched folders, waits until each new file has
+//! stopped growing, processes it with the job configuration and moves it to a
+//! `done/` or `failed/` folder. Videos go through `VideoProcessor` when the
+//! config has a `video_config`; everything else goes through `Processor` in
+//! `SingleFile` mode. Errors are recorded and the watcher keeps running.
+
+use crate::process::config::{ProcessConfig, VideoExtractionConfig};
+use crate::process::manifest::{JobManifest, JobStatus};
+use crate::process::processor::Processor;
+use crate::process::stats::ProcessingStats;
+use crate::process::types::{ProcessError, ProcessingMode};
+use crate::process::video::VideoProcessor;
+use crate::process::workers::scanner::VideoScanner;
+use crate::rtsp::shutdown::ShutdownToken;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Default status file name inside the output directory
+pub const WATCH_STATUS_FILE_NAME: &str = "watch_status.json";
+
+/// Staging folder (inside each watched folder) for files being processed
+const STAGING_DIR_NAME: &str = ".processing";
+
+/// Outcome of one watched file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchEvent {
+    pub file: String,
+    pub success: bool,
+    pub moved_to: Option<String>,
+    pub error: Option<String>,
+    pub duration_seconds: f64,
+    pub finished_at: String,
+}
+
+/// Rolling status written after every poll and every processed file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchStatus {
+    pub started_at: String,
+    pub updated_at: String,
+    pub watched_directories: Vec<String>,
+    /// File being processed right now, if any
+    pub current_file: Option<String>,
+    /// Files seen but still growing
+    pub pending_files: usize,
+    pub files_done: u64,
+    pub files_failed: u64,
+    pub last_error: Option<String>,
+    /// Most recent results, newest last
+    pub recent: VecDeque<WatchEvent>,
+}
+
+/// Size and mtime of a file when it was last seen, and since when unchanged
+struct Observation {
+    size: u64,
+    modified: Option<SystemTime>,
+    unchanged_since: Instant,
+}
+
+/// Polls watched folders and processes files once they are complete
+pub struct FolderWatcher {
+    config: ProcessConfig,
+    watch_dirs: Vec<PathBuf>,
+    status_path: PathBuf,
+    status: WatchStatus,
+    observed: HashMap<PathBuf, Observation>,
+}
+
+impl FolderWatcher {
+    /// Create a watcher for `config`
+    pub fn new(config: ProcessConfig) -> Result<Self, ProcessError> {
+        let watch_dirs: Vec<PathBuf> = match &config.video_config {
+            Some(video_config) => video_config
+                .input_directories
+                .iter()
+                .map(PathBuf::from)
+                .collect(),
+            None => vec![PathBuf::from(&config.input_path)],
+        };
+        if watch_dirs.is_empty() {
+            return Err(ProcessError::ConfigurationError(
+                "Watch mode needs at least one input directory".to_string(),
+            ));
+        }
+        for dir in &watch_dirs {
+            if !dir.is_dir() {
+                return Err(ProcessError::InvalidInput(format!(
+                    "Watched path is not a directory: {}",
+                    dir.display()
+                )));
+            }
+        }
+
+        let status_path = match &config.watch.status_file {
+            Some(path) => PathBuf::from(path),
+            None => Self::output_root(&config).join(WATCH_STATUS_FILE_NAME),
+        };
+        let now = Local::now().to_rfc3339();
+        let status = WatchStatus {
+            started_at: now.clone(),
+            updated_at: now,
+            watched_directories: watch_dirs
+                .iter()
+                .map(|d| d.to_string_lossy().to_string())
+                .collect(),
+            current_file: None,
+            pending_files: 0,
+            files_done: 0,
+            files_failed: 0,
+            last_error: None,
+            recent: VecDeque::new(),
+        };
+
+        Ok(Self {
+            config,
+            watch_dirs,
+            status_path,
+            status,
+            observed: HashMap::new(),
+        })
+    }
+
+    /// Load a `ProcessConfig` (or a bare `VideoExtractionConfig`) and create a watcher
+    pub fn from_config_file(config_path: &str) -> Result<Self, ProcessError> {
+        let config_data = fs::read_to_string(config_path).map_err(|e| {
+            ProcessError::IoError(format!("Unable to read config file {}: {}", config_path, e))
+        })?;
+
+        let config = match serde_json::from_str::<ProcessConfig>(&config_data) {
+            Ok(config) => config,
+            Err(_) => {
+                let deserializer = &mut serde_json::Deserializer::from_str(&config_data);
+                let video_config: VideoExtractionConfig =
+                    serde_path_to_error::deserialize(deserializer).map_err(|e| {
+                        ProcessError::ConfigurationError(format!(
+                            "Error parsing config.json at '{}': {}",
+                            e.path(),
+                            e
+                        ))
+                    })?;
+                ProcessConfig {
+                    output_path: video_config.output_directory.clone(),
+                    video_config: Some(video_config),
+                    ..Default::default()
+                }
+            }
+        };
+
+        Self::new(config)
+    }
+
+    /// Current status snapshot
+    pub fn status(&self) -> &WatchStatus {
+        &self.status
+    }
+
+    /// Location of the status file
+    pub fn status_path(&self) -> &Path {
+        &self.status_path
+    }
+
+    /// Poll until `shutdown` is triggered, sleeping `poll_interval_seconds`
+    /// between polls
+    ///
+    /// Pass `ShutdownToken::from_signals()` to stop on Ctrl-C and SIGTERM.
+    pub fn run(&mut self, shutdown: &ShutdownToken) -> Result<(), ProcessError> {
+        let interval = Duration::from_secs(self.config.watch.poll_interval_seconds.max(1));
+        println!(
+            "👀 Watching {} folder(s), polling every {:?}",
+            self.watch_dirs.len(),
+            interval
+        );
+        for dir in &self.watch_dirs {
+            println!("   • {}", dir.display());
+        }
+
+        while !shutdown.is_triggered() {
+            if let Err(e) = self.poll_once() {
+                eprintln!("❌ Watch poll failed: {}", e);
+                self.status.last_error = Some(e.to_string());
+                self.write_status();
+            }
+
+            if shutdown.sleep(interval) {
+                break;
+            }
+        }
+
+        println!("🛑 Watch mode stopped");
+        Ok(())
+    }
+
+    /// Scan the watched folders once and process every file that is ready
+    ///
+    /// Returns the number of files processed (successfully or not).
+    pub fn poll_once(&mut self) -> Result<usize, ProcessError> {
+        let ready = self.collect_ready_files()?;
+        self.status.pending_files = self.observed.len();
+        self.write_status();
+
+        for (watch_dir, file) in &ready {
+            self.handle_file(watch_dir, file);
+        }
+        if !ready.is_empty() {
+            self.status.pending_files = self.observed.len();
+            self.write_status();
+        }
+
+        Ok(ready.len())
+    }
+
+    /// Update observations and return files that stopped changing
+    fn collect_ready_files(&mut self) -> Result<Vec<(PathBuf, PathBuf)>, ProcessError> {
+        let stable_for = Duration::from_secs(self.config.watch.stable_seconds);
+        let now = Instant::now();
+        let mut seen = Vec::new();
+        let mut ready = Vec::new();
+
+        for watch_dir in &self.watch_dirs {
+            let entries = fs::read_dir(watch_dir).map_err(|e| {
+                ProcessError::IoError(format!(
+                    "Failed to read watched directory {}: {}",
+                    watch_dir.display(),
+                    e
+                ))
+            })?;
+
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let path = entry.path();
+                let hidden = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with('.'));
+                // Only top-level files; done/, failed/ and the staging folder are skipped
+                if hidden || !path.is_file() {
+                    continue;
+                }
+                let Ok(metadata) = fs::metadata(&path) else {
+                    continue;
+                };
+                let size = metadata.len();
+                let modified = metadata.modified().ok();
+                seen.push(path.clone());
+
+                match self.observed.get_mut(&path) {
+                    Some(obs) if obs.size == size && obs.modified == modified => {
+                        if now.duration_since(obs.unchanged_since) >= stable_for {
+                            ready.push((watch_dir.clone(), path.clone()));
+                        }
+                    }
+                    Some(obs) => {
+                        // Still being written
+                        obs.size = size;
+                        obs.modified = modified;
+                        obs.unchanged_since = now;
+                    }
+                    None => {
+                        self.observed.insert(
+                            path.clone(),
+                            Observation {
+                                size,
+                                modified,
+                                unchanged_since: now,
+                            },
+                        );
+                    }
+                }
+            }
+        }
+
+        // Forget files that disappeared or are about to be processed
+        self.observed.retain(|path, _| seen.contains(path));
+        for (_, path) in &ready {
+            self.observed.remove(path);
+        }
+
+        Ok(ready)
+    }
+
+    /// Process one file and move it to done/ or failed/; never returns an error
+    fn handle_file(&mut self, watch_dir: &Path, file: &Path) {
+        let started = Instant::now();
+        let file_str = file.to_string_lossy().to_string();
+        println!("🎬 Processing watched file: {}", file.display());
+        self.status.current_file = Some(file_str.clone());
+        self.write_status();
+
+        let staged = match Self::stage(watch_dir, file) {
+            Ok(staged) => staged,
+            Err(e) => {
+                eprintln!("❌ Failed to stage {}: {}", file.display(), e);
+                let moved_to = Self::move_to(file, &self.target_dir(watch_dir, false));
+                self.record(
+                    file_str,
+                    false,
+                    moved_to,
+                    Some(e.to_string()),
+                    started.elapsed(),
+                );
+                return;
+            }
+        };
+
+        match self.process_staged(watch_dir, file, &staged) {
+            Ok(()) => {
+                let moved_to = Self::move_to(&staged, &self.target_dir(watch_dir, true));
+                self.record(file_str, true, moved_to, None, started.elapsed());
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to process {}: {}", file.display(), e);
+                let moved_to = Self::move_to(&staged, &self.target_dir(watch_dir, false));
+                self.record(
+                    file_str,
+                    false,
+                    moved_to,
+                    Some(e.to_string()),
+                    started.elapsed(),
+                );
+            }
+        }
+        if let Some(staging_dir) = staged.parent() {
+            let _ = fs::remove_dir(staging_dir);
+        }
+    }
+
+    /// Move a ready file into its own staging folder
+    ///
+    /// The folder is named after the full file name plus a timestamp (and a
+    /// counter if that is taken), so `clip.mp4` and `clip.mov`, or the same
+    /// name dropped twice, never share a folder. Video outputs are named after
+    /// the containing folder, so they don't overwrite each other either.
+    fn stage(watch_dir: &Path, file: &Path) -> Result<PathBuf, ProcessError> {
+        let file_name = file.file_name().ok_or_else(|| {
+            ProcessError::InvalidInput(format!("Invalid file name: {}", file.display()))
+        })?;
+        let staging_root = watch_dir.join(STAGING_DIR_NAME);
+        fs::create_dir_all(&staging_root).map_err(|e| {
+            ProcessError::IoError(format!("Failed to create staging directory: {}", e))
+        })?;
+        let base = format!(
+            "{}_{}",
+            file_name.to_string_lossy(),
+            Local::now().format("%Y%m%d_%H%M%S_%3f")
+        );
+        let mut staging_dir = staging_root.join(&base);
+        let mut counter = 1;
+        // create_dir (not create_dir_all) fails when the folder already exists
+        while let Err(e) = fs::create_dir(&staging_dir) {
+            if e.kind() != std::io::ErrorKind::AlreadyExists {
+                return Err(ProcessError::IoError(format!(
+                    "Failed to create staging directory: {}",
+                    e
+                )));
+            }
+            staging_dir = staging_root.join(format!("{}_{}", base, counter));
+            counter += 1;
+        }
+        let staged = staging_dir.join(file_name);
+        fs::rename(file, &staged).map_err(|e| {
+            ProcessError::IoError(format!("Failed to stage {}: {}", file.display(), e))
+        })?;
+        Ok(staged)
+    }
+
+    /// Run the configured pipeline on a staged file
+    ///
+    /// Video results are recorded in the job manifest under the watched path
+    /// (`file`), not the temporary staged one, so a file dropped again is
+    /// matched with its earlier runs.
+    fn process_staged(
+        &self,
+        watch_dir: &Path,
+        file: &Path,
+        staged: &Path,
+    ) -> Result<(), ProcessError> {
+        let staged_str = staged.to_string_lossy().to_string();
+
+        match &self.config.video_config {
+            Some(video_config) if VideoScanner::is_video_file(staged) => {
+                let mut video_config = video_config.clone();
+                video_config.input_directories = vec![staged_str];
+                let output_dir = PathBuf::from(&video_config.output_directory);
+
+                let mut stats = ProcessingStats::new();
+                VideoProcessor::run_with_config(
+                    video_config,
+                    self.config.processing_options.clone(),
+                    &mut stats,
+                    Instant::now(),
+                )?;
+
+                // Job stats include earlier runs; the manifest has this file's result
+                let mut manifest = JobManifest::load_or_create(&output_dir)?;
+                // `file` is gone from the watched folder, so resolve via the folder
+                let watched = fs::canonicalize(watch_dir)
+                    .map(|dir| dir.join(file.file_name().unwrap_or_default()))
+                    .unwrap_or_else(|_| file.to_path_buf());
+                let entry = manifest.move_entry(staged, &watched).cloned();
+                manifest.save()?;
+                match entry {
+                    Some(entry) if entry.status == JobStatus::Completed => Ok(()),
+                    Some(entry) => Err(ProcessError::ProcessingFailed(
+                        entry
+                            .error
+                            .clone()
+                            .unwrap_or_else(|| "Video processing failed".to_string()),
+                    )),
+                    None => Err(ProcessError::ProcessingFailed(
+                        "Video processing recorded no result".to_string(),
+                    )),
+                }
+            }
+            _ => {
+                let file_name = staged.file_name().ok_or_else(|| {
+                    ProcessError::InvalidInput(format!("Invalid file name: {}", staged.display()))
+                })?;
+                let output = Self::output_root(&self.config).join(file_name);
+                let output_str = output.to_string_lossy().to_string();
+
+                let mut config = self.config.clone();
+                config.processing_mode = ProcessingMode::SingleFile;
+                config.input_path = staged_str.clone();
+                config.output_path = output_str.clone();
+
+                let mut processor = Processor::new(config)?;
+                processor.process_from_source(&staged_str, &output_str)
+            }
+        }
+    }
+
+    fn output_root(config: &ProcessConfig) -> PathBuf {
+        PathBuf::from(&config.output_path)
+    }
+
+    fn target_dir(&self, watch_dir: &Path, success: bool) -> PathBuf {
+        let configured = if success {
+            &self.config.watch.done_directory
+        } else {
+            &self.config.watch.failed_directory
+        };
+        match configured {
+            Some(dir) => PathBuf::from(dir),
+            None => watch_dir.join(if success { "done" } else { "failed" }),
+        }
+    }
+
+    /// Move a file into `dir`, adding a timestamp if the name is taken
+    fn move_to(file: &Path, dir: &Path) -> Option<PathBuf> {
+        let file_name = file.file_name()?;
+        if let Err(e) = fs::create_dir_all(dir) {
+            eprintln!("⚠️ Failed to create {}: {}", dir.display(), e);
+            return None;
+        }
+
+        let mut target = dir.join(file_name);
+        if target.exists() {
+            let stem = file.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+            let timestamp = Local::now().format("%Y%m%d_%H%M%S%.3f");
+            target = match file.extension().and_then(|e| e.to_str()) {
+                Some(ext) => dir.join(format!("{}_{}.{}", stem, timestamp, ext)),
+                None => dir.join(format!("{}_{}", stem, timestamp)),
+            };
+        }
+
+        // rename fails across filesystems; fall back to copy + remove
+        let moved = fs::rename(file, &target)
+            .or_else(|_| fs::copy(file, &target).and_then(|_| fs::remove_file(file)));
+        match moved {
+            Ok(()) => Some(target),
+            Err(e) => {
+                eprintln!(
+                    "⚠️ Failed to move {} to {}: {}",
+                    file.display(),
+                    dir.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Write the status file atomically; failures are only logged
+    fn write_status(&mut self) {
+        self.status.updated_at = Local::now().to_rfc3339();
+
+        let result = (|| -> std::io::Result<()> {
+            if let Some(parent) = self.status_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let json = serde_json::to_string_pretty(&self.status)?;
+            let temp_path = self.status_path.with_extension("json.tmp");
+            fs::write(&temp_path, json)?;
+            fs::rename(&temp_path, &self.status_path)
+        })();
+
+        if let Err(e) = result {
+            eprintln!(
+                "⚠️ Failed to write watch status {}: {}",
+                self.status_path.display(),
+                e
+            );
+        }
+    }
+
+    fn record(
+        &mut self,
+        file: String,
+        success: bool,
+        moved_to: Option<PathBuf>,
+        error: Option<String>,
+        elapsed: Duration,
+    ) {
+        if success {
+            self.status.files_done += 1;
+            println!("✅ Done: {}", file);
+        } else {
+            self.status.files_failed += 1;
+            self.status.last_error = error.clone();
+        }
+        self.status.current_file = None;
+        self.status.recent.push_back(WatchEvent {
+            file,
+            success,
+            moved_to: moved_to.map(|p| p.to_string_lossy().to_string()),
+            error,
+            duration_seconds: elapsed.as_secs_f64(),
+            finished_at: Local::now().to_rfc3339(),
+        });
+        while self.status.recent.len() > self.config.watch.status_history.max(1) {
+            self.status.recent.pop_front();
+        }
+        self.write_status();
+    }
+}
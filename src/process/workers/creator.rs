@@ -1,3 +1,5 @@
+use crate::process::config::VideoEncodingConfig;
+use crate::process::timeout::{Deadline, run_with_deadline};
 use crate::process::types::ProcessError;
 use std::fs;
 use std::io::Write;
@@ -8,10 +10,14 @@ pub struct VideoCreator;
 
 impl VideoCreator {
     /// Create video from temp frames
+    ///
+    /// The ffmpeg child is killed if it is still running when `deadline` passes.
     pub fn create_video_from_temp_frames(
         temp_frame_dir: &str,
         output_video_path: &PathBuf,
         fps: i32,
+        encoding: &VideoEncodingConfig,
+        deadline: &Deadline,
     ) -> Result<(), ProcessError> {
         let frame_source_dir = Path::new(temp_frame_dir);
         let final_output_dir = output_video_path.parent().unwrap_or_else(|| Path::new("."));
@@ -127,10 +133,7 @@ impl VideoCreator {
             .arg("0")
             .arg("-i")
             .arg(list_file_path.to_str().unwrap())
-            .arg("-c:v")
-            .arg("libx264")
-            .arg("-pix_fmt")
-            .arg("yuv420p")
+            .args(encoding.to_ffmpeg_args())
             .arg("-r")
             .arg(fps.to_string())
             .arg("-y") // Overwrite output
@@ -139,12 +142,11 @@ impl VideoCreator {
             .arg("-loglevel")
             .arg("warning");
 
-        let output = cmd.output().map_err(|e| {
-            ProcessError::ProcessingFailed(format!(
-                "Failed to execute ffmpeg for video creation: {}",
-                e
-            ))
-        })?;
+        let output = run_with_deadline(
+            &mut cmd,
+            deadline,
+            &format!("ffmpeg video creation for {}", output_video_path.display()),
+        )?;
 
         if !output.status.success() {
             eprintln!("ffmpeg stdout: {}", String::from_utf8_lossy(&output.stdout));
@@ -0,0 +1,187 @@
+use crate::process::config::VideoEncodingConfig;
+use crate::process::timeout::{
+    Deadline, join_pipe_reader, kill_and_reap, spawn_pipe_reader, wait_with_deadline,
+};
+use crate::process::types::ProcessError;
+use opencv::{
+    core::{CV_8UC3, Mat, Size},
+    imgproc,
+    prelude::*,
+};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread::JoinHandle;
+
+/// Streams raw BGR frames into an `ffmpeg` encoder over stdin
+///
+/// Used by the "stream" video creation mode so sampled frames never touch
+/// the disk. Frames whose size differs from the first frame are resized to it.
+pub struct PipeEncoder {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stderr_reader: Option<JoinHandle<Vec<u8>>>,
+    frame_size: Size,
+    output_path: PathBuf,
+    frames_written: u64,
+}
+
+impl PipeEncoder {
+    /// Spawn ffmpeg reading `width`x`height` BGR frames at `fps` from stdin
+    pub fn start(
+        output_video_path: &Path,
+        frame_size: Size,
+        fps: i32,
+        encoding: &VideoEncodingConfig,
+    ) -> Result<Self, ProcessError> {
+        let mut cmd = Command::new("ffmpeg");
+        cmd.arg("-hide_banner")
+            .arg("-loglevel")
+            .arg("warning")
+            .arg("-y")
+            .arg("-f")
+            .arg("rawvideo")
+            .arg("-pix_fmt")
+            .arg("bgr24")
+            .arg("-s")
+            .arg(format!("{}x{}", frame_size.width, frame_size.height))
+            .arg("-r")
+            .arg(fps.to_string())
+            .arg("-i")
+            .arg("pipe:0")
+            .args(encoding.to_ffmpeg_args())
+            .arg(output_video_path);
+
+        println!(
+            "Starting ffmpeg pipe encoder ({}, crf {}) for {}",
+            encoding.codec,
+            encoding.crf,
+            output_video_path.display()
+        );
+
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                ProcessError::ProcessingFailed(format!("Failed to execute ffmpeg encoder: {}", e))
+            })?;
+
+        let stdin = child.stdin.take();
+        let stderr_reader = child.stderr.take().map(spawn_pipe_reader);
+
+        Ok(Self {
+            child,
+            stdin,
+            stderr_reader,
+            frame_size,
+            output_path: output_video_path.to_path_buf(),
+            frames_written: 0,
+        })
+    }
+
+    /// Size every frame is encoded at
+    pub fn frame_size(&self) -> Size {
+        self.frame_size
+    }
+
+    /// Number of frames sent to the encoder so far
+    pub fn frames_written(&self) -> u64 {
+        self.frames_written
+    }
+
+    /// Send one BGR frame to the encoder
+    pub fn write_frame(&mut self, frame: &Mat) -> Result<(), ProcessError> {
+        if frame.typ() != CV_8UC3 {
+            return Err(ProcessError::ProcessingFailed(format!(
+                "Pipe encoder expects 8-bit BGR frames, got Mat type {}",
+                frame.typ()
+            )));
+        }
+
+        let frame_size = frame.size().map_err(|e| {
+            ProcessError::ProcessingFailed(format!("Failed to get frame size: {}", e))
+        })?;
+
+        let mut converted = Mat::default();
+        let frame = if frame_size != self.frame_size {
+            imgproc::resize(
+                frame,
+                &mut converted,
+                self.frame_size,
+                0.0,
+                0.0,
+                imgproc::INTER_LINEAR,
+            )
+            .map_err(|e| {
+                ProcessError::ProcessingFailed(format!("Failed to resize frame: {}", e))
+            })?;
+            &converted
+        } else if !frame.is_continuous() {
+            converted = frame.try_clone().map_err(|e| {
+                ProcessError::ProcessingFailed(format!("Failed to copy frame: {}", e))
+            })?;
+            &converted
+        } else {
+            frame
+        };
+
+        let bytes = frame.data_bytes().map_err(|e| {
+            ProcessError::ProcessingFailed(format!("Failed to access frame data: {}", e))
+        })?;
+        let stdin = self.stdin.as_mut().ok_or_else(|| {
+            ProcessError::ProcessingFailed("ffmpeg encoder stdin is closed".to_string())
+        })?;
+        stdin.write_all(bytes).map_err(|e| {
+            ProcessError::ProcessingFailed(format!(
+                "Failed to write frame to ffmpeg encoder for {}: {}",
+                self.output_path.display(),
+                e
+            ))
+        })?;
+
+        self.frames_written += 1;
+        Ok(())
+    }
+
+    /// Close stdin and wait for ffmpeg to finish writing the output
+    pub fn finish(mut self, deadline: &Deadline) -> Result<u64, ProcessError> {
+        // Dropping stdin signals end of input to ffmpeg
+        drop(self.stdin.take());
+
+        let context = format!("ffmpeg pipe encoding for {}", self.output_path.display());
+        let status = wait_with_deadline(&mut self.child, deadline, &context)?;
+        let stderr = join_pipe_reader(self.stderr_reader.take());
+
+        if !status.success() {
+            eprintln!("ffmpeg stderr: {}", String::from_utf8_lossy(&stderr));
+            return Err(ProcessError::ProcessingFailed(format!(
+                "Failed to create output video {}",
+                self.output_path.display()
+            )));
+        }
+
+        println!(
+            "Successfully created video (stream): {} ({} frames)",
+            self.output_path.display(),
+            self.frames_written
+        );
+        Ok(self.frames_written)
+    }
+
+    /// Kill the encoder without finishing the output
+    pub fn abort(self) {
+        // Drop kills the child because stdin is still open
+        drop(self);
+    }
+}
+
+impl Drop for PipeEncoder {
+    fn drop(&mut self) {
+        // Never leave an unfinished encoder running
+        if self.stdin.take().is_some() {
+            kill_and_reap(&mut self.child);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::process::hw_accel::HardwareAcceleratedCapture;
+use crate::process::timeout::{Deadline, run_with_deadline};
 use crate::process::types::ProcessError;
 use opencv::{
     core::{Mat, Vector},
@@ -14,17 +15,53 @@ pub struct FrameExtractor;
 
 impl FrameExtractor {
     /// Extract frames using OpenCV
+    ///
+    /// The deadline is checked between frames; a single blocked read cannot
+    /// be interrupted.
     pub fn extract_frames_opencv(
         video_filename: &str,
         video_index: usize,
         temp_frame_dir: &str,
         frame_interval: usize,
         hw_config: &crate::process::hw_accel::HardwareAccelConfig,
+        deadline: &Deadline,
     ) -> Result<(), ProcessError> {
         fs::create_dir_all(temp_frame_dir).map_err(|e| {
             ProcessError::IoError(format!("Failed to create temp frame directory: {}", e))
         })?;
 
+        Self::for_each_frame_opencv(
+            video_filename,
+            frame_interval,
+            hw_config,
+            deadline,
+            |frame_number, frame| {
+                let output_path = format!(
+                    "{}/video{:03}_frame{:07}.jpg",
+                    temp_frame_dir, video_index, frame_number
+                );
+                imgcodecs::imwrite(&output_path, frame, &Vector::new()).map_err(|e| {
+                    ProcessError::ProcessingFailed(format!("Failed to write frame: {}", e))
+                })?;
+                Ok(())
+            },
+        )
+    }
+
+    /// Decode every `frame_interval`-th frame with OpenCV and hand it to `on_frame`
+    ///
+    /// `on_frame` receives the source frame number and the decoded BGR frame.
+    /// Empty frames and failed seeks are skipped with a warning.
+    pub fn for_each_frame_opencv<F>(
+        video_filename: &str,
+        frame_interval: usize,
+        hw_config: &crate::process::hw_accel::HardwareAccelConfig,
+        deadline: &Deadline,
+        mut on_frame: F,
+    ) -> Result<(), ProcessError>
+    where
+        F: FnMut(usize, &Mat) -> Result<(), ProcessError>,
+    {
         let mut cap = HardwareAcceleratedCapture::create_capture(video_filename, hw_config)
             .map_err(|e| {
                 ProcessError::ProcessingFailed(format!(
@@ -48,6 +85,8 @@ impl FrameExtractor {
         })? as usize;
 
         for frame_number in (0..total_frames).step_by(frame_interval) {
+            deadline.check(&format!("OpenCV frame extraction from {}", video_filename))?;
+
             let mut frame = Mat::default();
             if !cap
                 .set(videoio::CAP_PROP_POS_FRAMES, frame_number as f64)
@@ -72,13 +111,7 @@ impl FrameExtractor {
                     );
                     continue;
                 }
-                let output_path = format!(
-                    "{}/video{:03}_frame{:07}.jpg",
-                    temp_frame_dir, video_index, frame_number
-                );
-                imgcodecs::imwrite(&output_path, &frame, &Vector::new()).map_err(|e| {
-                    ProcessError::ProcessingFailed(format!("Failed to write frame: {}", e))
-                })?;
+                on_frame(frame_number, &frame)?;
             } else {
                 break;
             }
@@ -87,11 +120,14 @@ impl FrameExtractor {
     }
 
     /// Extract frames using FFmpeg
+    ///
+    /// The ffmpeg child is killed if it is still running when `deadline` passes.
     pub fn extract_frames_ffmpeg(
         video_filename: &str,
         video_index: usize,
         temp_frame_dir: &str,
         frame_interval: usize,
+        deadline: &Deadline,
     ) -> Result<(), ProcessError> {
         fs::create_dir_all(temp_frame_dir).map_err(|e| {
             ProcessError::IoError(format!("Failed to create temp frame directory: {}", e))
@@ -128,9 +164,11 @@ impl FrameExtractor {
             video_index, video_filename
         );
 
-        let output = cmd.output().map_err(|e| {
-            ProcessError::ProcessingFailed(format!("Failed to execute ffmpeg: {}", e))
-        })?;
+        let output = run_with_deadline(
+            &mut cmd,
+            deadline,
+            &format!("ffmpeg frame extraction for {}", video_filename),
+        )?;
 
         if !output.status.success() {
             eprintln!("ffmpeg stdout: {}", String::from_utf8_lossy(&output.stdout));
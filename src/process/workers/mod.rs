@@ -1,3 +1,5 @@
 pub mod creator;
+pub mod encoder;
 pub mod extractors;
+pub mod resolution;
 pub mod scanner;
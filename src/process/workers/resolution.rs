@@ -0,0 +1,250 @@
+use crate::process::config::ResolutionPolicy;
+use crate::process::types::ProcessError;
+use opencv::{
+    core::{self, Mat, Rect, Scalar, Size},
+    imgproc,
+    prelude::*,
+};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How a frame of one size is turned into a frame of the output size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FramePlan {
+    /// Sizes already match
+    Unchanged,
+    /// Stretch to the output size
+    Resize(Size),
+    /// Scale to fit inside the output, then pad with black bars
+    Letterbox {
+        scaled: Size,
+        top: i32,
+        bottom: i32,
+        left: i32,
+        right: i32,
+    },
+    /// Scale to cover the output, then cut the centered region
+    Crop { scaled: Size, region: Rect },
+    /// Drop the frame
+    Skip,
+    /// Abort processing
+    Fail,
+}
+
+/// Applies a `ResolutionPolicy` to frames of mismatched size
+pub struct FrameAdapter;
+
+impl FrameAdapter {
+    /// Plan the conversion from `source` to `target` under `policy`
+    pub fn plan(source: Size, target: Size, policy: &ResolutionPolicy) -> FramePlan {
+        if source == target {
+            return FramePlan::Unchanged;
+        }
+        if source.width <= 0 || source.height <= 0 {
+            return FramePlan::Skip;
+        }
+
+        let scale_x = target.width as f64 / source.width as f64;
+        let scale_y = target.height as f64 / source.height as f64;
+
+        match policy {
+            ResolutionPolicy::ScaleToFit => FramePlan::Resize(target),
+            ResolutionPolicy::Letterbox => {
+                let scale = scale_x.min(scale_y);
+                let scaled = Size::new(
+                    ((source.width as f64 * scale).round() as i32).clamp(1, target.width),
+                    ((source.height as f64 * scale).round() as i32).clamp(1, target.height),
+                );
+                let pad_x = target.width - scaled.width;
+                let pad_y = target.height - scaled.height;
+                FramePlan::Letterbox {
+                    scaled,
+                    top: pad_y / 2,
+                    bottom: pad_y - pad_y / 2,
+                    left: pad_x / 2,
+                    right: pad_x - pad_x / 2,
+                }
+            }
+            ResolutionPolicy::Crop => {
+                let scale = scale_x.max(scale_y);
+                let scaled = Size::new(
+                    ((source.width as f64 * scale).round() as i32).max(target.width),
+                    ((source.height as f64 * scale).round() as i32).max(target.height),
+                );
+                let region = Rect::new(
+                    (scaled.width - target.width) / 2,
+                    (scaled.height - target.height) / 2,
+                    target.width,
+                    target.height,
+                );
+                FramePlan::Crop { scaled, region }
+            }
+            ResolutionPolicy::SkipMismatched => FramePlan::Skip,
+            ResolutionPolicy::Fail => FramePlan::Fail,
+        }
+    }
+
+    /// Produce the output frame for `plan`, or `None` when the frame is skipped
+    ///
+    /// `FramePlan::Fail` is turned into a `ProcessError::ValidationError`.
+    pub fn apply(frame: &Mat, plan: &FramePlan) -> Result<Option<Mat>, ProcessError> {
+        let cv_err = |e: opencv::Error| {
+            ProcessError::ProcessingFailed(format!("Failed to adapt frame size: {}", e))
+        };
+
+        match plan {
+            FramePlan::Unchanged => Ok(Some(frame.try_clone().map_err(cv_err)?)),
+            FramePlan::Resize(size) => Ok(Some(Self::resize(frame, *size)?)),
+            FramePlan::Letterbox {
+                scaled,
+                top,
+                bottom,
+                left,
+                right,
+            } => {
+                let resized = Self::resize(frame, *scaled)?;
+                let mut padded = Mat::default();
+                core::copy_make_border(
+                    &resized,
+                    &mut padded,
+                    *top,
+                    *bottom,
+                    *left,
+                    *right,
+                    core::BORDER_CONSTANT,
+                    Scalar::all(0.0),
+                )
+                .map_err(cv_err)?;
+                Ok(Some(padded))
+            }
+            FramePlan::Crop { scaled, region } => {
+                let resized = Self::resize(frame, *scaled)?;
+                let cropped = Mat::roi(&resized, *region).map_err(cv_err)?;
+                Ok(Some(cropped.try_clone().map_err(cv_err)?))
+            }
+            FramePlan::Skip => Ok(None),
+            FramePlan::Fail => Err(ProcessError::ValidationError(
+                "Frame size does not match output size (resolution_policy: fail)".to_string(),
+            )),
+        }
+    }
+
+    fn resize(frame: &Mat, size: Size) -> Result<Mat, ProcessError> {
+        let mut resized = Mat::default();
+        imgproc::resize(frame, &mut resized, size, 0.0, 0.0, imgproc::INTER_AREA).map_err(|e| {
+            ProcessError::ProcessingFailed(format!("Failed to resize frame: {}", e))
+        })?;
+        Ok(resized)
+    }
+}
+
+/// One input whose frames did not match the output size
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolutionEntry {
+    pub path: PathBuf,
+    pub width: i32,
+    pub height: i32,
+    pub frames: u64,
+}
+
+/// Which inputs were adapted or skipped while building one output video
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolutionReport {
+    pub output: PathBuf,
+    pub policy: ResolutionPolicy,
+    pub output_width: i32,
+    pub output_height: i32,
+    pub adapted: Vec<ResolutionEntry>,
+    pub skipped: Vec<ResolutionEntry>,
+}
+
+impl ResolutionReport {
+    pub fn new(output: &Path, policy: &ResolutionPolicy) -> Self {
+        Self {
+            output: output.to_path_buf(),
+            policy: policy.clone(),
+            output_width: 0,
+            output_height: 0,
+            adapted: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Count one adapted (`true`) or skipped (`false`) frame from `path`
+    pub fn record(&mut self, path: &Path, size: Size, adapted: bool) {
+        let list = if adapted {
+            &mut self.adapted
+        } else {
+            &mut self.skipped
+        };
+        match list
+            .iter_mut()
+            .find(|e| e.path == path && e.width == size.width && e.height == size.height)
+        {
+            Some(entry) => entry.frames += 1,
+            None => list.push(ResolutionEntry {
+                path: path.to_path_buf(),
+                width: size.width,
+                height: size.height,
+                frames: 1,
+            }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.adapted.is_empty() && self.skipped.is_empty()
+    }
+
+    /// Print a summary and write the report next to the output video
+    ///
+    /// The report is written as `<output stem>_resolution_report.json`.
+    pub fn save(&self) -> Result<Option<PathBuf>, ProcessError> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+
+        println!(
+            "Resolution report for {} ({}x{}, policy {:?}):",
+            self.output.display(),
+            self.output_width,
+            self.output_height,
+            self.policy
+        );
+        for entry in &self.adapted {
+            println!(
+                "  adapted {} ({}x{}, {} frames)",
+                entry.path.display(),
+                entry.width,
+                entry.height,
+                entry.frames
+            );
+        }
+        for entry in &self.skipped {
+            println!(
+                "  skipped {} ({}x{}, {} frames)",
+                entry.path.display(),
+                entry.width,
+                entry.height,
+                entry.frames
+            );
+        }
+
+        let stem = self
+            .output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+        let report_path = self
+            .output
+            .with_file_name(format!("{}_resolution_report.json", stem));
+        let json = serde_json::to_string_pretty(self).map_err(|e| {
+            ProcessError::ProcessingFailed(format!("Failed to serialize resolution report: {}", e))
+        })?;
+        fs::write(&report_path, json).map_err(|e| {
+            ProcessError::IoError(format!("Failed to write resolution report: {}", e))
+        })?;
+
+        Ok(Some(report_path))
+    }
+}
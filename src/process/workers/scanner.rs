@@ -1,17 +1,26 @@
-use crate::process::config::VideoExtractionConfig;
+use crate::process::config::{ScanOptions, VideoExtractionConfig};
+use crate::process::sniff::{MediaKind, SniffedFormat};
 use crate::process::types::ProcessError;
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use glob::Pattern;
 use path_clean::PathClean;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use walkdir::WalkDir;
 
 pub struct VideoScanner;
 
 impl VideoScanner {
     /// scan directories for video files
+    ///
+    /// Files are grouped by the directory that directly contains them. Files
+    /// in the input directory itself are keyed by the input string as given.
     pub fn scan(
         video_config: &VideoExtractionConfig,
     ) -> Result<HashMap<String, Vec<PathBuf>>, ProcessError> {
+        let filter = ScanFilter::new(&video_config.scan)?;
         let mut video_files_by_dir: HashMap<String, Vec<PathBuf>> = HashMap::new();
 
         for dir_path_str in &video_config.input_directories {
@@ -19,10 +28,7 @@ impl VideoScanner {
 
             if path.is_file() {
                 // Handle single file input
-                if matches!(
-                    path.extension().and_then(|s| s.to_str()),
-                    Some("mp4" | "mov" | "avi" | "mkv")
-                ) {
+                if Self::is_video_file(path) {
                     let parent = path.parent().unwrap_or_else(|| Path::new("."));
                     let parent_str = parent.to_string_lossy().to_string();
 
@@ -38,30 +44,7 @@ impl VideoScanner {
                 }
             } else if path.is_dir() {
                 // Handle directory input
-                let dir_path = path;
-                let video_files: Vec<PathBuf> = fs::read_dir(dir_path)
-                    .map_err(|e| {
-                        ProcessError::IoError(format!(
-                            "Failed to read directory {}: {}",
-                            dir_path.display(),
-                            e
-                        ))
-                    })?
-                    .filter_map(|entry| entry.ok())
-                    .filter(|entry| {
-                        let path = entry.path();
-                        path.is_file()
-                            && matches!(
-                                path.extension().and_then(|s| s.to_str()),
-                                Some("mp4" | "mov" | "avi" | "mkv")
-                            )
-                    })
-                    .map(|entry| entry.path().clean())
-                    .collect();
-
-                if !video_files.is_empty() {
-                    video_files_by_dir.insert(dir_path_str.to_string(), video_files);
-                }
+                Self::scan_directory(path, dir_path_str, &filter, &mut video_files_by_dir)?;
             } else {
                 eprintln!(
                     "Warning: Input path does not exist or is inaccessible: {}",
@@ -72,4 +55,215 @@ impl VideoScanner {
 
         Ok(video_files_by_dir)
     }
+
+    /// Whether a file is a video, judged by its extension (any case)
+    ///
+    /// Only files whose extension is unknown or ambiguous (`.ogg`) are opened
+    /// and sniffed, so a large scan doesn't read every file.
+    pub fn is_video_file(path: &Path) -> bool {
+        let by_extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(SniffedFormat::from_extension);
+        match by_extension {
+            Some(format) if !format.is_ambiguous() => format.media_kind() == MediaKind::Video,
+            _ => SniffedFormat::from_file(path)
+                .is_some_and(|format| format.media_kind() == MediaKind::Video),
+        }
+    }
+
+    /// Walk one input directory, following symlinks, and group matching videos
+    fn scan_directory(
+        dir_path: &Path,
+        dir_path_str: &str,
+        filter: &ScanFilter,
+        video_files_by_dir: &mut HashMap<String, Vec<PathBuf>>,
+    ) -> Result<(), ProcessError> {
+        // Symlinked files can be reached through more than one path
+        let mut seen: HashSet<PathBuf> = HashSet::new();
+
+        let walker = WalkDir::new(dir_path)
+            .follow_links(true)
+            .max_depth(filter.walk_depth())
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                entry.depth() == 0
+                    || !entry.file_type().is_dir()
+                    || !filter.is_excluded(entry.path(), dir_path)
+            });
+
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) if e.depth() == 0 => {
+                    return Err(ProcessError::IoError(format!(
+                        "Failed to read directory {}: {}",
+                        dir_path.display(),
+                        e
+                    )));
+                }
+                Err(e) => {
+                    if let Some(ancestor) = e.loop_ancestor() {
+                        eprintln!(
+                            "Warning: Skipping symlink loop at {} (points back to {})",
+                            e.path()
+                                .map(|p| p.display().to_string())
+                                .unwrap_or_default(),
+                            ancestor.display()
+                        );
+                    } else {
+                        eprintln!("Warning: Failed to read directory entry: {}", e);
+                    }
+                    continue;
+                }
+            };
+
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let file_path = entry.path();
+            if !filter.matches(file_path, dir_path) || !Self::is_video_file(file_path) {
+                continue;
+            }
+
+            let canonical = fs::canonicalize(file_path).unwrap_or_else(|_| file_path.to_path_buf());
+            if !seen.insert(canonical) {
+                continue;
+            }
+
+            let key = match file_path.parent() {
+                Some(parent) if parent != dir_path => parent.to_string_lossy().to_string(),
+                _ => dir_path_str.to_string(),
+            };
+            video_files_by_dir
+                .entry(key)
+                .or_default()
+                .push(file_path.clean());
+        }
+
+        Ok(())
+    }
+}
+
+/// Compiled form of `ScanOptions`
+struct ScanFilter {
+    recursive: bool,
+    max_depth: Option<usize>,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    min_size_bytes: Option<u64>,
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+}
+
+impl ScanFilter {
+    fn new(options: &ScanOptions) -> Result<Self, ProcessError> {
+        Ok(Self {
+            recursive: options.recursive,
+            max_depth: options.max_depth,
+            include: Self::compile(&options.include_globs)?,
+            exclude: Self::compile(&options.exclude_globs)?,
+            min_size_bytes: options.min_size_bytes,
+            modified_after: options
+                .modified_after
+                .as_deref()
+                .map(parse_time)
+                .transpose()?,
+            modified_before: options
+                .modified_before
+                .as_deref()
+                .map(parse_time)
+                .transpose()?,
+        })
+    }
+
+    fn compile(globs: &[String]) -> Result<Vec<Pattern>, ProcessError> {
+        globs
+            .iter()
+            .map(|glob| {
+                Pattern::new(glob).map_err(|e| {
+                    ProcessError::ConfigurationError(format!("Invalid glob '{}': {}", glob, e))
+                })
+            })
+            .collect()
+    }
+
+    /// `WalkDir` depth limit (the input directory is depth 0, its files depth 1)
+    fn walk_depth(&self) -> usize {
+        if self.recursive {
+            self.max_depth.map_or(usize::MAX, |depth| depth + 1)
+        } else {
+            1
+        }
+    }
+
+    /// Match a pattern against the entry name and its path relative to `root`
+    fn any_match(patterns: &[Pattern], path: &Path, root: &Path) -> bool {
+        let name = path.file_name().map(Path::new).unwrap_or(path);
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        patterns
+            .iter()
+            .any(|pattern| pattern.matches_path(name) || pattern.matches_path(relative))
+    }
+
+    fn is_excluded(&self, path: &Path, root: &Path) -> bool {
+        Self::any_match(&self.exclude, path, root)
+    }
+
+    fn matches(&self, path: &Path, root: &Path) -> bool {
+        if self.is_excluded(path, root) {
+            return false;
+        }
+        if !self.include.is_empty() && !Self::any_match(&self.include, path, root) {
+            return false;
+        }
+        if self.min_size_bytes.is_none()
+            && self.modified_after.is_none()
+            && self.modified_before.is_none()
+        {
+            return true;
+        }
+
+        let Ok(metadata) = fs::metadata(path) else {
+            return false;
+        };
+        if self.min_size_bytes.is_some_and(|min| metadata.len() < min) {
+            return false;
+        }
+        if self.modified_after.is_some() || self.modified_before.is_some() {
+            let Ok(modified) = metadata.modified() else {
+                return false;
+            };
+            if self.modified_after.is_some_and(|after| modified < after) {
+                return false;
+            }
+            if self
+                .modified_before
+                .is_some_and(|before| modified >= before)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Parse an RFC 3339 timestamp or a `YYYY-MM-DD` date (local midnight)
+fn parse_time(value: &str) -> Result<SystemTime, ProcessError> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(SystemTime::from(time));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+        .map(SystemTime::from)
+        .ok_or_else(|| {
+            ProcessError::ConfigurationError(format!(
+                "Invalid time '{}': expected RFC 3339 or YYYY-MM-DD",
+                value
+            ))
+        })
 }
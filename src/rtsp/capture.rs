@@ -1,10 +1,25 @@
-use crate::rtsp::types::HLSConfig;
+use crate::process::timeout::Deadline;
+use crate::rtsp::event::{EventConfig, EventRequest, EventTrigger};
+use crate::rtsp::ffmpeg_log::{FfmpegLogConfig, RotatingLog};
+use crate::rtsp::index::{SegmentIndex, SegmentIndexWorker, SegmentIndexer};
+use crate::rtsp::progress::{ProgressMonitor, StatsHandle};
+use crate::rtsp::shutdown::{DEFAULT_STOP_TIMEOUT, ShutdownToken, stop_child};
+use crate::rtsp::snapshot::SnapshotConfig;
+use crate::rtsp::supervisor::{CameraEvent, StatusHandle, Supervisor, SupervisorConfig};
+use crate::rtsp::types::{CameraSettings, HLSConfig, RecordingBackend, RtspTransport};
+use crate::rtsp::url::RtspUrl;
 use opencv::{Result, videoio};
+use std::path::PathBuf;
 use std::process::Child;
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::time::{Duration, Instant};
 
+/// Extension of recorded segments, in both the ffmpeg and OpenCV backends
+pub(crate) const SEGMENT_EXTENSION: &str = "mp4";
+
 pub struct RTSPCapture {
-    pub url: String,
+    /// Stream source; prints redacted, see `RtspUrl::connection_url`
+    pub url: RtspUrl,
     pub output_dir: String,
     pub show_preview: bool,
     pub capture: Option<videoio::VideoCapture>,
@@ -16,11 +31,40 @@ pub struct RTSPCapture {
     pub custom_fps: f64,
     pub hls_config: Option<HLSConfig>,
     pub run_once: bool,
+    /// Stop capturing after this much time (bounded capture)
+    pub max_duration: Option<Duration>,
+    /// Stop capturing after this many frames (bounded capture)
+    pub max_frames: Option<u64>,
+    /// Hard time budget for bounded captures; ffmpeg is killed when it passes
+    pub deadline: Deadline,
+    /// Health tracking and restart policy for the FFmpeg and HLS modes
+    pub supervisor: Supervisor,
+    /// Stops the capture loops and their ffmpeg child when triggered
+    pub shutdown: ShutdownToken,
+    /// RTSP transport for the ffmpeg modes
+    pub transport: RtspTransport,
+    /// Audio in the ffmpeg modes; `None` drops it when recording and copies it for HLS
+    pub audio: Option<bool>,
+    /// Records closed segments in the segment index (recording modes only)
+    pub segment_indexer: Option<SegmentIndexWorker>,
+    /// Record clips around events instead of continuously (see `rtsp::event`)
+    pub event_config: Option<EventConfig>,
+    pub(crate) event_trigger: EventTrigger,
+    pub(crate) event_receiver: Receiver<EventRequest>,
+    /// Take periodic JPEG snapshots instead of recording (see `rtsp::snapshot`)
+    pub snapshot_config: Option<SnapshotConfig>,
+    /// Rotating log of ffmpeg diagnostics (see `rtsp::ffmpeg_log`)
+    pub ffmpeg_log: Option<FfmpegLogConfig>,
+    pub(crate) stats_log: Option<RotatingLog>,
+    /// Live ffmpeg statistics, kept across restarts
+    pub(crate) stats: StatsHandle,
+    /// Reader for the running ffmpeg child's progress and stderr
+    pub(crate) progress: Option<ProgressMonitor>,
 }
 
 impl RTSPCapture {
     pub fn new(
-        url: String,
+        url: impl Into<RtspUrl>,
         output_dir: String,
         show_preview: bool,
         segment_duration_secs: u64,
@@ -29,6 +73,10 @@ impl RTSPCapture {
         hls_config: Option<HLSConfig>,
         run_once: bool,
     ) -> Result<Self> {
+        let url = url.into();
+        let supervisor = Supervisor::new(url.camera_id(), SupervisorConfig::default());
+        let stats = StatsHandle::new(url.camera_id());
+        let (event_sender, event_receiver) = mpsc::channel();
         Ok(Self {
             url,
             output_dir,
@@ -42,9 +90,172 @@ impl RTSPCapture {
             custom_fps,
             hls_config,
             run_once,
+            max_duration: None,
+            max_frames: None,
+            deadline: Deadline::unlimited(),
+            supervisor,
+            shutdown: ShutdownToken::new(),
+            transport: RtspTransport::default(),
+            audio: None,
+            segment_indexer: None,
+            event_config: None,
+            event_trigger: EventTrigger::new(event_sender),
+            event_receiver,
+            snapshot_config: None,
+            ffmpeg_log: None,
+            stats_log: None,
+            stats,
+            progress: None,
         })
     }
 
+    /// Create a capture for one resolved camera from `StreamConfig::camera_settings`
+    pub fn from_camera(camera: &CameraSettings, run_once: bool) -> Result<Self> {
+        let capture = Self::new(
+            camera.url.clone(),
+            camera.output_directory.clone(),
+            camera.show_preview,
+            camera.segment_duration,
+            camera.backend == RecordingBackend::Opencv,
+            camera.fps,
+            Some(camera.hls.clone()),
+            run_once,
+        )?;
+        let capture = capture
+            .with_transport(camera.transport)
+            .with_audio(camera.audio);
+        Ok(match camera.backend {
+            RecordingBackend::Event => capture.with_event_mode(camera.event.clone()),
+            RecordingBackend::Snapshot => capture.with_snapshot_mode(camera.snapshot.clone()),
+            _ => capture,
+        })
+    }
+
+    /// Bound the capture by duration and/or frame count
+    pub fn with_limits(mut self, max_duration: Option<Duration>, max_frames: Option<u64>) -> Self {
+        self.max_duration = max_duration;
+        self.max_frames = max_frames;
+        self
+    }
+
+    /// Kill a bounded capture that is still running when `deadline` passes
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Use custom restart and health settings
+    pub fn with_supervisor_config(mut self, config: SupervisorConfig) -> Self {
+        self.supervisor.set_config(config);
+        self
+    }
+
+    /// Send camera state changes to `sender`
+    pub fn with_event_sender(mut self, sender: Sender<CameraEvent>) -> Self {
+        self.supervisor.set_event_sender(sender);
+        self
+    }
+
+    /// Stop capturing when `shutdown` is triggered
+    pub fn with_shutdown(mut self, shutdown: ShutdownToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Use UDP or TCP for RTSP sources
+    pub fn with_transport(mut self, transport: RtspTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Keep (`Some(true)`) or drop (`Some(false)`) audio in the ffmpeg modes
+    pub fn with_audio(mut self, audio: Option<bool>) -> Self {
+        self.audio = audio;
+        self
+    }
+
+    /// Only record clips around events (see `event_trigger`)
+    pub fn with_event_mode(mut self, config: EventConfig) -> Self {
+        self.event_config = Some(config);
+        self
+    }
+
+    /// Only write a JPEG snapshot every `interval_secs`
+    pub fn with_snapshot_mode(mut self, config: SnapshotConfig) -> Self {
+        self.snapshot_config = Some(config);
+        self
+    }
+
+    /// Add every closed segment to `index`
+    ///
+    /// Call after `with_alias`, since the camera id is taken from the URL here.
+    pub fn with_segment_index(mut self, index: SegmentIndex) -> Self {
+        let indexer = SegmentIndexer::new(index, self.url.camera_id(), self.camera_dir())
+            .with_extension(SEGMENT_EXTENSION);
+        self.segment_indexer = Some(SegmentIndexWorker::spawn(indexer));
+        self
+    }
+
+    /// Log ffmpeg's stderr and progress statistics as configured
+    pub fn with_ffmpeg_log(mut self, config: FfmpegLogConfig) -> Self {
+        self.ffmpeg_log = config.enabled.then_some(config);
+        self
+    }
+
+    /// Handle for querying this camera's health from another thread
+    pub fn status_handle(&self) -> StatusHandle {
+        self.supervisor.handle()
+    }
+
+    /// Handle for reading this camera's live ffmpeg statistics from another thread
+    pub fn stats_handle(&self) -> StatsHandle {
+        self.stats.clone()
+    }
+
+    /// Name this camera; the alias replaces the URL hash in directory names
+    pub fn with_alias(mut self, alias: &str) -> Self {
+        self.url = self.url.clone().with_alias(alias);
+        self.supervisor.set_camera(self.url.camera_id());
+        self.stats.set_camera(self.url.camera_id());
+        self
+    }
+
+    /// Directory that receives this stream's recordings
+    pub fn camera_dir(&self) -> PathBuf {
+        PathBuf::from(&self.output_dir).join(format!("camera_{}", self.url.camera_id()))
+    }
+
+    /// Whether the source is an RTSP stream (enables RTSP transport options)
+    pub fn is_rtsp_source(&self) -> bool {
+        matches!(self.url.scheme(), "rtsp" | "rtsps")
+    }
+
+    /// Whether the source is a live network stream rather than a local file
+    pub fn is_network_source(&self) -> bool {
+        !self.url.is_local()
+    }
+
+    /// Queue the segments that are no longer being written for indexing
+    ///
+    /// `include_newest` once recording has stopped, so the last segment is closed too.
+    /// Probing runs on the index worker thread, not here.
+    pub fn index_segments(&mut self, include_newest: bool) {
+        if let Some(indexer) = &self.segment_indexer {
+            indexer.request(include_newest);
+        }
+    }
+
+    /// Stop the running ffmpeg child, letting it finalize the current segment
+    pub fn stop_ffmpeg(&mut self) {
+        self.progress = None;
+        if let Some(mut process) = self.ffmpeg_process.take() {
+            match stop_child(&mut process, DEFAULT_STOP_TIMEOUT) {
+                Ok(status) => println!("🛑 FFmpeg for {} stopped ({})", self.url, status),
+                Err(e) => eprintln!("❌ Failed to stop FFmpeg for {}: {}", self.url, e),
+            }
+        }
+    }
+
     pub fn process_stream(&mut self) -> Result<()> {
         // Priority 1: Check HLS mode first
         if let Some(ref config) = self.hls_config {
@@ -59,12 +270,22 @@ impl RTSPCapture {
             }
         }
 
-        // Priority 2: OpenCV mode with custom FPS
+        // Priority 2: Event mode (clips around triggers only)
+        if self.event_config.is_some() {
+            return self.process_stream_event();
+        }
+
+        // Priority 3: Snapshot mode (one JPEG per interval)
+        if self.snapshot_config.is_some() {
+            return self.process_stream_snapshot();
+        }
+
+        // Priority 4: OpenCV mode with custom FPS
         if self.use_custom_fps {
             self.start_opencv_recording()?;
             self.process_stream_opencv()
         }
-        // Priority 3: FFmpeg mode (default)
+        // Priority 5: FFmpeg mode (default)
         else {
             self.start_ffmpeg_recording().map_err(|e| {
                 opencv::Error::new(
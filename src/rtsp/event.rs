@@ -0,0 +1,569 @@
+//! Event-triggered recording with a pre-roll buffer
+//!
+//! In event mode the stream is decoded continuously but only written to disk
+//! around events. The last `pre_roll_secs` of frames are kept in memory. When
+//! a trigger arrives they are written to a clip, followed by `post_roll_secs`
+//! of live frames. Triggers that arrive while a clip is open extend it.
+//!
+//! Triggers come from an `EventTrigger` handle (library API), from files
+//! dropped into `trigger_directory`, or from `analysis::MotionDetector` running
+//! on a downscaled copy of every `frame_interval`-th frame. Clips are written
+//! to `<camera dir>/<clip_subdirectory>/event_<id>_<YYYYmmdd_HHMMSS>.mp4`.
+
+use crate::analysis::{MotionConfig, MotionDetector};
+use crate::rtsp::capture::RTSPCapture;
+use crate::rtsp::opencv::path_str;
+use chrono::{DateTime, Local};
+use opencv::{
+    Result,
+    core::{Rect, Size},
+    imgproc,
+    prelude::*,
+    videoio,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often `trigger_directory` is checked for new files
+const DROP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Event mode settings
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct EventConfig {
+    /// Seconds of frames kept in memory and written before the trigger
+    ///
+    /// Frames are stored decoded, so this costs roughly
+    /// `width * height * 3 * fps * pre_roll_secs` bytes of memory.
+    pub pre_roll_secs: f64,
+    /// Seconds recorded after the last trigger
+    pub post_roll_secs: f64,
+    /// Every file dropped into this directory triggers an event named after
+    /// the file (the file is removed)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_directory: Option<String>,
+    /// Trigger on motion
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub motion: Option<MotionTriggerConfig>,
+    /// Clip directory inside the camera directory
+    pub clip_subdirectory: String,
+}
+
+impl Default for EventConfig {
+    fn default() -> Self {
+        Self {
+            pre_roll_secs: 5.0,
+            post_roll_secs: 10.0,
+            trigger_directory: None,
+            motion: None,
+            clip_subdirectory: "events".to_string(),
+        }
+    }
+}
+
+/// Motion trigger settings
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MotionTriggerConfig {
+    /// Detector settings, as in `analysis` motion mode (`frame_skip` is ignored);
+    /// `roi` is in pixels of the full-resolution stream
+    pub detector: MotionConfig,
+    /// Analyze every Nth decoded frame
+    pub frame_interval: u32,
+    /// Frames are downscaled to this width before analysis
+    pub analysis_width: i32,
+}
+
+impl Default for MotionTriggerConfig {
+    fn default() -> Self {
+        Self {
+            detector: MotionConfig::default(),
+            frame_interval: 5,
+            analysis_width: 320,
+        }
+    }
+}
+
+impl MotionTriggerConfig {
+    /// Detector for `analysis_frame` output: the ROI is already cropped there
+    pub fn create_detector(&self) -> Result<MotionDetector> {
+        let config = MotionConfig {
+            roi: None,
+            ..self.detector.clone()
+        };
+        MotionDetector::new(config).map_err(|e| {
+            opencv::Error::new(
+                opencv::core::StsError,
+                &format!("Failed to create motion detector: {}", e),
+            )
+        })
+    }
+
+    /// The part of `frame` to analyze: the ROI, cropped at full resolution
+    /// (clipped to the frame), then downscaled to `analysis_width`
+    pub fn analysis_frame(&self, frame: &Mat) -> Result<Mat> {
+        let Some(roi) = &self.detector.roi else {
+            return downscale(frame, self.analysis_width);
+        };
+        let (x, y) = (roi.x.max(0), roi.y.max(0));
+        let right = roi.x.saturating_add(roi.width).min(frame.cols());
+        let bottom = roi.y.saturating_add(roi.height).min(frame.rows());
+        if right <= x || bottom <= y {
+            return Err(opencv::Error::new(
+                opencv::core::StsError,
+                &format!(
+                    "Motion ROI {}x{}+{}+{} is outside the {}x{} frame",
+                    roi.width,
+                    roi.height,
+                    roi.x,
+                    roi.y,
+                    frame.cols(),
+                    frame.rows()
+                ),
+            ));
+        }
+        let rect = Rect::new(x, y, right - x, bottom - y);
+        let cropped = Mat::roi(frame, rect)?.try_clone()?;
+        downscale(&cropped, self.analysis_width)
+    }
+}
+
+/// Where an event came from
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TriggerSource {
+    Api,
+    File,
+    Motion,
+}
+
+impl fmt::Display for TriggerSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TriggerSource::Api => write!(f, "api"),
+            TriggerSource::File => write!(f, "file"),
+            TriggerSource::Motion => write!(f, "motion"),
+        }
+    }
+}
+
+/// A request to record an event clip
+#[derive(Debug, Clone)]
+pub struct EventRequest {
+    pub id: String,
+    pub source: TriggerSource,
+}
+
+/// Handle for triggering event clips from another thread
+#[derive(Debug, Clone)]
+pub struct EventTrigger {
+    sender: Sender<EventRequest>,
+    counter: Arc<AtomicU64>,
+}
+
+impl EventTrigger {
+    pub fn new(sender: Sender<EventRequest>) -> Self {
+        Self {
+            sender,
+            counter: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Record a clip around now; returns the event id
+    ///
+    /// Without an `id` one is generated. Triggers are ignored once the
+    /// capture has stopped.
+    pub fn trigger(&self, id: Option<&str>) -> String {
+        let id = match id {
+            Some(id) => sanitize_event_id(id),
+            None => self.next_id(TriggerSource::Api),
+        };
+        let _ = self.sender.send(EventRequest {
+            id: id.clone(),
+            source: TriggerSource::Api,
+        });
+        id
+    }
+
+    /// A new id like `motion-3`
+    pub fn next_id(&self, source: TriggerSource) -> String {
+        format!(
+            "{}-{}",
+            source,
+            self.counter.fetch_add(1, Ordering::Relaxed)
+        )
+    }
+}
+
+/// Items from the last `max_age`, oldest first
+#[derive(Debug)]
+pub struct PreRollBuffer<T> {
+    items: VecDeque<(Instant, T)>,
+    max_age: Duration,
+}
+
+impl<T> PreRollBuffer<T> {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            items: VecDeque::new(),
+            max_age,
+        }
+    }
+
+    /// Add an item received at `at` and drop the ones older than `max_age`
+    pub fn push(&mut self, at: Instant, item: T) {
+        self.items.push_back((at, item));
+        while self
+            .items
+            .front()
+            .is_some_and(|(oldest, _)| at.saturating_duration_since(*oldest) > self.max_age)
+        {
+            self.items.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Remove and return everything, oldest first
+    pub fn drain(&mut self) -> Vec<T> {
+        self.items.drain(..).map(|(_, item)| item).collect()
+    }
+}
+
+/// File name of an event clip
+pub fn event_clip_name(id: &str, at: DateTime<Local>) -> String {
+    format!(
+        "event_{}_{}.mp4",
+        sanitize_event_id(id),
+        at.format("%Y%m%d_%H%M%S")
+    )
+}
+
+/// Take the files dropped into `directory` and return their names as event ids
+///
+/// Each file is removed so it triggers only once. Hidden files (e.g. editor
+/// temp files) are left alone.
+pub fn take_dropped_triggers(directory: &Path) -> io::Result<Vec<String>> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| !name.starts_with('.'))
+        })
+        .collect();
+    files.sort();
+
+    let mut ids = Vec::new();
+    for path in files {
+        fs::remove_file(&path)?;
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        ids.push(sanitize_event_id(&stem));
+    }
+    Ok(ids)
+}
+
+/// Keep `[A-Za-z0-9_-]`, replacing everything else with `_`
+fn sanitize_event_id(id: &str) -> String {
+    let id: String = id
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if id.is_empty() {
+        "event".to_string()
+    } else {
+        id
+    }
+}
+
+/// A clip being written
+struct EventClip {
+    id: String,
+    path: PathBuf,
+    writer: videoio::VideoWriter,
+    until: Instant,
+    frames: u64,
+}
+
+impl RTSPCapture {
+    /// Handle for triggering event clips from another thread (event mode only)
+    pub fn event_trigger(&self) -> EventTrigger {
+        self.event_trigger.clone()
+    }
+
+    /// Decode the stream and record clips around triggers
+    ///
+    /// Runs until `self.shutdown` is triggered; an open clip is finished
+    /// first. A lost stream closes the open clip and is reopened after the
+    /// supervisor's backoff. In `run_once` mode the first lost stream ends it.
+    pub fn process_stream_event(&mut self) -> Result<()> {
+        let config = self.event_config.clone().ok_or_else(|| {
+            opencv::Error::new(opencv::core::StsError, "Event config not provided")
+        })?;
+
+        let clip_dir = self.camera_dir().join(&config.clip_subdirectory);
+        fs::create_dir_all(&clip_dir).map_err(|e| {
+            opencv::Error::new(
+                opencv::core::StsError,
+                &format!("Failed to create directory: {}", e),
+            )
+        })?;
+
+        // Created up front so it's clear where this camera's triggers go
+        if let Some(directory) = &config.trigger_directory
+            && let Err(e) = fs::create_dir_all(directory)
+        {
+            eprintln!("⚠️ Failed to create trigger directory {}: {}", directory, e);
+        }
+
+        let mut fps = self.recording_fps()?;
+        let post_roll = Duration::from_secs_f64(config.post_roll_secs.max(0.0));
+        let mut buffer = PreRollBuffer::new(Duration::from_secs_f64(config.pre_roll_secs.max(0.0)));
+        let mut detector = match &config.motion {
+            Some(motion) => Some(motion.create_detector()?),
+            None => None,
+        };
+
+        println!(
+            "🎯 Event mode for {}: {:.1}s pre-roll, {:.1}s post-roll, clips in {}",
+            self.url,
+            config.pre_roll_secs,
+            config.post_roll_secs,
+            clip_dir.display()
+        );
+
+        let mut clip: Option<EventClip> = None;
+        let mut frame = Mat::default();
+        let mut frame_index: u64 = 0;
+        let mut last_drop_check: Option<Instant> = None;
+
+        loop {
+            if self.shutdown.is_triggered() {
+                println!("🛑 Stopping event recording for {}", self.url);
+                break;
+            }
+
+            if self.capture.is_none() {
+                match self.start_opencv_recording() {
+                    Ok(()) => {
+                        self.supervisor.started();
+                        fps = self.recording_fps()?;
+                    }
+                    Err(e) => {
+                        let delay = self
+                            .supervisor
+                            .failed(format!("Failed to open stream: {}", e));
+                        if self.run_once {
+                            self.supervisor.stopped(None);
+                            return Err(e);
+                        }
+                        self.shutdown.sleep(delay);
+                        continue;
+                    }
+                }
+            }
+            let Some(capture) = &mut self.capture else {
+                continue;
+            };
+
+            if !matches!(capture.read(&mut frame), Ok(true)) || frame.empty() {
+                self.capture = None;
+                // Frames from before the gap would be stitched to the next trigger
+                buffer.drain();
+                if let Some(finished) = clip.take() {
+                    Self::finish_event_clip(finished)?;
+                }
+                if self.run_once {
+                    break;
+                }
+                eprintln!("❌ Stream lost for {}, reconnecting...", self.url);
+                let delay = self.supervisor.failed("Stream ended".to_string());
+                self.shutdown.sleep(delay);
+                continue;
+            }
+            let now = Instant::now();
+            self.supervisor.observe_output(Some(SystemTime::now()));
+
+            let mut triggers: Vec<EventRequest> = self.event_receiver.try_iter().collect();
+
+            if let Some(directory) = &config.trigger_directory
+                && last_drop_check.is_none_or(|last| now.duration_since(last) >= DROP_POLL_INTERVAL)
+            {
+                last_drop_check = Some(now);
+                match take_dropped_triggers(Path::new(directory)) {
+                    Ok(ids) => triggers.extend(ids.into_iter().map(|id| EventRequest {
+                        id,
+                        source: TriggerSource::File,
+                    })),
+                    Err(e) => eprintln!("⚠️ Failed to read trigger directory {}: {}", directory, e),
+                }
+            }
+
+            if let (Some(detector), Some(motion)) = (&mut detector, &config.motion)
+                && frame_index.is_multiple_of(u64::from(motion.frame_interval.max(1)))
+            {
+                match motion
+                    .analysis_frame(&frame)
+                    .map_err(|e| e.to_string())
+                    .and_then(|small| detector.detect(&small).map_err(|e| e.to_string()))
+                {
+                    Ok(regions) if !regions.is_empty() => triggers.push(EventRequest {
+                        id: self.event_trigger.next_id(TriggerSource::Motion),
+                        source: TriggerSource::Motion,
+                    }),
+                    Ok(_) => {}
+                    Err(e) => eprintln!("⚠️ Motion detection failed for {}: {}", self.url, e),
+                }
+            }
+            frame_index += 1;
+
+            for trigger in triggers {
+                if let Some(open) = &mut clip {
+                    open.until = now + post_roll;
+                    continue;
+                }
+                let mut opened = self.open_event_clip(&clip_dir, &trigger, fps, &frame)?;
+                for buffered in buffer.drain() {
+                    opened.writer.write(&buffered)?;
+                    opened.frames += 1;
+                }
+                opened.until = now + post_roll;
+                clip = Some(opened);
+            }
+
+            match &mut clip {
+                Some(open) => {
+                    open.writer.write(&frame)?;
+                    open.frames += 1;
+                }
+                None => buffer.push(now, frame.try_clone()?),
+            }
+            if clip.as_ref().is_some_and(|open| now >= open.until)
+                && let Some(finished) = clip.take()
+            {
+                Self::finish_event_clip(finished)?;
+            }
+        }
+
+        if let Some(finished) = clip.take() {
+            Self::finish_event_clip(finished)?;
+        }
+        self.capture = None;
+        self.supervisor.stopped(None);
+        Ok(())
+    }
+
+    /// Output FPS for clips: the custom FPS, else the stream's, else 30
+    fn recording_fps(&self) -> Result<f64> {
+        let stream_fps = match &self.capture {
+            Some(capture) => capture.get(videoio::CAP_PROP_FPS)?,
+            None => 0.0,
+        };
+        Ok(if self.use_custom_fps {
+            self.custom_fps
+        } else if stream_fps > 0.0 {
+            stream_fps
+        } else {
+            30.0
+        })
+    }
+
+    fn open_event_clip(
+        &self,
+        clip_dir: &Path,
+        trigger: &EventRequest,
+        fps: f64,
+        frame: &Mat,
+    ) -> Result<EventClip> {
+        let path = clip_dir.join(event_clip_name(&trigger.id, Local::now()));
+        let fourcc = videoio::VideoWriter::fourcc('m', 'p', '4', 'v')?;
+        let writer = videoio::VideoWriter::new(
+            path_str(&path)?,
+            fourcc,
+            fps,
+            Size::new(frame.cols(), frame.rows()),
+            true,
+        )?;
+        if !writer.is_opened()? {
+            return Err(opencv::Error::new(
+                opencv::core::StsError,
+                "Failed to create video writer",
+            ));
+        }
+        println!(
+            "🔴 Event {} ({}) on {}: recording {}",
+            trigger.id,
+            trigger.source,
+            self.url,
+            path.display()
+        );
+        Ok(EventClip {
+            id: trigger.id.clone(),
+            path,
+            writer,
+            until: Instant::now(),
+            frames: 0,
+        })
+    }
+
+    fn finish_event_clip(mut clip: EventClip) -> Result<()> {
+        clip.writer.release()?;
+        println!(
+            "✅ Event {} saved: {} ({} frames)",
+            clip.id,
+            clip.path.display(),
+            clip.frames
+        );
+        Ok(())
+    }
+}
+
+/// Copy of `frame` scaled down to `width` (unchanged if it is already narrower)
+fn downscale(frame: &Mat, width: i32) -> Result<Mat> {
+    if width <= 0 || frame.cols() <= width {
+        return frame.try_clone();
+    }
+    let height = (frame.rows() as f64 * width as f64 / frame.cols() as f64).round() as i32;
+    let mut small = Mat::default();
+    imgproc::resize(
+        frame,
+        &mut small,
+        Size::new(width, height.max(1)),
+        0.0,
+        0.0,
+        imgproc::INTER_AREA,
+    )?;
+    Ok(small)
+}
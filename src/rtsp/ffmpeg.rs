@@ -1,10 +1,14 @@
-use crate::rtsp::capture::RTSPCapture;
+use crate::process::timeout::wait_with_deadline;
+use crate::rtsp::capture::{RTSPCapture, SEGMENT_EXTENSION};
+use crate::rtsp::ffmpeg_log::RotatingLog;
+use crate::rtsp::progress::ProgressMonitor;
+use crate::rtsp::types::CaptureSummary;
+use crate::rtsp::watchdog::{OutputSnapshot, StallWatchdog};
 use opencv::Result;
 use std::fs;
-use std::path::PathBuf;
-use std::process::{Command, Stdio};
-use std::thread;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant, SystemTime};
 
 impl RTSPCapture {
     /// Start HLS (HTTP Live Streaming) output
@@ -30,14 +34,24 @@ impl RTSPCapture {
             .arg("-y")
             .arg("-loglevel")
             .arg("error")
+            .arg("-progress")
+            .arg("pipe:1")
+            .arg("-nostats")
             .arg("-rtsp_transport")
-            .arg("tcp")
+            .arg(self.transport.as_str())
             .arg("-i")
-            .arg(&self.url)
+            .arg(self.url.connection_url())
             .arg("-c:v")
-            .arg("copy")
-            .arg("-c:a")
-            .arg("copy")
+            .arg("copy");
+
+        // Audio is copied unless explicitly disabled
+        if self.audio == Some(false) {
+            command.arg("-an");
+        } else {
+            command.arg("-c:a").arg("copy");
+        }
+
+        command
             .arg("-f")
             .arg("hls")
             .arg("-hls_time")
@@ -55,14 +69,19 @@ impl RTSPCapture {
 
         command.arg(playlist_path.to_str().unwrap());
 
-        println!("🎬 Starting HLS streaming: {:?}", command);
+        println!(
+            "🎬 Starting HLS streaming: {}",
+            self.url.scrub(&format!("{:?}", command))
+        );
 
-        let process = command
-            .stdin(Stdio::null())
+        // stdin stays open so the child can be asked to quit (see `stop_ffmpeg`)
+        let mut process = command
+            .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()?;
 
+        self.monitor_child(&mut process);
         self.ffmpeg_process = Some(process);
         Ok(())
     }
@@ -72,100 +91,15 @@ impl RTSPCapture {
     /// Similar to process_stream_ffmpeg() but for HLS mode.
     /// Monitors the FFmpeg HLS process and restarts on failure.
     pub fn process_stream_hls(&mut self) -> Result<()> {
-        let mut consecutive_failures = 0;
-        let max_failures = 3;
-
-        loop {
-            if self.ffmpeg_process.is_none() {
-                match self.start_hls_streaming() {
-                    Ok(_) => {
-                        println!("📺 Successfully started HLS streaming for {}", self.url);
-                        consecutive_failures = 0;
-                    }
-                    Err(e) => {
-                        eprintln!("❌ Failed to start HLS streaming for {}: {}", self.url, e);
-                        consecutive_failures += 1;
-                        if consecutive_failures >= max_failures {
-                            if self.run_once {
-                                return Err(opencv::Error::new(
-                                    opencv::core::StsError,
-                                    "Failed to start HLS streaming in run_once mode",
-                                ));
-                            }
-                            thread::sleep(Duration::from_secs(10));
-                        } else {
-                            thread::sleep(Duration::from_secs(1));
-                        }
-                        continue;
-                    }
-                }
-            }
-
-            if let Some(process) = &mut self.ffmpeg_process {
-                match process.try_wait() {
-                    Ok(Some(status)) => {
-                        println!("HLS process for {} ended with status: {}", self.url, status);
-                        if !status.success() {
-                            eprintln!("❌ HLS process failed for {}, restarting...", self.url);
-                            consecutive_failures += 1;
-                        } else if self.run_once {
-                            println!("✅ HLS process finished successfully in run_once mode.");
-                            return Ok(());
-                        }
-
-                        self.ffmpeg_process = None;
-
-                        if self.run_once && !status.success() {
-                            return Err(opencv::Error::new(
-                                opencv::core::StsError,
-                                "HLS process failed in run_once mode",
-                            ));
-                        }
-
-                        if consecutive_failures >= max_failures {
-                            thread::sleep(Duration::from_secs(10));
-                        } else {
-                            thread::sleep(Duration::from_secs(1));
-                        }
-                    }
-                    Ok(None) => {
-                        // Process still running
-                        consecutive_failures = 0;
-                        thread::sleep(Duration::from_secs(1));
-                    }
-                    Err(e) => {
-                        eprintln!("❌ Error checking HLS process for {}: {}", self.url, e);
-                        self.ffmpeg_process = None;
-                        consecutive_failures += 1;
-                        if self.run_once {
-                            return Err(opencv::Error::new(
-                                opencv::core::StsError,
-                                &format!("Error checking HLS process: {}", e),
-                            ));
-                        }
-                        if consecutive_failures >= max_failures {
-                            thread::sleep(Duration::from_secs(10));
-                        } else {
-                            thread::sleep(Duration::from_secs(1));
-                        }
-                    }
-                }
-            }
-        }
+        self.supervise_ffmpeg(true)
     }
 
     pub fn start_ffmpeg_recording(&mut self) -> std::io::Result<()> {
-        let camera_dir = PathBuf::from(&self.output_dir).join(format!(
-            "camera_{}",
-            self.url
-                .replace("://", "_")
-                .replace("/", "_")
-                .replace(":", "_")
-        ));
+        let camera_dir = self.camera_dir();
         fs::create_dir_all(&camera_dir)?;
 
         let output_pattern = camera_dir
-            .join("segment_%Y%m%d_%H%M%S.mp4")
+            .join(format!("segment_%Y%m%d_%H%M%S.{}", SEGMENT_EXTENSION))
             .to_str()
             .unwrap()
             .to_string();
@@ -175,15 +109,40 @@ impl RTSPCapture {
             "-y".to_string(),
             "-loglevel".to_string(),
             "error".to_string(),
-            "-rtsp_transport".to_string(),
-            "tcp".to_string(),
-            "-use_wallclock_as_timestamps".to_string(),
-            "1".to_string(),
+            "-progress".to_string(),
+            "pipe:1".to_string(),
+            "-nostats".to_string(),
+        ];
+
+        if self.is_rtsp_source() {
+            args.push("-rtsp_transport".to_string());
+            args.push(self.transport.as_str().to_string());
+        }
+        if self.is_network_source() {
+            args.push("-use_wallclock_as_timestamps".to_string());
+            args.push("1".to_string());
+        }
+        // Files are read at their native rate; faster than realtime, several
+        // segments would start within one second and share a file name
+        if self.url.is_local() {
+            args.push("-re".to_string());
+        }
+
+        args.extend([
             "-i".to_string(),
-            self.url.clone(),
+            self.url.connection_url(),
             "-c:v".to_string(),
             "copy".to_string(),
-            "-an".to_string(),
+        ]);
+
+        // Audio is dropped unless explicitly enabled (AAC for MP4 compatibility)
+        if self.audio == Some(true) {
+            args.extend(["-c:a".to_string(), "aac".to_string()]);
+        } else {
+            args.push("-an".to_string());
+        }
+
+        args.extend([
             "-f".to_string(),
             "segment".to_string(),
             "-segment_time".to_string(),
@@ -198,118 +157,355 @@ impl RTSPCapture {
             "0.05".to_string(),
             "-strftime".to_string(),
             "1".to_string(),
-            "-reconnect_at_eof".to_string(),
-            "1".to_string(),
-            "-reconnect_streamed".to_string(),
-            "1".to_string(),
-            "-reconnect_delay_max".to_string(),
-            "120".to_string(),
-        ];
+        ]);
 
-        if self.run_once {
+        if self.is_network_source() {
+            args.extend([
+                "-reconnect_at_eof".to_string(),
+                "1".to_string(),
+                "-reconnect_streamed".to_string(),
+                "1".to_string(),
+                "-reconnect_delay_max".to_string(),
+                "120".to_string(),
+            ]);
+        }
+
+        if let Some(max_duration) = self.max_duration {
+            args.push("-t".to_string());
+            args.push(format!("{:.3}", max_duration.as_secs_f64()));
+        } else if self.run_once {
             // Run for slightly longer than one segment to ensure it finishes
             args.push("-t".to_string());
             args.push((self.segment_duration.as_secs() + 5).to_string());
         }
 
+        if let Some(max_frames) = self.max_frames {
+            args.push("-frames:v".to_string());
+            args.push(max_frames.to_string());
+        }
+
         args.push(output_pattern);
 
         command.args(&args);
 
-        println!("Starting FFmpeg with command: {:?}", command);
+        println!(
+            "Starting FFmpeg with command: {}",
+            self.url.scrub(&format!("{:?}", command))
+        );
 
-        let process = command
-            .stdin(Stdio::null())
+        // stdin stays open so the child can be asked to quit (see `stop_ffmpeg`)
+        let mut process = command
+            .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()?;
 
+        self.monitor_child(&mut process);
         self.ffmpeg_process = Some(process);
         Ok(())
     }
 
+    /// Record segmented MP4 files until the capture limits are reached
+    ///
+    /// Runs a single FFmpeg recording to completion instead of supervising it,
+    /// so at least one of `max_duration` / `max_frames` should be set for live
+    /// sources. Local files simply stop at end of input. A source that stalls
+    /// is killed when `deadline` passes.
+    ///
+    /// # Returns
+    /// - `Ok(CaptureSummary)` with the segments written during this run
+    /// - `Err` if FFmpeg fails to start or exits with an error
+    pub fn record_segments(&mut self) -> Result<CaptureSummary> {
+        let started_at = SystemTime::now();
+
+        self.start_ffmpeg_recording().map_err(|e| {
+            opencv::Error::new(
+                opencv::core::StsError,
+                &format!("Failed to start FFmpeg: {}", e),
+            )
+        })?;
+
+        let mut process = self.ffmpeg_process.take().ok_or_else(|| {
+            opencv::Error::new(opencv::core::StsError, "FFmpeg process was not started")
+        })?;
+        let waited = wait_with_deadline(&mut process, &self.deadline, "FFmpeg recording");
+        let last_error = self.finish_monitor();
+        let status =
+            waited.map_err(|e| opencv::Error::new(opencv::core::StsError, &e.to_string()))?;
+
+        if !status.success() {
+            eprintln!("❌ FFmpeg recording failed for {}", self.url);
+            if let Some(line) = last_error {
+                eprintln!("stderr: {}", line);
+            }
+            return Err(opencv::Error::new(
+                opencv::core::StsError,
+                &format!("FFmpeg recording exited with status: {}", status),
+            ));
+        }
+
+        let mut summary = CaptureSummary::default();
+        let entries = fs::read_dir(self.camera_dir()).map_err(|e| {
+            opencv::Error::new(
+                opencv::core::StsError,
+                &format!("Failed to read output directory: {}", e),
+            )
+        })?;
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let is_new = metadata
+                .modified()
+                .map(|modified| modified >= started_at)
+                .unwrap_or(false);
+            if !metadata.is_file() || !is_new {
+                continue;
+            }
+            if path.extension().and_then(|ext| ext.to_str()) != Some("mp4") {
+                continue;
+            }
+
+            summary.frames += count_video_frames(&path).unwrap_or(0);
+            summary.bytes_written += metadata.len();
+            summary.files.push(path);
+        }
+        summary.files.sort();
+        self.index_segments(true);
+
+        println!(
+            "✅ Recorded {} segment(s), {} frames from {}",
+            summary.files.len(),
+            summary.frames,
+            self.url
+        );
+
+        Ok(summary)
+    }
+
+    /// Monitor and maintain the FFmpeg recording process, restarting it on failure
     pub fn process_stream_ffmpeg(&mut self) -> Result<()> {
-        let mut consecutive_failures = 0;
-        let max_failures = 3;
+        self.supervise_ffmpeg(false)
+    }
+
+    /// Shared supervision loop for the recording and HLS processes
+    ///
+    /// Health is reported through `self.supervisor`; restarts wait for its
+    /// jittered exponential backoff. In `run_once` mode a clean exit ends the
+    /// loop, a failed exit is returned as an error and failed starts are
+    /// retried up to `run_once_max_failures` times. When `self.shutdown` is
+    /// triggered the child is stopped gracefully and the loop returns `Ok`.
+    ///
+    /// A running child whose output and `-progress` both stop moving for
+    /// `stall_timeout_secs` is stopped and restarted like a failed one.
+    fn supervise_ffmpeg(&mut self, hls: bool) -> Result<()> {
+        let label = if hls { "HLS" } else { "FFmpeg" };
+        let mut watchdog = StallWatchdog::new(self.supervisor.config().stall_timeout());
+
+        if self.ffmpeg_process.is_some() {
+            self.supervisor.started();
+            watchdog.reset(Instant::now());
+        }
 
         loop {
+            if self.shutdown.is_triggered() {
+                self.stop_ffmpeg();
+                if !hls {
+                    self.index_segments(true);
+                }
+                self.supervisor
+                    .stopped(Some("Shutdown requested".to_string()));
+                return Ok(());
+            }
+
             if self.ffmpeg_process.is_none() {
-                match self.start_ffmpeg_recording() {
+                let started = if hls {
+                    self.start_hls_streaming()
+                } else {
+                    self.start_ffmpeg_recording()
+                };
+
+                match started {
                     Ok(_) => {
-                        println!("Successfully started FFmpeg process for {}", self.url);
-                        consecutive_failures = 0;
+                        println!("📺 Successfully started {} process for {}", label, self.url);
+                        self.supervisor.started();
+                        watchdog.reset(Instant::now());
                     }
                     Err(e) => {
-                        eprintln!("Failed to start FFmpeg for {}: {}", self.url, e);
-                        consecutive_failures += 1;
-                        if consecutive_failures >= max_failures {
-                            if self.run_once {
-                                return Err(opencv::Error::new(
-                                    opencv::core::StsError,
-                                    "Failed to start FFmpeg in run_once mode",
-                                ));
-                            }
-                            thread::sleep(Duration::from_secs(10));
-                        } else {
-                            thread::sleep(Duration::from_secs(1));
+                        eprintln!("❌ Failed to start {} for {}: {}", label, self.url, e);
+                        let delay = self
+                            .supervisor
+                            .failed(format!("Failed to start {}: {}", label, e));
+                        if self.run_once
+                            && self.supervisor.consecutive_failures()
+                                >= self.supervisor.config().run_once_max_failures
+                        {
+                            self.supervisor.stopped(None);
+                            return Err(opencv::Error::new(
+                                opencv::core::StsError,
+                                &format!("Failed to start {} in run_once mode", label),
+                            ));
                         }
+                        self.shutdown.sleep(delay);
                         continue;
                     }
                 }
             }
 
-            if let Some(process) = &mut self.ffmpeg_process {
-                match process.try_wait() {
-                    Ok(Some(status)) => {
+            let Some(process) = &mut self.ffmpeg_process else {
+                continue;
+            };
+
+            match process.try_wait() {
+                Ok(Some(status)) => {
+                    self.ffmpeg_process = None;
+                    let stderr = self.finish_monitor();
+                    println!(
+                        "{} process for {} ended with status: {}",
+                        label, self.url, status
+                    );
+                    if !hls {
+                        self.index_segments(true);
+                    }
+
+                    if status.success() && self.run_once {
                         println!(
-                            "FFmpeg process for {} ended with status: {}",
-                            self.url, status
+                            "✅ {} process finished successfully in run_once mode.",
+                            label
                         );
-                        if !status.success() {
-                            eprintln!("FFmpeg process failed for {}, restarting...", self.url);
-                            consecutive_failures += 1;
-                        } else if self.run_once {
-                            println!("FFmpeg process finished successfully in run_once mode.");
-                            return Ok(());
-                        }
+                        self.supervisor.stopped(None);
+                        return Ok(());
+                    }
 
-                        self.ffmpeg_process = None;
+                    let reason = match stderr {
+                        Some(line) => format!("{} exited with {}: {}", label, status, line),
+                        None => format!("{} exited with {}", label, status),
+                    };
 
-                        if self.run_once && !status.success() {
-                            return Err(opencv::Error::new(
-                                opencv::core::StsError,
-                                "FFmpeg process failed in run_once mode",
-                            ));
-                        }
+                    if self.run_once {
+                        self.supervisor.failed(reason.clone());
+                        self.supervisor.stopped(None);
+                        return Err(opencv::Error::new(opencv::core::StsError, &reason));
+                    }
 
-                        if consecutive_failures >= max_failures {
-                            thread::sleep(Duration::from_secs(10));
-                        } else {
-                            thread::sleep(Duration::from_secs(1));
+                    eprintln!("❌ {} process ended for {}, restarting...", label, self.url);
+                    let delay = self.supervisor.failed(reason);
+                    self.shutdown.sleep(delay);
+                }
+                Ok(None) => {
+                    // Process still running
+                    let newest_output = self.latest_output(hls);
+                    self.supervisor
+                        .observe_output(newest_output.as_ref().and_then(|output| output.modified));
+
+                    let progress = self.progress.as_ref().and_then(|p| p.last_advance());
+                    if watchdog.observe(Instant::now(), newest_output, progress) {
+                        let reason = format!(
+                            "{} stalled: no new output or progress for {}s",
+                            label,
+                            watchdog.idle(Instant::now()).as_secs()
+                        );
+                        eprintln!("⚠️ {} for {}, restarting...", reason, self.url);
+                        self.stop_ffmpeg();
+                        if !hls {
+                            self.index_segments(true);
                         }
-                    }
-                    Ok(None) => {
-                        consecutive_failures = 0;
-                        thread::sleep(Duration::from_secs(1));
-                    }
-                    Err(e) => {
-                        eprintln!("Error checking FFmpeg process for {}: {}", self.url, e);
-                        self.ffmpeg_process = None;
-                        consecutive_failures += 1;
+                        let delay = self.supervisor.stalled(reason.clone());
                         if self.run_once {
-                            return Err(opencv::Error::new(
-                                opencv::core::StsError,
-                                &format!("Error checking FFmpeg process: {}", e),
-                            ));
-                        }
-                        if consecutive_failures >= max_failures {
-                            thread::sleep(Duration::from_secs(10));
-                        } else {
-                            thread::sleep(Duration::from_secs(1));
+                            self.supervisor.stopped(None);
+                            return Err(opencv::Error::new(opencv::core::StsError, &reason));
                         }
+                        self.shutdown.sleep(delay);
+                        continue;
+                    }
+
+                    if !hls {
+                        self.index_segments(false);
                     }
+                    self.shutdown.sleep(Duration::from_secs(1));
+                }
+                Err(e) => {
+                    eprintln!(
+                        "❌ Error checking {} process for {}: {}",
+                        label, self.url, e
+                    );
+                    self.ffmpeg_process = None;
+                    self.progress = None;
+                    let reason = format!("Error checking {} process: {}", label, e);
+                    let delay = self.supervisor.failed(reason.clone());
+                    if self.run_once {
+                        self.supervisor.stopped(None);
+                        return Err(opencv::Error::new(opencv::core::StsError, &reason));
+                    }
+                    self.shutdown.sleep(delay);
                 }
             }
         }
     }
+
+    /// Read the new child's progress and stderr into this camera's statistics
+    fn monitor_child(&mut self, process: &mut Child) {
+        let mut monitor = ProgressMonitor::new(self.stats.clone()).redacting(self.url.clone());
+        if let Some(config) = &self.ffmpeg_log {
+            let log = self
+                .stats_log
+                .get_or_insert_with(|| {
+                    RotatingLog::new(
+                        config.log_path(&self.output_dir, &self.url.camera_id()),
+                        config.max_bytes,
+                        config.max_files,
+                    )
+                })
+                .clone();
+            monitor = monitor.with_log(log, config.stats_interval());
+        }
+        self.progress = Some(monitor.attach(process));
+    }
+
+    /// Wait for the exited child's output to be read; returns its last stderr line
+    fn finish_monitor(&mut self) -> Option<String> {
+        self.progress.take()?.finish().last_error
+    }
+
+    /// Size and modification time of the newest output: the HLS playlist, or
+    /// the newest MP4 segment in the camera directory
+    fn latest_output(&self, hls: bool) -> Option<OutputSnapshot> {
+        if hls {
+            let hls_config = self.hls_config.as_ref()?;
+            let playlist = PathBuf::from(&hls_config.output_directory).join("playlist.m3u8");
+            return OutputSnapshot::of(&playlist);
+        }
+
+        fs::read_dir(self.camera_dir())
+            .ok()?
+            .flatten()
+            .filter(|entry| entry.path().extension().and_then(|ext| ext.to_str()) == Some("mp4"))
+            .filter_map(|entry| OutputSnapshot::of(&entry.path()))
+            .max_by_key(|output| output.modified)
+    }
+}
+
+/// Count the video frames in a recorded file using ffprobe packet counting
+fn count_video_frames(path: &Path) -> Option<u64> {
+    let output = Command::new("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-select_streams")
+        .arg("v:0")
+        .arg("-count_packets")
+        .arg("-show_entries")
+        .arg("stream=nb_read_packets")
+        .arg("-of")
+        .arg("csv=p=0")
+        .arg(path)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
 }
@@ -0,0 +1,161 @@
+//! Rotating per-camera log of ffmpeg diagnostics
+//!
+//! Each camera gets one JSON-lines file with its classified stderr lines and
+//! periodic progress statistics (see `rtsp::progress`). When the file grows
+//! past `max_bytes` it is renamed to `<file>.1`, older files shift up, and
+//! at most `max_files` rotated files are kept.
+
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Directory (below the output directory) used when `directory` is not set
+const DEFAULT_LOG_DIRECTORY: &str = "logs";
+
+/// Where and how much ffmpeg diagnostics to keep
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct FfmpegLogConfig {
+    pub enabled: bool,
+    /// Log directory (default: `logs` in the output directory)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    /// Rotate a camera's log once it reaches this many bytes
+    pub max_bytes: u64,
+    /// Number of rotated files kept per camera
+    pub max_files: u32,
+    /// Seconds between progress statistics entries
+    pub stats_interval_secs: u64,
+}
+
+impl Default for FfmpegLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: None,
+            max_bytes: 5 * 1024 * 1024,
+            max_files: 3,
+            stats_interval_secs: 60,
+        }
+    }
+}
+
+impl FfmpegLogConfig {
+    /// Log file for `camera`, relative to `output_directory` unless configured
+    pub fn log_path(&self, output_directory: &str, camera: &str) -> PathBuf {
+        let directory = match &self.directory {
+            Some(directory) => PathBuf::from(directory),
+            None => Path::new(output_directory).join(DEFAULT_LOG_DIRECTORY),
+        };
+        directory.join(format!("ffmpeg_{}.log", camera))
+    }
+
+    /// Log for `camera`, or `None` when logging is disabled
+    pub fn open(&self, output_directory: &str, camera: &str) -> Option<RotatingLog> {
+        self.enabled.then(|| {
+            RotatingLog::new(
+                self.log_path(output_directory, camera),
+                self.max_bytes,
+                self.max_files,
+            )
+        })
+    }
+
+    pub fn stats_interval(&self) -> Duration {
+        Duration::from_secs(self.stats_interval_secs)
+    }
+}
+
+#[derive(Debug)]
+struct LogFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: u32,
+    file: Option<File>,
+    written: u64,
+}
+
+/// Size-rotated line log; clones append to the same file
+#[derive(Debug, Clone)]
+pub struct RotatingLog {
+    inner: Arc<Mutex<LogFile>>,
+}
+
+impl RotatingLog {
+    /// The file (and its directory) is created on the first write
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64, max_files: u32) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(LogFile {
+                path: path.into(),
+                max_bytes,
+                max_files,
+                file: None,
+                written: 0,
+            })),
+        }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.inner.lock().unwrap().path.clone()
+    }
+
+    /// Append one line, rotating first if it would exceed `max_bytes`
+    pub fn write_line(&self, line: &str) -> io::Result<()> {
+        let mut log = self.inner.lock().unwrap();
+        let length = line.len() as u64 + 1;
+
+        if log.file.is_none() {
+            if let Some(parent) = log.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&log.path)?;
+            log.written = file.metadata()?.len();
+            log.file = Some(file);
+        }
+
+        if log.written > 0 && log.written + length > log.max_bytes {
+            log.file = None;
+            rotate(&log.path, log.max_files)?;
+            log.file = Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&log.path)?,
+            );
+            log.written = 0;
+        }
+
+        let file = log.file.as_mut().expect("log file was opened above");
+        writeln!(file, "{}", line)?;
+        log.written += length;
+        Ok(())
+    }
+}
+
+/// Shift `path.N` to `path.N+1` (dropping the oldest) and `path` to `path.1`
+fn rotate(path: &Path, max_files: u32) -> io::Result<()> {
+    if max_files == 0 {
+        return fs::remove_file(path);
+    }
+    for n in (1..max_files).rev() {
+        match fs::rename(rotated_path(path, n), rotated_path(path, n + 1)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    fs::rename(path, rotated_path(path, 1))
+}
+
+/// `path` with `.n` appended, e.g. `ffmpeg_lobby.log.1`
+pub fn rotated_path(path: &Path, n: u32) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
@@ -0,0 +1,547 @@
+//! Index of recorded segments
+//!
+//! Every closed segment is appended to a JSON-lines file with its camera,
+//! wall-clock start and end, size, codec and whether it contains gaps.
+//! `SegmentIndex::lookup` finds the segments covering a time range and
+//! `SegmentIndex::export` joins them into a single trimmed MP4 with ffmpeg's
+//! concat demuxer. Probing a segment reads all of its packets, so recorders
+//! index through a `SegmentIndexWorker` thread.
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+/// Index file name used when `path` is not set
+const DEFAULT_INDEX_FILE: &str = "segment_index.jsonl";
+
+/// Timestamp jumps longer than this (in seconds) count as a gap
+const GAP_TOLERANCE_SECS: f64 = 2.0;
+
+/// Serializes appends from the capture threads sharing an index file
+static APPEND_LOCK: Mutex<()> = Mutex::new(());
+
+/// Segment index settings
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SegmentIndexConfig {
+    /// Index closed segments while recording (off by default: every closed
+    /// segment is probed with ffprobe)
+    pub enabled: bool,
+    /// Index file (default: `segment_index.jsonl` in the output directory)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl Default for SegmentIndexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+        }
+    }
+}
+
+impl SegmentIndexConfig {
+    /// Index file path, relative to `output_directory` unless configured
+    pub fn index_path(&self, output_directory: &str) -> PathBuf {
+        match &self.path {
+            Some(path) => PathBuf::from(path),
+            None => Path::new(output_directory).join(DEFAULT_INDEX_FILE),
+        }
+    }
+}
+
+/// One closed segment
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SegmentRecord {
+    /// Camera id (see `RtspUrl::camera_id`)
+    pub camera: String,
+    pub path: PathBuf,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub duration_secs: f64,
+    pub bytes: u64,
+    /// Video codec name as reported by ffprobe
+    pub codec: String,
+    /// Video timestamps jump inside the segment, or it starts well after the
+    /// previous segment of the same camera ended
+    pub has_gaps: bool,
+}
+
+impl SegmentRecord {
+    /// Probe a closed segment with ffprobe
+    ///
+    /// The start time comes from the `segment_%Y%m%d_%H%M%S` file name when
+    /// present, otherwise from the modification time minus the duration.
+    pub fn probe(camera: &str, path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let probe = probe_segment(path)?;
+
+        let start = start_from_file_name(path).unwrap_or_else(|| {
+            let modified: DateTime<Local> = metadata
+                .modified()
+                .map(DateTime::from)
+                .unwrap_or_else(|_| Local::now());
+            modified - chrono::Duration::milliseconds((probe.duration_secs * 1000.0) as i64)
+        });
+        let end = start + chrono::Duration::milliseconds((probe.duration_secs * 1000.0) as i64);
+
+        Ok(Self {
+            camera: camera.to_string(),
+            path: path.to_path_buf(),
+            start,
+            end,
+            duration_secs: probe.duration_secs,
+            bytes: metadata.len(),
+            codec: probe.codec,
+            has_gaps: probe.has_gaps,
+        })
+    }
+
+    /// Whether this segment overlaps `[from, to)`
+    pub fn overlaps(&self, from: &DateTime<Local>, to: &DateTime<Local>) -> bool {
+        self.start < *to && self.end > *from
+    }
+}
+
+/// A JSON-lines segment index
+#[derive(Debug, Clone)]
+pub struct SegmentIndex {
+    path: PathBuf,
+}
+
+impl SegmentIndex {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one record
+    pub fn append(&self, record: &SegmentRecord) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(record)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        line.push('\n');
+
+        let _guard = APPEND_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())
+    }
+
+    /// All records, in the order they were written (empty if the index doesn't exist)
+    ///
+    /// Lines that can't be parsed (e.g. a partial last line) are skipped.
+    pub fn load(&self) -> io::Result<Vec<SegmentRecord>> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut records = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(record) => records.push(record),
+                Err(e) => eprintln!(
+                    "⚠️ Skipping invalid line {} in {}: {}",
+                    number + 1,
+                    self.path.display(),
+                    e
+                ),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Records of `camera` overlapping `[from, to)`, ordered by start time
+    ///
+    /// Segments whose file no longer exists (e.g. removed by retention) are left out.
+    pub fn lookup(
+        &self,
+        camera: &str,
+        from: DateTime<Local>,
+        to: DateTime<Local>,
+    ) -> io::Result<Vec<SegmentRecord>> {
+        let mut records: Vec<SegmentRecord> = self
+            .load()?
+            .into_iter()
+            .filter(|record| record.camera == camera && record.overlaps(&from, &to))
+            .filter(|record| record.path.exists())
+            .collect();
+        records.sort_by(|a, b| a.start.cmp(&b.start));
+        records.dedup_by(|a, b| a.path == b.path);
+        Ok(records)
+    }
+
+    /// Join the segments of `camera` covering `[from, to)` into one MP4
+    ///
+    /// Uses stream copy, so the cut points snap to the nearest preceding
+    /// keyframe. Returns the segments that were used.
+    pub fn export(
+        &self,
+        camera: &str,
+        from: DateTime<Local>,
+        to: DateTime<Local>,
+        output: &Path,
+    ) -> io::Result<Vec<SegmentRecord>> {
+        let records = self.lookup(camera, from, to)?;
+        if records.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "No segments for camera {} between {} and {}",
+                    camera, from, to
+                ),
+            ));
+        }
+
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let list = Self::concat_list(&records, from, to)?;
+        let list_file = tempfile::Builder::new()
+            .prefix("segment_concat_")
+            .suffix(".txt")
+            .tempfile()?;
+        fs::write(list_file.path(), list)?;
+
+        let output_status = Command::new("ffmpeg")
+            .arg("-y")
+            .arg("-loglevel")
+            .arg("error")
+            .arg("-f")
+            .arg("concat")
+            .arg("-safe")
+            .arg("0")
+            .arg("-i")
+            .arg(list_file.path())
+            .arg("-c")
+            .arg("copy")
+            .arg("-movflags")
+            .arg("+faststart")
+            .arg(output)
+            .output()?;
+
+        if !output_status.status.success() {
+            return Err(io::Error::other(format!(
+                "ffmpeg concat failed ({}): {}",
+                output_status.status,
+                String::from_utf8_lossy(&output_status.stderr).trim()
+            )));
+        }
+
+        println!(
+            "✅ Exported {} segment(s) of {} to {}",
+            records.len(),
+            camera,
+            output.display()
+        );
+        Ok(records)
+    }
+
+    /// ffconcat list joining `records`, trimmed to `[from, to)`
+    ///
+    /// The first segment gets an `inpoint` and the last an `outpoint` when
+    /// the range starts or ends inside them. Paths are made absolute.
+    pub fn concat_list(
+        records: &[SegmentRecord],
+        from: DateTime<Local>,
+        to: DateTime<Local>,
+    ) -> io::Result<String> {
+        let mut list = String::from("ffconcat version 1.0\n");
+        for (position, record) in records.iter().enumerate() {
+            let path = fs::canonicalize(&record.path)?;
+            list.push_str(&format!(
+                "file '{}'\n",
+                path.to_string_lossy().replace('\'', "'\\''")
+            ));
+            if position == 0 && from > record.start {
+                let inpoint = (from - record.start).num_milliseconds() as f64 / 1000.0;
+                list.push_str(&format!("inpoint {:.3}\n", inpoint));
+            }
+            if position == records.len() - 1 && to < record.end {
+                let outpoint = (to - record.start).num_milliseconds() as f64 / 1000.0;
+                list.push_str(&format!("outpoint {:.3}\n", outpoint));
+            }
+        }
+        Ok(list)
+    }
+}
+
+/// Adds the closed segments of one camera directory to an index
+///
+/// The newest file in the directory is the one being written, so it is only
+/// indexed once recording has stopped. Only files with the recording's
+/// extension (`mp4` unless set with `with_extension`) are indexed.
+#[derive(Debug)]
+pub struct SegmentIndexer {
+    index: SegmentIndex,
+    camera: String,
+    directory: PathBuf,
+    extension: String,
+    indexed: HashSet<PathBuf>,
+    last_end: Option<DateTime<Local>>,
+}
+
+impl SegmentIndexer {
+    /// Track `directory`, skipping segments the index already contains
+    pub fn new(
+        index: SegmentIndex,
+        camera: impl Into<String>,
+        directory: impl Into<PathBuf>,
+    ) -> Self {
+        let camera = camera.into();
+        let existing: Vec<SegmentRecord> = match index.load() {
+            Ok(records) => records
+                .into_iter()
+                .filter(|record| record.camera == camera)
+                .collect(),
+            Err(e) => {
+                eprintln!(
+                    "⚠️ Failed to read segment index {}: {}",
+                    index.path().display(),
+                    e
+                );
+                Vec::new()
+            }
+        };
+
+        Self {
+            last_end: existing.iter().map(|record| record.end).max(),
+            indexed: existing.into_iter().map(|record| record.path).collect(),
+            index,
+            camera,
+            directory: directory.into(),
+            extension: "mp4".to_string(),
+        }
+    }
+
+    /// Index segments with this extension, e.g. `RecordingContainer::extension()`
+    pub fn with_extension(mut self, extension: &str) -> Self {
+        self.extension = extension.trim_start_matches('.').to_string();
+        self
+    }
+
+    pub fn index(&self) -> &SegmentIndex {
+        &self.index
+    }
+
+    /// Index every segment that is not being written; `include_newest` once recording stopped
+    ///
+    /// Returns the newly indexed records.
+    pub fn update(&mut self, include_newest: bool) -> Vec<SegmentRecord> {
+        let segments = self.closed_segments(include_newest);
+        self.index_segments(segments)
+    }
+
+    /// Segment files in the directory, oldest first, without the one being
+    /// written unless `include_newest`
+    pub fn closed_segments(&self, include_newest: bool) -> Vec<PathBuf> {
+        list_segments(&self.directory, &self.extension, include_newest)
+    }
+
+    /// Probe and index those of `segments` not indexed yet, in order
+    ///
+    /// Returns the newly indexed records.
+    pub fn index_segments(&mut self, segments: Vec<PathBuf>) -> Vec<SegmentRecord> {
+        let mut added = Vec::new();
+        for path in segments {
+            if self.indexed.contains(&path) {
+                continue;
+            }
+            match SegmentRecord::probe(&self.camera, &path) {
+                Ok(mut record) => {
+                    let gap_before = self.last_end.is_some_and(|end| {
+                        (record.start - end).num_milliseconds() as f64 / 1000.0 > GAP_TOLERANCE_SECS
+                    });
+                    record.has_gaps |= gap_before;
+                    if let Err(e) = self.index.append(&record) {
+                        eprintln!("❌ Failed to index {}: {}", path.display(), e);
+                        continue;
+                    }
+                    self.last_end =
+                        Some(self.last_end.map_or(record.end, |end| end.max(record.end)));
+                    added.push(record);
+                }
+                // Unreadable (e.g. empty) segments are not retried
+                Err(e) => eprintln!("⚠️ Failed to probe {}: {}", path.display(), e),
+            }
+            self.indexed.insert(path);
+        }
+        added
+    }
+}
+
+/// Runs a `SegmentIndexer` on its own thread
+///
+/// `request` lists the closed segments on the calling thread (one directory
+/// read) and hands them to the worker, which probes them; requests that queue
+/// up meanwhile are merged. Listing at request time means a segment started
+/// after the request is never mistaken for a closed one. Dropping the worker
+/// finishes the queued requests.
+#[derive(Debug)]
+pub struct SegmentIndexWorker {
+    directory: PathBuf,
+    extension: String,
+    sender: Option<Sender<Vec<PathBuf>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl SegmentIndexWorker {
+    pub fn spawn(mut indexer: SegmentIndexer) -> Self {
+        let directory = indexer.directory.clone();
+        let extension = indexer.extension.clone();
+        let (sender, receiver) = mpsc::channel::<Vec<PathBuf>>();
+        let handle = thread::spawn(move || {
+            while let Ok(mut segments) = receiver.recv() {
+                segments.extend(receiver.try_iter().flatten());
+                segments.sort();
+                segments.dedup();
+                indexer.index_segments(segments);
+            }
+        });
+        Self {
+            directory,
+            extension,
+            sender: Some(sender),
+            handle: Some(handle),
+        }
+    }
+
+    /// Queue the closed segments for indexing; returns without probing
+    pub fn request(&self, include_newest: bool) {
+        let segments = list_segments(&self.directory, &self.extension, include_newest);
+        if let Some(sender) = &self.sender
+            && !segments.is_empty()
+        {
+            let _ = sender.send(segments);
+        }
+    }
+}
+
+impl Drop for SegmentIndexWorker {
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Files with `extension` in `directory` in name (= start time) order,
+/// without the newest unless `include_newest`
+fn list_segments(directory: &Path, extension: &str, include_newest: bool) -> Vec<PathBuf> {
+    let mut segments: Vec<PathBuf> = match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some(extension))
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+    segments.sort();
+    if !include_newest {
+        segments.pop();
+    }
+    segments
+}
+
+/// Parse a local time given as RFC 3339, `YYYY-MM-DD HH:MM:SS` or `YYYY-MM-DDTHH:MM:SS`
+pub fn parse_local_time(value: &str) -> Option<DateTime<Local>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Local));
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+}
+
+/// Duration, video codec and internal gaps of a segment
+struct SegmentProbe {
+    duration_secs: f64,
+    codec: String,
+    has_gaps: bool,
+}
+
+fn probe_segment(path: &Path) -> io::Result<SegmentProbe> {
+    let output = Command::new("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-select_streams")
+        .arg("v:0")
+        .arg("-show_entries")
+        .arg("stream=codec_name:format=duration:packet=pts_time")
+        .arg("-of")
+        .arg("json")
+        .arg(path)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let json: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let duration_secs = json["format"]["duration"]
+        .as_str()
+        .and_then(|d| d.parse::<f64>().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing duration"))?;
+    let codec = json["streams"][0]["codec_name"]
+        .as_str()
+        .unwrap_or("unknown")
+        .to_string();
+
+    let mut pts: Vec<f64> = json["packets"]
+        .as_array()
+        .map(|packets| {
+            packets
+                .iter()
+                .filter_map(|packet| packet["pts_time"].as_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    // Packets are in decode order; B-frames make raw pts non-monotonic
+    pts.sort_by(|a, b| a.total_cmp(b));
+    let has_gaps = pts.windows(2).any(|w| w[1] - w[0] > GAP_TOLERANCE_SECS);
+
+    Ok(SegmentProbe {
+        duration_secs,
+        codec,
+        has_gaps,
+    })
+}
+
+/// Start time encoded in `segment_%Y%m%d_%H%M%S.mp4` (local time)
+fn start_from_file_name(path: &Path) -> Option<DateTime<Local>> {
+    let stem = path.file_stem()?.to_str()?;
+    let timestamp = stem.rsplit_once("segment_")?.1;
+    let naive = NaiveDateTime::parse_from_str(timestamp, "%Y%m%d_%H%M%S").ok()?;
+    Local.from_local_datetime(&naive).earliest()
+}
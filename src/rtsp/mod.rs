@@ -1,9 +1,42 @@
 pub mod capture;
 pub mod config;
+pub mod event;
 pub mod ffmpeg;
+pub mod ffmpeg_log;
+pub mod index;
 pub mod opencv;
+pub mod progress;
+pub mod retention;
+pub mod shutdown;
+pub mod snapshot;
+pub mod supervisor;
 pub mod types;
+pub mod url;
+pub mod watchdog;
 
 pub use capture::RTSPCapture;
 pub use config::generate_default_config;
-pub use types::{CaptureConfig, HLSConfig, SavingOption, StreamConfig};
+pub use event::{
+    EventConfig, EventRequest, EventTrigger, MotionTriggerConfig, PreRollBuffer, TriggerSource,
+    event_clip_name, take_dropped_triggers,
+};
+pub use ffmpeg_log::{FfmpegLogConfig, RotatingLog};
+pub use index::{
+    SegmentIndex, SegmentIndexConfig, SegmentIndexWorker, SegmentIndexer, SegmentRecord,
+    parse_local_time,
+};
+pub use progress::{FfmpegStats, ProgressMonitor, StatsHandle, StatsRegistry, StderrClass};
+pub use retention::{
+    DeletionReason, RetentionConfig, RetentionManager, RetentionPolicy, SegmentDeletion,
+};
+pub use shutdown::{ShutdownToken, stop_child};
+pub use snapshot::{LATEST_SNAPSHOT, SnapshotConfig, snapshot_path, write_atomically};
+pub use supervisor::{
+    CameraEvent, CameraState, CameraStatus, StatusHandle, Supervisor, SupervisorConfig,
+};
+pub use types::{
+    CameraConfig, CameraSettings, CaptureConfig, CaptureSummary, HLSConfig, RecordingBackend,
+    RtspTransport, SavingOption, StreamConfig,
+};
+pub use url::{CredentialSource, Credentials, RtspUrl};
+pub use watchdog::{OutputSnapshot, StallWatchdog};
@@ -1,14 +1,16 @@
-use crate::rtsp::capture::RTSPCapture;
+use crate::rtsp::capture::{RTSPCapture, SEGMENT_EXTENSION};
+use crate::rtsp::types::CaptureSummary;
 use chrono::Local;
-use opencv::{Result, prelude::*, videoio};
+use opencv::{Result, core::Vector, imgcodecs, prelude::*, videoio};
 use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
 use std::thread;
 use std::time::{Duration, Instant};
 
 impl RTSPCapture {
     pub fn start_opencv_recording(&mut self) -> Result<()> {
-        let mut capture = videoio::VideoCapture::from_file(&self.url, videoio::CAP_FFMPEG)?;
+        let mut capture =
+            videoio::VideoCapture::from_file(&self.url.connection_url(), videoio::CAP_FFMPEG)?;
         if !capture.is_opened()? {
             return Err(opencv::Error::new(
                 opencv::core::StsError,
@@ -45,10 +47,15 @@ impl RTSPCapture {
         self.create_new_video_file()?;
 
         loop {
+            if self.shutdown.is_triggered() {
+                println!("🛑 Stopping OpenCV recording for {}", self.url);
+                break;
+            }
             let current_time = Instant::now();
             let segment_elapsed = current_time.duration_since(self.current_file_start);
             if segment_elapsed >= self.segment_duration {
                 self.create_new_video_file()?;
+                self.index_segments(false);
                 continue;
             }
             if let Some(capture) = &mut self.capture {
@@ -75,23 +82,83 @@ impl RTSPCapture {
                 thread::sleep(Duration::from_millis(10));
             }
         }
+        // Finalize the current segment
+        if let Some(mut writer) = self.writer.take() {
+            writer.release()?;
+        }
+        self.index_segments(true);
         if let Some(window_name) = &window {
             opencv::highgui::destroy_window(window_name)?;
         }
         Ok(())
     }
 
+    /// Save decoded frames as JPEG images until the capture limits are reached
+    ///
+    /// Every `frame_interval`-th frame is written to the camera directory as
+    /// `frame_NNNNNN.jpg`, numbered on from the frames already there so an
+    /// earlier capture is never overwritten. Capture stops at `max_frames` saved frames, after
+    /// `max_duration`, or when the stream ends.
+    pub fn capture_frames(&mut self, frame_interval: usize) -> Result<CaptureSummary> {
+        if self.capture.is_none() {
+            self.start_opencv_recording()?;
+        }
+
+        let camera_dir = self.camera_dir();
+        fs::create_dir_all(&camera_dir).map_err(|e| {
+            opencv::Error::new(
+                opencv::core::StsError,
+                &format!("Failed to create directory: {}", e),
+            )
+        })?;
+
+        let frame_interval = frame_interval.max(1);
+        let started = Instant::now();
+        let mut summary = CaptureSummary::default();
+        let mut frame = Mat::default();
+        let mut frame_index: usize = 0;
+        let first_number = next_frame_number(&camera_dir);
+
+        while let Some(capture) = &mut self.capture {
+            if self.shutdown.is_triggered()
+                || self.deadline.is_expired()
+                || self.max_frames.is_some_and(|max| summary.frames >= max)
+                || self
+                    .max_duration
+                    .is_some_and(|max| started.elapsed() >= max)
+            {
+                break;
+            }
+
+            if !capture.read(&mut frame)? || frame.empty() {
+                break;
+            }
+
+            if frame_index.is_multiple_of(frame_interval) {
+                let file_name =
+                    camera_dir.join(format!("frame_{:06}.jpg", first_number + summary.frames));
+                imgcodecs::imwrite(path_str(&file_name)?, &frame, &Vector::new())?;
+                summary.bytes_written += fs::metadata(&file_name).map(|m| m.len()).unwrap_or(0);
+                summary.files.push(file_name);
+                summary.frames += 1;
+            }
+            frame_index += 1;
+        }
+
+        self.capture = None;
+        println!(
+            "✅ Saved {} frames ({} bytes) from {}",
+            summary.frames, summary.bytes_written, self.url
+        );
+
+        Ok(summary)
+    }
+
     pub fn create_new_video_file(&mut self) -> Result<()> {
         if let Some(mut writer) = self.writer.take() {
             writer.release()?;
         }
-        let camera_dir = PathBuf::from(&self.output_dir).join(format!(
-            "camera_{}",
-            self.url
-                .replace("://", "_")
-                .replace("/", "_")
-                .replace(":", "_")
-        ));
+        let camera_dir = self.camera_dir();
         fs::create_dir_all(&camera_dir).map_err(|e| {
             opencv::Error::new(
                 opencv::core::StsError,
@@ -99,7 +166,7 @@ impl RTSPCapture {
             )
         })?;
         let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-        let file_name = camera_dir.join(format!("segment_{}.mp4", timestamp));
+        let file_name = camera_dir.join(format!("segment_{}.{}", timestamp, SEGMENT_EXTENSION));
         if let Some(capture) = &self.capture {
             let frame_width = capture.get(videoio::CAP_PROP_FRAME_WIDTH)? as i32;
             let frame_height = capture.get(videoio::CAP_PROP_FRAME_HEIGHT)? as i32;
@@ -113,7 +180,7 @@ impl RTSPCapture {
             };
             let fourcc = videoio::VideoWriter::fourcc('m', 'p', '4', 'v')?;
             let writer = videoio::VideoWriter::new(
-                file_name.to_str().unwrap(),
+                path_str(&file_name)?,
                 fourcc,
                 fps,
                 (frame_width, frame_height).into(),
@@ -131,3 +198,30 @@ impl RTSPCapture {
         Ok(())
     }
 }
+
+/// `path` as UTF-8, which OpenCV needs for file names
+pub(crate) fn path_str(path: &Path) -> Result<&str> {
+    path.to_str().ok_or_else(|| {
+        opencv::Error::new(
+            opencv::core::StsError,
+            &format!("Output path is not valid UTF-8: {}", path.display()),
+        )
+    })
+}
+
+/// One past the highest `frame_NNNNNN.jpg` number in `directory`
+fn next_frame_number(directory: &Path) -> u64 {
+    fs::read_dir(directory)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            let number = name
+                .to_str()?
+                .strip_prefix("frame_")?
+                .strip_suffix(".jpg")?;
+            number.parse::<u64>().ok()
+        })
+        .max()
+        .map_or(0, |highest| highest + 1)
+}
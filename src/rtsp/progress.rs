@@ -0,0 +1,421 @@
+//! Readers for ffmpeg's `-progress` output and stderr
+//!
+//! ffmpeg started with `-progress pipe:1` writes blocks of `key=value` lines
+//! to stdout, each ending with `progress=continue` (or `progress=end`).
+//! `ProgressMonitor` drains stdout and stderr of one child on their own
+//! threads, so the pipes never fill up and block ffmpeg. Progress values and
+//! classified stderr lines are collected in a per-camera `FfmpegStats` that
+//! outlives restarts, and optionally written to a `RotatingLog`.
+
+use crate::rtsp::ffmpeg_log::{FfmpegLogConfig, RotatingLog};
+use crate::rtsp::url::RtspUrl;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Kind of problem reported on ffmpeg's stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StderrClass {
+    ConnectionRefused,
+    /// 401 from the camera: missing or wrong credentials
+    Unauthorized,
+    Timeout,
+    /// Non-monotonic DTS warnings from the muxer
+    NonMonotonicDts,
+    Other,
+}
+
+/// How ffmpeg reports a 401; a bare "401" also turns up in pointers and numbers
+const UNAUTHORIZED_PHRASES: [&str; 3] = [
+    "401 unauthorized",
+    "server returned 401",
+    "method describe failed: 401",
+];
+
+impl StderrClass {
+    pub fn classify(line: &str) -> Self {
+        let line = line.to_lowercase();
+        if line.contains("connection refused") {
+            Self::ConnectionRefused
+        } else if UNAUTHORIZED_PHRASES
+            .iter()
+            .any(|phrase| line.contains(phrase))
+        {
+            Self::Unauthorized
+        } else if line.contains("timed out") || line.contains("timeout") {
+            Self::Timeout
+        } else if line.contains("non-monoton") || line.contains("non monotonically increasing dts")
+        {
+            Self::NonMonotonicDts
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// Live ffmpeg statistics for one camera
+///
+/// Progress values and the last error describe the current child; the
+/// stderr counters accumulate over every child started for the camera.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FfmpegStats {
+    pub camera: String,
+    /// Number of ffmpeg children that were monitored
+    pub processes_started: u64,
+    pub frame: u64,
+    pub fps: f64,
+    /// Output bitrate in kbit/s
+    pub bitrate_kbps: f64,
+    /// Output time in microseconds
+    pub out_time_us: i64,
+    /// Processing speed relative to real time
+    pub speed: f64,
+    pub dup_frames: u64,
+    pub drop_frames: u64,
+    pub connection_refused: u64,
+    pub unauthorized: u64,
+    pub timeouts: u64,
+    pub non_monotonic_dts: u64,
+    pub other_errors: u64,
+    /// Last stderr line, credentials redacted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error_class: Option<StderrClass>,
+    /// RFC 3339 local time of the last progress block
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    /// When the frame count or output time last increased
+    #[serde(skip)]
+    pub last_progress_at: Option<Instant>,
+}
+
+impl FfmpegStats {
+    /// Apply one `key=value` pair of a progress block
+    pub fn apply_progress(&mut self, key: &str, value: &str) {
+        let value = value.trim();
+        match key {
+            "frame" => self.frame = value.parse().unwrap_or(self.frame),
+            "fps" => self.fps = value.parse().unwrap_or(self.fps),
+            "bitrate" => {
+                self.bitrate_kbps = value
+                    .trim_end_matches("kbits/s")
+                    .parse()
+                    .unwrap_or(self.bitrate_kbps)
+            }
+            // `out_time_ms` is in microseconds as well
+            "out_time_us" | "out_time_ms" => {
+                self.out_time_us = value.parse().unwrap_or(self.out_time_us)
+            }
+            "speed" => self.speed = value.trim_end_matches('x').parse().unwrap_or(self.speed),
+            "dup_frames" => self.dup_frames = value.parse().unwrap_or(self.dup_frames),
+            "drop_frames" => self.drop_frames = value.parse().unwrap_or(self.drop_frames),
+            _ => {}
+        }
+    }
+
+    /// Clear the per-child values for a newly started child
+    fn start_child(&mut self) {
+        *self = Self {
+            camera: std::mem::take(&mut self.camera),
+            processes_started: self.processes_started + 1,
+            connection_refused: self.connection_refused,
+            unauthorized: self.unauthorized,
+            timeouts: self.timeouts,
+            non_monotonic_dts: self.non_monotonic_dts,
+            other_errors: self.other_errors,
+            last_progress_at: self.last_progress_at,
+            ..Default::default()
+        };
+    }
+
+    /// Count a classified stderr line
+    pub fn record_stderr(&mut self, class: StderrClass, line: &str) {
+        let counter = match class {
+            StderrClass::ConnectionRefused => &mut self.connection_refused,
+            StderrClass::Unauthorized => &mut self.unauthorized,
+            StderrClass::Timeout => &mut self.timeouts,
+            StderrClass::NonMonotonicDts => &mut self.non_monotonic_dts,
+            StderrClass::Other => &mut self.other_errors,
+        };
+        *counter += 1;
+        self.last_error = Some(line.to_string());
+        self.last_error_class = Some(class);
+    }
+}
+
+/// Cloneable, thread-safe view of a camera's ffmpeg statistics
+#[derive(Debug, Clone)]
+pub struct StatsHandle {
+    inner: Arc<Mutex<FfmpegStats>>,
+}
+
+impl StatsHandle {
+    pub fn new(camera: String) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(FfmpegStats {
+                camera,
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// Copy of the current statistics
+    pub fn snapshot(&self) -> FfmpegStats {
+        self.inner.lock().unwrap().clone()
+    }
+
+    /// Rename the camera reported in the statistics
+    pub fn set_camera(&self, camera: String) {
+        self.inner.lock().unwrap().camera = camera;
+    }
+}
+
+/// One entry of the rotating ffmpeg log
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum LogEntry<'a> {
+    Stderr {
+        time: String,
+        camera: &'a str,
+        class: StderrClass,
+        line: &'a str,
+    },
+    Stats {
+        time: String,
+        #[serde(flatten)]
+        stats: &'a FfmpegStats,
+    },
+}
+
+impl LogEntry<'_> {
+    fn write(&self, log: &RotatingLog) {
+        if let Ok(line) = serde_json::to_string(self)
+            && let Err(e) = log.write_line(&line)
+        {
+            eprintln!("⚠️ Failed to write {}: {}", log.path().display(), e);
+        }
+    }
+}
+
+/// Reads the stdout and stderr of one ffmpeg child into a `StatsHandle`
+#[derive(Debug)]
+pub struct ProgressMonitor {
+    stats: StatsHandle,
+    log: Option<(RotatingLog, Duration)>,
+    redact: Vec<RtspUrl>,
+    readers: Vec<JoinHandle<()>>,
+}
+
+impl ProgressMonitor {
+    pub fn new(stats: StatsHandle) -> Self {
+        Self {
+            stats,
+            log: None,
+            redact: Vec::new(),
+            readers: Vec::new(),
+        }
+    }
+
+    /// Also write stderr lines, and statistics every `stats_interval`, to `log`
+    pub fn with_log(mut self, log: RotatingLog, stats_interval: Duration) -> Self {
+        self.log = Some((log, stats_interval));
+        self
+    }
+
+    /// Remove `url`'s credentials from stderr lines (call once per input)
+    pub fn redacting(mut self, url: RtspUrl) -> Self {
+        self.redact.push(url);
+        self
+    }
+
+    /// Read the child's stdout (`-progress pipe:1`) and stderr
+    pub fn attach(mut self, child: &mut Child) -> Self {
+        self.stats.inner.lock().unwrap().start_child();
+        if let Some(stdout) = child.stdout.take() {
+            self = self.read_progress(stdout);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            self = self.read_stderr(stderr);
+        }
+        self
+    }
+
+    /// Read progress blocks from `output` until it closes
+    pub fn read_progress<R: Read + Send + 'static>(mut self, output: R) -> Self {
+        let stats = self.stats.clone();
+        let log = self.log.clone();
+        self.readers.push(thread::spawn(move || {
+            // Compared per child, since counters restart with every child
+            let mut last_frame = 0;
+            let mut last_out_time = 0;
+            let mut last_logged: Option<Instant> = None;
+
+            for line in BufReader::new(output).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let Some((key, value)) = line.trim().split_once('=') else {
+                    continue;
+                };
+                let mut stats = stats.inner.lock().unwrap();
+                if key != "progress" {
+                    stats.apply_progress(key, value);
+                    continue;
+                }
+
+                if stats.frame > last_frame || stats.out_time_us > last_out_time {
+                    stats.last_progress_at = Some(Instant::now());
+                }
+                last_frame = stats.frame;
+                last_out_time = stats.out_time_us;
+                stats.updated_at = Some(Local::now().to_rfc3339());
+
+                if let Some((log, interval)) = &log
+                    && (value == "end" || last_logged.is_none_or(|at| at.elapsed() >= *interval))
+                {
+                    last_logged = Some(Instant::now());
+                    LogEntry::Stats {
+                        time: Local::now().to_rfc3339(),
+                        stats: &stats,
+                    }
+                    .write(log);
+                }
+            }
+        }));
+        self
+    }
+
+    /// Classify stderr lines from `output` until it closes
+    pub fn read_stderr<R: Read + Send + 'static>(mut self, output: R) -> Self {
+        let stats = self.stats.clone();
+        let log = self.log.clone();
+        let redact = self.redact.clone();
+        self.readers.push(thread::spawn(move || {
+            for line in BufReader::new(output).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let line = redact
+                    .iter()
+                    .fold(line.to_string(), |line, url| url.scrub(&line));
+
+                let class = StderrClass::classify(&line);
+                let camera = {
+                    let mut stats = stats.inner.lock().unwrap();
+                    stats.record_stderr(class, &line);
+                    stats.camera.clone()
+                };
+                if let Some((log, _)) = &log {
+                    LogEntry::Stderr {
+                        time: Local::now().to_rfc3339(),
+                        camera: &camera,
+                        class,
+                        line: &line,
+                    }
+                    .write(log);
+                }
+            }
+        }));
+        self
+    }
+
+    /// When the frame count or output time last increased
+    pub fn last_advance(&self) -> Option<Instant> {
+        self.stats.inner.lock().unwrap().last_progress_at
+    }
+
+    pub fn stats(&self) -> FfmpegStats {
+        self.stats.snapshot()
+    }
+
+    /// Wait until the child's pipes have been read to the end
+    ///
+    /// Only call once the child has exited.
+    pub fn finish(self) -> FfmpegStats {
+        for reader in self.readers {
+            let _ = reader.join();
+        }
+        self.stats.snapshot()
+    }
+}
+
+/// Statistics for a set of cameras, with their logs
+#[derive(Debug, Clone, Default)]
+pub struct StatsRegistry {
+    cameras: Arc<Mutex<BTreeMap<String, (StatsHandle, Option<RotatingLog>)>>>,
+    log: Option<(FfmpegLogConfig, String)>,
+}
+
+impl StatsRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log each camera as configured, relative to `output_directory`
+    pub fn with_log(mut self, config: FfmpegLogConfig, output_directory: &str) -> Self {
+        self.log = config
+            .enabled
+            .then(|| (config, output_directory.to_string()));
+        self
+    }
+
+    /// Statistics of `camera`, created on first use
+    pub fn handle(&self, camera: &str) -> StatsHandle {
+        self.entry(camera).0
+    }
+
+    /// Monitor for a new child of `url`'s camera; call `attach` on it
+    pub fn monitor(&self, url: &RtspUrl) -> ProgressMonitor {
+        self.monitor_inputs(&url.camera_id(), std::slice::from_ref(url))
+    }
+
+    /// Monitor for a child reading several cameras, recorded as `name`
+    pub fn monitor_inputs(&self, name: &str, urls: &[RtspUrl]) -> ProgressMonitor {
+        let (stats, log) = self.entry(name);
+        let monitor = urls
+            .iter()
+            .fold(ProgressMonitor::new(stats), |monitor, url| {
+                monitor.redacting(url.clone())
+            });
+        match (log, &self.log) {
+            (Some(log), Some((config, _))) => monitor.with_log(log, config.stats_interval()),
+            _ => monitor,
+        }
+    }
+
+    /// Current statistics of every camera, ordered by camera id
+    pub fn snapshot(&self) -> Vec<FfmpegStats> {
+        self.cameras
+            .lock()
+            .unwrap()
+            .values()
+            .map(|(stats, _)| stats.snapshot())
+            .collect()
+    }
+
+    fn entry(&self, camera: &str) -> (StatsHandle, Option<RotatingLog>) {
+        self.cameras
+            .lock()
+            .unwrap()
+            .entry(camera.to_string())
+            .or_insert_with(|| {
+                let log = self
+                    .log
+                    .as_ref()
+                    .and_then(|(config, output)| config.open(output, camera));
+                (StatsHandle::new(camera.to_string()), log)
+            })
+            .clone()
+    }
+}
@@ -0,0 +1,376 @@
+//! Retention of recorded segments
+//!
+//! `RetentionManager` periodically deletes old recordings so the disk never
+//! fills up. Each camera directory has a maximum segment age and a maximum
+//! total size. A minimum free-disk threshold applies across all cameras.
+//! Segments are deleted oldest-first. The newest segment in each directory is
+//! the one being written and is never deleted. Every deletion is appended to an
+//! audit file as a JSON line.
+
+use crate::rtsp::shutdown::ShutdownToken;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+/// File extensions treated as recorded segments
+const SEGMENT_EXTENSIONS: &[&str] = &["mp4", "mkv", "avi", "mov", "ts"];
+
+/// Audit file name used when `audit_log` is not set
+const DEFAULT_AUDIT_FILE: &str = "retention_audit.log";
+
+/// Limits for one camera directory
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Delete segments older than this many seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u64>,
+    /// Keep the camera's recordings below this many bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total_bytes: Option<u64>,
+}
+
+/// Retention settings for all recordings
+///
+/// Disabled (nothing is deleted) unless at least one limit is set.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// Default maximum segment age in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u64>,
+    /// Default maximum size of each camera's recordings in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total_bytes: Option<u64>,
+    /// Delete the oldest segments (of any camera) while free disk space is below this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_free_bytes: Option<u64>,
+    /// Seconds between retention passes
+    pub check_interval_secs: u64,
+    /// Audit file for deletions (default: `retention_audit.log` in the output directory)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audit_log: Option<String>,
+    /// Per-camera overrides keyed by camera id (the alias, see `RtspUrl::camera_id`)
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub cameras: HashMap<String, RetentionPolicy>,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            max_age_secs: None,
+            max_total_bytes: None,
+            min_free_bytes: None,
+            check_interval_secs: 60,
+            audit_log: None,
+            cameras: HashMap::new(),
+        }
+    }
+}
+
+impl RetentionConfig {
+    /// Whether any limit is configured
+    pub fn is_enabled(&self) -> bool {
+        self.max_age_secs.is_some()
+            || self.max_total_bytes.is_some()
+            || self.min_free_bytes.is_some()
+            || self
+                .cameras
+                .values()
+                .any(|policy| policy.max_age_secs.is_some() || policy.max_total_bytes.is_some())
+    }
+
+    /// Limits for `camera`, falling back to the defaults field by field
+    pub fn policy_for(&self, camera: &str) -> RetentionPolicy {
+        let overrides = self.cameras.get(camera).copied().unwrap_or_default();
+        RetentionPolicy {
+            max_age_secs: overrides.max_age_secs.or(self.max_age_secs),
+            max_total_bytes: overrides.max_total_bytes.or(self.max_total_bytes),
+        }
+    }
+
+    /// Audit file path, relative to `output_directory` unless configured
+    pub fn audit_path(&self, output_directory: &str) -> PathBuf {
+        match &self.audit_log {
+            Some(path) => PathBuf::from(path),
+            None => Path::new(output_directory).join(DEFAULT_AUDIT_FILE),
+        }
+    }
+}
+
+/// Why a segment was deleted
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionReason {
+    MaxAge,
+    MaxTotalBytes,
+    MinFreeDisk,
+}
+
+/// One deleted segment, as written to the audit file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SegmentDeletion {
+    /// RFC 3339 local time of the deletion
+    pub deleted_at: String,
+    pub camera: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// RFC 3339 local time the segment was last written
+    pub modified: String,
+    pub reason: DeletionReason,
+}
+
+/// A segment that may be deleted
+struct Segment {
+    path: PathBuf,
+    bytes: u64,
+    modified: SystemTime,
+}
+
+struct CameraRecordings {
+    camera: String,
+    directory: PathBuf,
+    policy: RetentionPolicy,
+}
+
+/// Enforces `RetentionConfig` on a set of camera directories
+pub struct RetentionManager {
+    min_free_bytes: Option<u64>,
+    check_interval: Duration,
+    audit_path: PathBuf,
+    cameras: Vec<CameraRecordings>,
+}
+
+impl RetentionManager {
+    /// Create a manager writing its audit log to `audit_path`
+    pub fn new(config: &RetentionConfig, audit_path: impl Into<PathBuf>) -> Self {
+        Self {
+            min_free_bytes: config.min_free_bytes,
+            check_interval: Duration::from_secs(config.check_interval_secs.max(1)),
+            audit_path: audit_path.into(),
+            cameras: Vec::new(),
+        }
+    }
+
+    /// Manage the segments in `directory` (not recursive)
+    pub fn add_camera(
+        &mut self,
+        camera: impl Into<String>,
+        directory: impl Into<PathBuf>,
+        policy: RetentionPolicy,
+    ) {
+        self.cameras.push(CameraRecordings {
+            camera: camera.into(),
+            directory: directory.into(),
+            policy,
+        });
+    }
+
+    pub fn audit_path(&self) -> &Path {
+        &self.audit_path
+    }
+
+    /// Run one retention pass and return the deleted segments
+    pub fn enforce(&self) -> io::Result<Vec<SegmentDeletion>> {
+        let now = SystemTime::now();
+        let mut deletions = Vec::new();
+
+        // Deletable segments per camera, oldest first, without the one being written
+        let mut candidates: Vec<Vec<Segment>> = Vec::with_capacity(self.cameras.len());
+
+        for recordings in &self.cameras {
+            let mut segments = list_segments(&recordings.directory)?;
+            let mut total: u64 = segments.iter().map(|s| s.bytes).sum();
+            segments.pop();
+            let mut segments = VecDeque::from(segments);
+
+            if let Some(max_age) = recordings.policy.max_age_secs {
+                let max_age = Duration::from_secs(max_age);
+                while segments.front().is_some_and(|segment| {
+                    now.duration_since(segment.modified).unwrap_or_default() > max_age
+                }) {
+                    let segment = segments.pop_front().unwrap();
+                    total = total.saturating_sub(segment.bytes);
+                    self.delete(recordings, segment, DeletionReason::MaxAge, &mut deletions);
+                }
+            }
+
+            if let Some(max_bytes) = recordings.policy.max_total_bytes {
+                while total > max_bytes {
+                    let Some(segment) = segments.pop_front() else {
+                        break;
+                    };
+                    total = total.saturating_sub(segment.bytes);
+                    self.delete(
+                        recordings,
+                        segment,
+                        DeletionReason::MaxTotalBytes,
+                        &mut deletions,
+                    );
+                }
+            }
+
+            candidates.push(segments.into());
+        }
+
+        if let Some(min_free) = self.min_free_bytes {
+            loop {
+                // Oldest candidate among cameras whose disk is below the threshold
+                let mut oldest: Option<(usize, SystemTime)> = None;
+                for (index, recordings) in self.cameras.iter().enumerate() {
+                    let Some(segment) = candidates[index].first() else {
+                        continue;
+                    };
+                    if fs2::available_space(&recordings.directory)? >= min_free {
+                        continue;
+                    }
+                    if oldest.is_none_or(|(_, modified)| segment.modified < modified) {
+                        oldest = Some((index, segment.modified));
+                    }
+                }
+
+                let Some((index, _)) = oldest else {
+                    break;
+                };
+                let segment = candidates[index].remove(0);
+                self.delete(
+                    &self.cameras[index],
+                    segment,
+                    DeletionReason::MinFreeDisk,
+                    &mut deletions,
+                );
+            }
+
+            for recordings in &self.cameras {
+                if recordings.directory.exists()
+                    && fs2::available_space(&recordings.directory)? < min_free
+                {
+                    eprintln!(
+                        "⚠️ Retention: free space for {} is below {} bytes with nothing left to delete",
+                        recordings.directory.display(),
+                        min_free
+                    );
+                }
+            }
+        }
+
+        Ok(deletions)
+    }
+
+    /// Run retention passes until `shutdown` is triggered
+    pub fn run_until(&self, shutdown: &ShutdownToken) {
+        println!(
+            "🧹 Retention enabled for {} camera(s), audit log: {}",
+            self.cameras.len(),
+            self.audit_path.display()
+        );
+        loop {
+            if let Err(e) = self.enforce() {
+                eprintln!("❌ Retention pass failed: {}", e);
+            }
+            if shutdown.sleep(self.check_interval) {
+                break;
+            }
+        }
+    }
+
+    /// Run retention passes on a background thread until `shutdown` is triggered
+    pub fn spawn(self, shutdown: ShutdownToken) -> JoinHandle<()> {
+        thread::spawn(move || self.run_until(&shutdown))
+    }
+
+    /// Delete `segment`, record it in the audit file and in `deletions`
+    fn delete(
+        &self,
+        recordings: &CameraRecordings,
+        segment: Segment,
+        reason: DeletionReason,
+        deletions: &mut Vec<SegmentDeletion>,
+    ) {
+        if let Err(e) = fs::remove_file(&segment.path) {
+            eprintln!(
+                "❌ Retention: failed to delete {}: {}",
+                segment.path.display(),
+                e
+            );
+            return;
+        }
+
+        let deletion = SegmentDeletion {
+            deleted_at: Local::now().to_rfc3339(),
+            camera: recordings.camera.clone(),
+            path: segment.path,
+            bytes: segment.bytes,
+            modified: DateTime::<Local>::from(segment.modified).to_rfc3339(),
+            reason,
+        };
+        println!(
+            "🗑️ Retention: deleted {} ({} bytes, {:?})",
+            deletion.path.display(),
+            deletion.bytes,
+            reason
+        );
+        if let Err(e) = self.write_audit(&deletion) {
+            eprintln!(
+                "❌ Retention: failed to write audit log {}: {}",
+                self.audit_path.display(),
+                e
+            );
+        }
+        deletions.push(deletion);
+    }
+
+    fn write_audit(&self, deletion: &SegmentDeletion) -> io::Result<()> {
+        if let Some(parent) = self.audit_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let line = serde_json::to_string(deletion)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.audit_path)?;
+        writeln!(file, "{}", line)
+    }
+}
+
+/// Segments directly inside `directory`, oldest first (empty if it doesn't exist)
+fn list_segments(directory: &Path) -> io::Result<Vec<Segment>> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut segments = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let is_segment = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| SEGMENT_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+        let metadata = entry.metadata()?;
+        if !is_segment || !metadata.is_file() {
+            continue;
+        }
+        segments.push(Segment {
+            path,
+            bytes: metadata.len(),
+            modified: metadata.modified()?,
+        });
+    }
+
+    // Names break ties between segments with the same mtime (they contain the start time)
+    segments.sort_by(|a, b| {
+        a.modified
+            .cmp(&b.modified)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    Ok(segments)
+}
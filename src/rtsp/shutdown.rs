@@ -0,0 +1,122 @@
+//! Cooperative shutdown for capture loops and their ffmpeg children
+//!
+//! A `ShutdownToken` is a cheap, cloneable flag shared by every capture
+//! thread. `ShutdownToken::from_signals()` returns the process-wide token that
+//! is triggered by SIGINT/SIGTERM (Ctrl-C). Loops check the token, stop their
+//! ffmpeg child with `stop_child` so the current segment is finalized, and
+//! return normally.
+
+use std::io::{self, Write};
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long an ffmpeg child gets to write its trailer before it is killed
+pub const DEFAULT_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Granularity of `ShutdownToken::sleep`
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shared flag telling capture loops to stop
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl ShutdownToken {
+    /// A token that is only triggered by calling `trigger()`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide token triggered by SIGINT and SIGTERM
+    ///
+    /// The signal handler is installed on the first call; later calls return
+    /// clones of the same token.
+    pub fn from_signals() -> Self {
+        static GLOBAL: OnceLock<ShutdownToken> = OnceLock::new();
+        GLOBAL
+            .get_or_init(|| {
+                let token = ShutdownToken::new();
+                let handler_token = token.clone();
+                let installed = ctrlc::set_handler(move || {
+                    if handler_token.is_triggered() {
+                        eprintln!(
+                            "⚠️ Shutdown already in progress, waiting for ffmpeg to finish..."
+                        );
+                    } else {
+                        println!("🛑 Shutdown requested, stopping capture...");
+                        handler_token.trigger();
+                    }
+                });
+                if let Err(e) = installed {
+                    eprintln!("⚠️ Failed to install signal handler: {}", e);
+                }
+                token
+            })
+            .clone()
+    }
+
+    /// Ask every holder of this token to stop
+    pub fn trigger(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+
+    /// Sleep for `duration`, waking early on shutdown
+    ///
+    /// Returns `true` if shutdown was requested.
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        loop {
+            if self.is_triggered() {
+                return true;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            thread::sleep(POLL_INTERVAL.min(deadline - now));
+        }
+    }
+}
+
+/// Stop an ffmpeg child so that it finalizes its output, then reap it
+///
+/// Sends `q` on the child's stdin (ffmpeg's interactive quit key), which
+/// makes ffmpeg flush and write the container trailer. If the child has no
+/// stdin pipe or has not exited after `timeout`, it is killed.
+pub fn stop_child(child: &mut Child, timeout: Duration) -> io::Result<ExitStatus> {
+    if let Some(status) = child.try_wait()? {
+        return Ok(status);
+    }
+
+    let asked = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(b"q").and_then(|_| stdin.flush()).is_ok(),
+        None => false,
+    };
+
+    if asked {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if let Some(status) = child.try_wait()? {
+                return Ok(status);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        eprintln!(
+            "⚠️ ffmpeg (pid {}) did not exit within {}s, killing it",
+            child.id(),
+            timeout.as_secs()
+        );
+    }
+
+    // The child may exit between the last poll and the kill
+    let _ = child.kill();
+    child.wait()
+}
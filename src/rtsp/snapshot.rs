@@ -0,0 +1,261 @@
+//! Periodic JPEG snapshots
+//!
+//! In snapshot mode the stream stays connected, but only one frame per
+//! `interval_secs` is converted, optionally resized, and encoded. Snapshots
+//! are written to `<camera dir>/YYYY/MM/DD/HHMMSS.jpg`, and `latest.jpg` in
+//! the camera directory is replaced by an atomic rename after each one, so a
+//! dashboard never reads a half-written image.
+
+use crate::rtsp::capture::RTSPCapture;
+use chrono::{DateTime, Local};
+use opencv::{
+    Result,
+    core::{Size, Vector},
+    imgcodecs, imgproc,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Name of the most recent snapshot in the camera directory
+pub const LATEST_SNAPSHOT: &str = "latest.jpg";
+
+/// Snapshot mode settings
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    /// Seconds between snapshots (at least 1)
+    pub interval_secs: u64,
+    /// JPEG quality, 0-100
+    pub quality: u8,
+    /// Resize to this width; the height follows the aspect ratio unless set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<i32>,
+    /// Resize to this height; the width follows the aspect ratio unless set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<i32>,
+    /// Keep `latest.jpg` in the camera directory
+    pub latest: bool,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 10,
+            quality: 85,
+            width: None,
+            height: None,
+            latest: true,
+        }
+    }
+}
+
+impl SnapshotConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(1))
+    }
+
+    /// Output size for a `cols` x `rows` frame, or `None` to keep it
+    pub fn output_size(&self, cols: i32, rows: i32) -> Option<(i32, i32)> {
+        let scaled = |length: i32, target: i32, other: i32| {
+            ((length as f64 * target as f64 / other as f64).round() as i32).max(1)
+        };
+        match (
+            self.width.filter(|w| *w > 0),
+            self.height.filter(|h| *h > 0),
+        ) {
+            (Some(width), Some(height)) => Some((width, height)),
+            (Some(width), None) if cols > 0 => Some((width, scaled(rows, width, cols))),
+            (None, Some(height)) if rows > 0 => Some((scaled(cols, height, rows), height)),
+            _ => None,
+        }
+        .filter(|size| *size != (cols, rows))
+    }
+}
+
+/// `<camera_dir>/YYYY/MM/DD/HHMMSS.jpg` for a snapshot taken `at`
+pub fn snapshot_path(camera_dir: &Path, at: DateTime<Local>) -> PathBuf {
+    camera_dir
+        .join(at.format("%Y").to_string())
+        .join(at.format("%m").to_string())
+        .join(at.format("%d").to_string())
+        .join(at.format("%H%M%S.jpg").to_string())
+}
+
+/// Write `bytes` to a temporary file next to `path`, then rename it over `path`
+pub fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    fs::write(&temporary, bytes)?;
+    fs::rename(&temporary, path)
+}
+
+impl RTSPCapture {
+    /// Write a JPEG snapshot every `interval_secs` until stopped
+    ///
+    /// Frames are grabbed continuously to keep the connection current, and
+    /// the one due at each interval is written. A stream that ends or fails
+    /// to read is reopened after the supervisor's backoff. Stops on `self.shutdown`,
+    /// after `max_frames` snapshots or `max_duration`, and after the first
+    /// snapshot in `run_once` mode.
+    pub fn process_stream_snapshot(&mut self) -> Result<()> {
+        let config = self.snapshot_config.clone().ok_or_else(|| {
+            opencv::Error::new(opencv::core::StsError, "Snapshot config not provided")
+        })?;
+        let camera_dir = self.camera_dir();
+        let interval = config.interval();
+        let params = Vector::from_slice(&[
+            imgcodecs::IMWRITE_JPEG_QUALITY,
+            i32::from(config.quality.min(100)),
+        ]);
+
+        println!(
+            "📸 Snapshot mode for {}: every {}s into {}",
+            self.url,
+            interval.as_secs(),
+            camera_dir.display()
+        );
+
+        let started = Instant::now();
+        let mut taken: u64 = 0;
+        let mut next_due = Instant::now();
+        let mut frame = Mat::default();
+
+        loop {
+            if self.shutdown.is_triggered()
+                || self.max_frames.is_some_and(|max| taken >= max)
+                || self
+                    .max_duration
+                    .is_some_and(|max| started.elapsed() >= max)
+            {
+                break;
+            }
+
+            if self.capture.is_none() {
+                match self.start_opencv_recording() {
+                    Ok(()) => self.supervisor.started(),
+                    Err(e) => {
+                        let delay = self
+                            .supervisor
+                            .failed(format!("Failed to open stream: {}", e));
+                        if self.run_once {
+                            self.supervisor.stopped(None);
+                            return Err(e);
+                        }
+                        self.shutdown.sleep(delay);
+                        continue;
+                    }
+                }
+            }
+            let Some(capture) = &mut self.capture else {
+                continue;
+            };
+
+            let lost = match capture.grab() {
+                Ok(true) => None,
+                Ok(false) => Some("Stream ended".to_string()),
+                Err(e) if self.run_once => {
+                    self.capture = None;
+                    self.supervisor.stopped(None);
+                    return Err(e);
+                }
+                Err(e) => Some(format!("Failed to read stream: {}", e)),
+            };
+            if let Some(reason) = lost {
+                self.capture = None;
+                if self.run_once {
+                    break;
+                }
+                eprintln!(
+                    "❌ Stream lost for {} ({}), reconnecting...",
+                    self.url, reason
+                );
+                let delay = self.supervisor.failed(reason);
+                self.shutdown.sleep(delay);
+                continue;
+            }
+
+            let now = Instant::now();
+            if now < next_due {
+                continue;
+            }
+            match capture.retrieve(&mut frame, 0) {
+                Ok(true) if !frame.empty() => {}
+                Ok(_) => continue,
+                Err(e) => {
+                    eprintln!("⚠️ Failed to decode frame from {}: {}", self.url, e);
+                    continue;
+                }
+            }
+            next_due = now + interval;
+
+            match self.write_snapshot(&config, &camera_dir, &frame, &params) {
+                Ok(path) => {
+                    taken += 1;
+                    self.supervisor.observe_output(Some(SystemTime::now()));
+                    println!("📸 {} → {}", self.url, path.display());
+                }
+                Err(e) => eprintln!("❌ Failed to write snapshot for {}: {}", self.url, e),
+            }
+            if self.run_once {
+                break;
+            }
+        }
+
+        self.capture = None;
+        self.supervisor.stopped(None);
+        println!("✅ Took {} snapshot(s) from {}", taken, self.url);
+        Ok(())
+    }
+
+    /// Resize and encode `frame`, write the dated file and update `latest.jpg`
+    fn write_snapshot(
+        &self,
+        config: &SnapshotConfig,
+        camera_dir: &Path,
+        frame: &Mat,
+        params: &Vector<i32>,
+    ) -> Result<PathBuf> {
+        let resized;
+        let image = match config.output_size(frame.cols(), frame.rows()) {
+            Some((width, height)) => {
+                let mut output = Mat::default();
+                imgproc::resize(
+                    frame,
+                    &mut output,
+                    Size::new(width, height),
+                    0.0,
+                    0.0,
+                    imgproc::INTER_AREA,
+                )?;
+                resized = output;
+                &resized
+            }
+            None => frame,
+        };
+
+        let mut jpeg = Vector::<u8>::new();
+        imgcodecs::imencode(".jpg", image, &mut jpeg, params)?;
+        let bytes = jpeg.to_vec();
+
+        let io_error = |e: io::Error| {
+            opencv::Error::new(
+                opencv::core::StsError,
+                &format!("Failed to write snapshot: {}", e),
+            )
+        };
+        let path = snapshot_path(camera_dir, Local::now());
+        write_atomically(&path, &bytes).map_err(io_error)?;
+        if config.latest {
+            write_atomically(&camera_dir.join(LATEST_SNAPSHOT), &bytes).map_err(io_error)?;
+        }
+        Ok(path)
+    }
+}
@@ -0,0 +1,317 @@
+//! Per-camera supervision for `RTSPCapture`
+//!
+//! A `Supervisor` tracks the health of one camera's ffmpeg child as a small
+//! state machine, computes jittered exponential backoff between restarts and
+//! publishes its status through a cloneable `StatusHandle`. State changes can
+//! also be sent as `CameraEvent`s over a channel.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Health of one camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CameraState {
+    /// ffmpeg was started and no output has appeared yet
+    Connecting,
+    /// Output is being written
+    Streaming,
+    /// ffmpeg is running but output stopped arriving
+    Degraded,
+    /// Waiting before the next restart
+    Backoff,
+    /// Supervision ended
+    Stopped,
+}
+
+/// Restart and health settings for a camera supervisor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SupervisorConfig {
+    /// Delay before the first restart, in seconds
+    pub initial_backoff_secs: f64,
+    /// Upper bound for the restart delay, in seconds
+    pub max_backoff_secs: f64,
+    /// Factor applied to the delay after each consecutive failure
+    pub backoff_multiplier: f64,
+    /// Random spread applied to each delay (0.2 = ±20%)
+    pub jitter: f64,
+    /// A running camera without new output for this long is `Degraded`
+    pub degraded_after_secs: u64,
+    /// In `run_once` mode, give up after this many failed starts
+    pub run_once_max_failures: u32,
+    /// Restart a running child whose output and progress stopped moving for
+    /// this long; 0 disables the stall watchdog
+    pub stall_timeout_secs: u64,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff_secs: 1.0,
+            max_backoff_secs: 60.0,
+            backoff_multiplier: 2.0,
+            jitter: 0.2,
+            degraded_after_secs: 30,
+            run_once_max_failures: 3,
+            stall_timeout_secs: 60,
+        }
+    }
+}
+
+impl SupervisorConfig {
+    /// Stall timeout for the watchdog, `None` when disabled
+    pub fn stall_timeout(&self) -> Option<Duration> {
+        (self.stall_timeout_secs > 0).then(|| Duration::from_secs(self.stall_timeout_secs))
+    }
+
+    /// Restart delay after `failures` consecutive failures (1-based)
+    ///
+    /// `unit` is a number in `[0, 1)` that picks the jitter: 0.5 gives the
+    /// unjittered delay. The result never exceeds `max_backoff_secs`.
+    pub fn backoff_delay(&self, failures: u32, unit: f64) -> Duration {
+        let exponent = failures.saturating_sub(1).min(63) as i32;
+        let base = (self.initial_backoff_secs * self.backoff_multiplier.max(1.0).powi(exponent))
+            .min(self.max_backoff_secs);
+        let spread = self.jitter.clamp(0.0, 1.0) * (2.0 * unit.clamp(0.0, 1.0) - 1.0);
+        let delay = (base * (1.0 + spread)).clamp(0.0, self.max_backoff_secs);
+        Duration::from_secs_f64(delay)
+    }
+}
+
+/// A camera state change
+#[derive(Debug, Clone)]
+pub struct CameraEvent {
+    pub camera: String,
+    pub from: CameraState,
+    pub to: CameraState,
+    /// Error or note that caused the change
+    pub reason: Option<String>,
+    pub at: SystemTime,
+}
+
+/// Point-in-time status of one camera
+#[derive(Debug, Clone)]
+pub struct CameraStatus {
+    pub camera: String,
+    pub state: CameraState,
+    pub last_error: Option<String>,
+    /// Number of times ffmpeg was restarted after a failure
+    pub restart_count: u64,
+    pub consecutive_failures: u32,
+    /// When the current streaming period began
+    pub streaming_since: Option<Instant>,
+    /// Modification time of the newest output (segment or playlist)
+    pub last_segment_at: Option<SystemTime>,
+    /// Number of times a running child was restarted because it stalled
+    pub stall_count: u64,
+    pub last_stall_at: Option<SystemTime>,
+}
+
+impl CameraStatus {
+    /// Time spent in the current streaming period (zero when not streaming)
+    pub fn uptime(&self) -> Duration {
+        self.streaming_since
+            .map(|since| since.elapsed())
+            .unwrap_or_default()
+    }
+}
+
+/// Cloneable, thread-safe view of a camera's status
+#[derive(Debug, Clone)]
+pub struct StatusHandle {
+    inner: Arc<Mutex<CameraStatus>>,
+}
+
+impl StatusHandle {
+    /// Copy of the current status
+    pub fn snapshot(&self) -> CameraStatus {
+        self.inner.lock().unwrap().clone()
+    }
+
+    pub fn state(&self) -> CameraState {
+        self.inner.lock().unwrap().state
+    }
+}
+
+/// Health state machine and restart policy for one camera
+pub struct Supervisor {
+    config: SupervisorConfig,
+    status: StatusHandle,
+    events: Option<Sender<CameraEvent>>,
+    child_started_at: Option<SystemTime>,
+}
+
+impl Supervisor {
+    pub fn new(camera: String, config: SupervisorConfig) -> Self {
+        Self {
+            config,
+            status: StatusHandle {
+                inner: Arc::new(Mutex::new(CameraStatus {
+                    camera,
+                    state: CameraState::Stopped,
+                    last_error: None,
+                    restart_count: 0,
+                    consecutive_failures: 0,
+                    streaming_since: None,
+                    last_segment_at: None,
+                    stall_count: 0,
+                    last_stall_at: None,
+                })),
+            },
+            events: None,
+            child_started_at: None,
+        }
+    }
+
+    pub fn config(&self) -> &SupervisorConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: SupervisorConfig) {
+        self.config = config;
+    }
+
+    /// Rename the camera reported in status and events
+    pub fn set_camera(&mut self, camera: String) {
+        self.status.inner.lock().unwrap().camera = camera;
+    }
+
+    /// Send state changes to `sender`
+    pub fn set_event_sender(&mut self, sender: Sender<CameraEvent>) {
+        self.events = Some(sender);
+    }
+
+    /// Handle for querying the status from other threads
+    pub fn handle(&self) -> StatusHandle {
+        self.status.clone()
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.status.inner.lock().unwrap().consecutive_failures
+    }
+
+    /// ffmpeg was (re)started
+    pub fn started(&mut self) {
+        self.child_started_at = Some(SystemTime::now());
+        self.transition(CameraState::Connecting, None);
+    }
+
+    /// Update health while ffmpeg is running
+    ///
+    /// `newest_output` is the modification time of the newest segment or
+    /// playlist. Output older than the current child does not count.
+    pub fn observe_output(&mut self, newest_output: Option<SystemTime>) {
+        let now = SystemTime::now();
+        let degraded_after = Duration::from_secs(self.config.degraded_after_secs);
+        let started_at = self.child_started_at.unwrap_or(now);
+
+        let fresh_output = newest_output.filter(|time| *time >= started_at);
+        if let Some(time) = fresh_output {
+            self.status.inner.lock().unwrap().last_segment_at = Some(time);
+        }
+
+        let reference = fresh_output.unwrap_or(started_at);
+        let idle = now.duration_since(reference).unwrap_or_default();
+
+        match (fresh_output, idle >= degraded_after) {
+            (Some(_), false) => {
+                {
+                    let mut status = self.status.inner.lock().unwrap();
+                    status.consecutive_failures = 0;
+                    if status.streaming_since.is_none() {
+                        status.streaming_since = Some(Instant::now());
+                    }
+                }
+                self.transition(CameraState::Streaming, None);
+            }
+            (_, true) => self.transition(
+                CameraState::Degraded,
+                Some(format!("No new output for {}s", idle.as_secs())),
+            ),
+            // Still waiting for the first output
+            (None, false) => {}
+        }
+    }
+
+    /// ffmpeg failed to start or exited; returns how long to wait before restarting
+    pub fn failed(&mut self, error: String) -> Duration {
+        let failures = {
+            let mut status = self.status.inner.lock().unwrap();
+            status.consecutive_failures += 1;
+            status.restart_count += 1;
+            status.streaming_since = None;
+            status.last_error = Some(error.clone());
+            status.consecutive_failures
+        };
+        self.child_started_at = None;
+        self.transition(CameraState::Backoff, Some(error));
+        self.config.backoff_delay(failures, jitter_unit())
+    }
+
+    /// A running child stalled and is being restarted; returns how long to wait
+    ///
+    /// Counts as a failure for the backoff, and is also recorded in
+    /// `stall_count` / `last_stall_at`.
+    pub fn stalled(&mut self, reason: String) -> Duration {
+        {
+            let mut status = self.status.inner.lock().unwrap();
+            status.stall_count += 1;
+            status.last_stall_at = Some(SystemTime::now());
+        }
+        self.failed(reason)
+    }
+
+    /// Supervision ended
+    pub fn stopped(&mut self, reason: Option<String>) {
+        self.child_started_at = None;
+        self.status.inner.lock().unwrap().streaming_since = None;
+        self.transition(CameraState::Stopped, reason);
+    }
+
+    fn transition(&mut self, to: CameraState, reason: Option<String>) {
+        let (camera, from) = {
+            let mut status = self.status.inner.lock().unwrap();
+            let from = status.state;
+            if from == to {
+                return;
+            }
+            status.state = to;
+            (status.camera.clone(), from)
+        };
+
+        match &reason {
+            Some(reason) => println!("📡 {}: {:?} -> {:?} ({})", camera, from, to, reason),
+            None => println!("📡 {}: {:?} -> {:?}", camera, from, to),
+        }
+
+        if let Some(sender) = &self.events {
+            // The receiver may have gone away; supervision continues regardless
+            let _ = sender.send(CameraEvent {
+                camera,
+                from,
+                to,
+                reason,
+                at: SystemTime::now(),
+            });
+        }
+    }
+}
+
+/// Pseudo-random number in `[0, 1)` for backoff jitter (splitmix64)
+fn jitter_unit() -> f64 {
+    static STATE: AtomicU64 = AtomicU64::new(0);
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    let mut z = STATE
+        .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed)
+        .wrapping_add(seed);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
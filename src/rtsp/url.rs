@@ -0,0 +1,458 @@
+//! Stream source URLs with credential handling
+//!
+//! `RtspUrl` keeps the connection string (with credentials) apart from what is
+//! shown to people: `Display` and `Debug` print a redacted form, and
+//! `camera_id()` gives a stable, credential-free name for directories and logs.
+//! Sources without a scheme (local files) are accepted as-is.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Placeholder written in place of credentials
+const REDACTED: &str = "***";
+
+/// Query parameters whose values are treated as secrets
+const SECRET_QUERY_KEYS: &[&str] = &[
+    "user", "username", "pass", "password", "pwd", "token", "auth", "key",
+];
+
+/// A stream source (RTSP/HTTP URL or local path) with redacted display
+#[derive(Clone, PartialEq, Eq)]
+pub struct RtspUrl {
+    /// Lowercased scheme, empty for local paths
+    scheme: String,
+    username: Option<String>,
+    password: Option<String>,
+    host: String,
+    port: Option<u16>,
+    /// Path including the leading `/` and any query string (the whole input for local paths)
+    path: String,
+    alias: Option<String>,
+}
+
+impl RtspUrl {
+    /// Parse a source string
+    ///
+    /// Anything without `://` is treated as a local path. Credentials are the
+    /// part of the authority before the last `@`, so passwords containing `@`
+    /// don't need to be percent-encoded.
+    pub fn parse(source: &str) -> Self {
+        let source = source.trim();
+        let Some((scheme, rest)) = source.split_once("://") else {
+            return Self {
+                scheme: String::new(),
+                username: None,
+                password: None,
+                host: String::new(),
+                port: None,
+                path: source.to_string(),
+                alias: None,
+            };
+        };
+
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(index) => rest.split_at(index),
+            None => (rest, ""),
+        };
+
+        let (userinfo, host_port) = match authority.rfind('@') {
+            Some(index) => (Some(&authority[..index]), &authority[index + 1..]),
+            None => (None, authority),
+        };
+
+        let (username, password) = match userinfo {
+            Some(info) => match info.split_once(':') {
+                Some((user, pass)) => (Some(user.to_string()), Some(pass.to_string())),
+                None => (Some(info.to_string()), None),
+            },
+            None => (None, None),
+        };
+
+        // Bracketed IPv6 hosts contain colons themselves
+        let (host, port) = match host_port.rfind(':') {
+            Some(index) if !host_port[index..].contains(']') => {
+                match host_port[index + 1..].parse::<u16>() {
+                    Ok(port) => (&host_port[..index], Some(port)),
+                    Err(_) => (host_port, None),
+                }
+            }
+            _ => (host_port, None),
+        };
+
+        Self {
+            scheme: scheme.to_ascii_lowercase(),
+            username: username.filter(|user| !user.is_empty()),
+            password,
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+            alias: None,
+        }
+    }
+
+    /// Parse `source` and fill in its alias and credentials from configuration
+    ///
+    /// `aliases` maps source strings, as written in the config, to camera names.
+    pub fn resolve(
+        source: &str,
+        aliases: &HashMap<String, String>,
+        credentials: &CredentialSource,
+    ) -> io::Result<Self> {
+        let mut url = Self::parse(source);
+        if let Some(alias) = aliases.get(source) {
+            url = url.with_alias(alias);
+        }
+        credentials.apply(&mut url)?;
+        Ok(url)
+    }
+
+    /// Name this camera (used for `camera_id()` instead of the hash)
+    pub fn with_alias(mut self, alias: &str) -> Self {
+        let alias = alias.trim();
+        self.alias = (!alias.is_empty()).then(|| alias.to_string());
+        self
+    }
+
+    /// Use these credentials for the connection
+    pub fn with_credentials(mut self, username: &str, password: Option<&str>) -> Self {
+        self.username = Some(username.to_string());
+        self.password = password.map(str::to_string);
+        self
+    }
+
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    pub fn alias(&self) -> Option<&str> {
+        self.alias.as_deref()
+    }
+
+    pub fn has_credentials(&self) -> bool {
+        self.username.is_some() || self.password.is_some()
+    }
+
+    /// Whether this is a local path rather than a URL
+    pub fn is_local(&self) -> bool {
+        self.scheme.is_empty() || self.scheme == "file"
+    }
+
+    /// The full source string including credentials, for ffmpeg and OpenCV only
+    pub fn connection_url(&self) -> String {
+        if self.scheme.is_empty() {
+            return self.path.clone();
+        }
+        let userinfo = match (&self.username, &self.password) {
+            (Some(user), Some(pass)) => format!("{}:{}@", user, pass),
+            (Some(user), None) => format!("{}@", user),
+            (None, Some(pass)) => format!(":{}@", pass),
+            (None, None) => String::new(),
+        };
+        format!(
+            "{}://{}{}{}",
+            self.scheme,
+            userinfo,
+            self.host_port(),
+            self.path
+        )
+    }
+
+    /// Source string safe for logs: credentials and secret query values replaced by `***`
+    pub fn redacted(&self) -> String {
+        if self.scheme.is_empty() {
+            return self.path.clone();
+        }
+        let userinfo = if self.has_credentials() {
+            format!("{}@", REDACTED)
+        } else {
+            String::new()
+        };
+        format!(
+            "{}://{}{}{}",
+            self.scheme,
+            userinfo,
+            self.host_port(),
+            redact_query(&self.path)
+        )
+    }
+
+    /// Stable, credential-free identifier for directory and file names
+    ///
+    /// The sanitized alias if one is set, otherwise the host (or file name)
+    /// followed by a short hash of host, port and path. Changing the
+    /// credentials (including secret query values) does not change it.
+    pub fn camera_id(&self) -> String {
+        if let Some(alias) = &self.alias {
+            return sanitize(alias);
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.scheme.as_bytes());
+        hasher.update(b"|");
+        hasher.update(self.host.to_ascii_lowercase().as_bytes());
+        hasher.update(b"|");
+        hasher.update(
+            self.port
+                .map(|p| p.to_string())
+                .unwrap_or_default()
+                .as_bytes(),
+        );
+        hasher.update(b"|");
+        hasher.update(redact_query(&self.path).as_bytes());
+        let hash: String = hasher
+            .finalize()
+            .iter()
+            .take(4)
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        let label = if self.scheme.is_empty() {
+            std::path::Path::new(&self.path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        } else {
+            self.host.clone()
+        };
+        let label = sanitize(&label);
+        if label.is_empty() {
+            hash
+        } else {
+            format!("{}_{}", label, hash)
+        }
+    }
+
+    /// Replace this source's credentials in free text (e.g. ffmpeg stderr)
+    ///
+    /// The userinfo of every URL in `text` is redacted, and the username or
+    /// password on its own only where it stands between delimiters, so a
+    /// password like `1` doesn't mangle every number in the text.
+    pub fn scrub(&self, text: &str) -> String {
+        let text = text.replace(&self.connection_url(), &self.redacted());
+        let mut text = userinfo_pattern()
+            .replace_all(&text, format!("${{scheme}}{}@", REDACTED))
+            .into_owned();
+        for secret in [&self.password, &self.username].into_iter().flatten() {
+            text = redact_token(&text, secret);
+        }
+        text
+    }
+
+    fn host_port(&self) -> String {
+        match self.port {
+            Some(port) => format!("{}:{}", self.host, port),
+            None => self.host.clone(),
+        }
+    }
+}
+
+impl fmt::Display for RtspUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.redacted())
+    }
+}
+
+impl fmt::Debug for RtspUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RtspUrl({})", self.redacted())
+    }
+}
+
+impl From<&str> for RtspUrl {
+    fn from(source: &str) -> Self {
+        Self::parse(source)
+    }
+}
+
+impl From<String> for RtspUrl {
+    fn from(source: String) -> Self {
+        Self::parse(&source)
+    }
+}
+
+/// Username and password for a camera
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// Where to find credentials for URLs that don't embed them
+///
+/// Lookup order: the secrets file entry for the camera alias, then for the
+/// host, then the environment variables, then the file's `"default"` entry.
+/// Credentials written in the URL itself are always kept.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CredentialSource {
+    /// Environment variable holding the username
+    pub username_env: Option<String>,
+    /// Environment variable holding the password
+    pub password_env: Option<String>,
+    /// JSON file mapping camera aliases or hosts (or `"default"`) to
+    /// `{"username": ..., "password": ...}`
+    pub secrets_file: Option<String>,
+}
+
+impl CredentialSource {
+    /// Fill in credentials for `url` if it has none
+    pub fn apply(&self, url: &mut RtspUrl) -> io::Result<()> {
+        if url.has_credentials() || url.is_local() {
+            return Ok(());
+        }
+        if let Some(credentials) = self.lookup(url)? {
+            *url = url
+                .clone()
+                .with_credentials(&credentials.username, credentials.password.as_deref());
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, url: &RtspUrl) -> io::Result<Option<Credentials>> {
+        let secrets = match &self.secrets_file {
+            Some(path) => Self::read_secrets(path)?,
+            None => Arc::default(),
+        };
+
+        let by_name = url
+            .alias()
+            .and_then(|alias| secrets.get(alias))
+            .or_else(|| secrets.get(url.host()))
+            .cloned();
+        if by_name.is_some() {
+            return Ok(by_name);
+        }
+
+        let env_username = self
+            .username_env
+            .as_ref()
+            .and_then(|name| std::env::var(name).ok());
+        if let Some(username) = env_username {
+            let password = self
+                .password_env
+                .as_ref()
+                .and_then(|name| std::env::var(name).ok());
+            return Ok(Some(Credentials { username, password }));
+        }
+
+        Ok(secrets.get("default").cloned())
+    }
+
+    /// Parsed secrets file, read again only when its modification time changes
+    fn read_secrets(path: &str) -> io::Result<Arc<HashMap<String, Credentials>>> {
+        static CACHE: OnceLock<Mutex<HashMap<String, CachedSecrets>>> = OnceLock::new();
+
+        let modified = fs::metadata(path)?.modified().ok();
+        let cache = CACHE.get_or_init(Mutex::default);
+        if let Some(cached) = cache.lock().unwrap().get(path)
+            && modified.is_some()
+            && cached.modified == modified
+        {
+            return Ok(cached.secrets.clone());
+        }
+
+        let content = fs::read_to_string(path)?;
+        let secrets: Arc<HashMap<String, Credentials>> =
+            serde_json::from_str(&content).map(Arc::new).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid secrets file {}: {}", path, e),
+                )
+            })?;
+        cache.lock().unwrap().insert(
+            path.to_string(),
+            CachedSecrets {
+                modified,
+                secrets: secrets.clone(),
+            },
+        );
+        Ok(secrets)
+    }
+}
+
+struct CachedSecrets {
+    modified: Option<SystemTime>,
+    secrets: Arc<HashMap<String, Credentials>>,
+}
+
+/// `scheme://user:pass@` in free text; the userinfo ends at the last `@` of the authority
+fn userinfo_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)(?P<scheme>\b[a-z][a-z0-9+.-]*://)[^\s/?#]*@")
+            .expect("valid userinfo pattern")
+    })
+}
+
+/// Characters that end a token in log lines and URLs
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || "\"'`:;,@/?#&=()[]{}<>".contains(c)
+}
+
+/// Replace occurrences of `secret` in `text` that stand between delimiters
+fn redact_token(text: &str, secret: &str) -> String {
+    if secret.is_empty() {
+        return text.to_string();
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, _) in text.match_indices(secret) {
+        let end = start + secret.len();
+        let before = text[..start].chars().next_back();
+        let after = text[end..].chars().next();
+        if before.is_none_or(is_delimiter) && after.is_none_or(is_delimiter) {
+            result.push_str(&text[last..start]);
+            result.push_str(REDACTED);
+            last = end;
+        }
+    }
+    result.push_str(&text[last..]);
+    result
+}
+
+/// Keep `[A-Za-z0-9_-]`, replacing everything else with `_`
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Replace the values of secret-looking query parameters
+fn redact_query(path: &str) -> String {
+    let Some((base, query)) = path.split_once('?') else {
+        return path.to_string();
+    };
+    let query: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if SECRET_QUERY_KEYS.contains(&key.to_ascii_lowercase().as_str()) => {
+                format!("{}={}", key, REDACTED)
+            }
+            _ => pair.to_string(),
+        })
+        .collect();
+    format!("{}?{}", base, query.join("&"))
+}
@@ -0,0 +1,104 @@
+//! Stall detection for running ffmpeg children
+//!
+//! An ffmpeg child can stay alive after the camera stops sending data. The
+//! `StallWatchdog` watches two signals: the newest output file (its size or
+//! modification time must change) and ffmpeg's `-progress` output (the frame
+//! count or output time must advance). When neither moves for the stall
+//! timeout, the child is considered stalled and should be restarted.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Newest output file of a child at one point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputSnapshot {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl OutputSnapshot {
+    /// Size and modification time of `path`, if it exists
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            path: path.to_path_buf(),
+            bytes: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// Detects a child whose output and progress both stopped moving
+#[derive(Debug)]
+pub struct StallWatchdog {
+    timeout: Option<Duration>,
+    last_activity: Instant,
+    last_output: Option<OutputSnapshot>,
+    last_progress: Option<Instant>,
+}
+
+impl StallWatchdog {
+    /// `None` disables stall detection
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            timeout,
+            last_activity: Instant::now(),
+            last_output: None,
+            last_progress: None,
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// A new child was started at `now`
+    ///
+    /// The last output snapshot is kept, so a file left by the previous child
+    /// doesn't count as activity.
+    pub fn reset(&mut self, now: Instant) {
+        self.last_activity = now;
+        self.last_progress = None;
+    }
+
+    /// Record the current output and progress; returns `true` if the child stalled
+    ///
+    /// `progress` is when ffmpeg's progress last advanced (see
+    /// `ProgressMonitor::last_advance`).
+    pub fn observe(
+        &mut self,
+        now: Instant,
+        output: Option<OutputSnapshot>,
+        progress: Option<Instant>,
+    ) -> bool {
+        if output.is_some() && output != self.last_output {
+            self.last_activity = now;
+        }
+        // Progress carries its own timestamp, which may predate this check
+        if let Some(at) = progress
+            && self.last_progress.is_none_or(|last| at > last)
+        {
+            self.last_activity = self.last_activity.max(at);
+        }
+        if output.is_some() {
+            self.last_output = output;
+        }
+        if progress.is_some() {
+            self.last_progress = progress;
+        }
+
+        self.timeout
+            .is_some_and(|timeout| self.idle(now) >= timeout)
+    }
+
+    /// Time since output or progress last moved
+    pub fn idle(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.last_activity)
+    }
+}
@@ -3,8 +3,14 @@
 //! Provides helper functions for FFmpeg operations including
 //! metadata extraction and recording.
 
-use crate::rtsp_sync::types::{RtspSyncError, StreamMetadata};
-use std::process::{Child, Command, Stdio};
+use crate::rtsp::progress::ProgressMonitor;
+use crate::rtsp::shutdown::{DEFAULT_STOP_TIMEOUT, ShutdownToken, stop_child};
+use crate::rtsp::url::RtspUrl;
+use crate::rtsp_sync::synchronizer::hls_output_args;
+use crate::rtsp_sync::types::{RecordingConfig, RecordingContainer, RtspSyncError, StreamMetadata};
+use chrono::Local;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::Duration;
 
 /// FFmpeg utility functions
 pub struct FFmpegUtils;
@@ -16,8 +22,9 @@ impl FFmpegUtils {
     /// * `url` - RTSP URL to probe
     ///
     /// # Returns
-    /// StreamMetadata with resolution, fps, codec, bitrate info
+    /// StreamMetadata with resolution, fps, codec, bitrate info (URL redacted)
     pub fn get_stream_metadata(url: &str) -> Result<StreamMetadata, RtspSyncError> {
+        let source = RtspUrl::parse(url);
         let mut command = Command::new("ffprobe");
         command.args([
             "-v",
@@ -37,7 +44,7 @@ impl FFmpegUtils {
             .map_err(|e| RtspSyncError::FFmpegError(format!("Failed to execute ffprobe: {}", e)))?;
 
         let mut metadata = StreamMetadata {
-            url: url.to_string(),
+            url: source.to_string(),
             status: "Unknown".to_string(),
             resolution: "Unknown".to_string(),
             fps: 0.0,
@@ -110,7 +117,7 @@ impl FFmpegUtils {
             metadata.status = "Connected".to_string();
             Ok(metadata)
         } else {
-            let error = String::from_utf8_lossy(&output.stderr);
+            let error = source.scrub(&String::from_utf8_lossy(&output.stderr));
             metadata.status = "Error".to_string();
             Err(RtspSyncError::FFmpegError(format!(
                 "Failed to get metadata: {}",
@@ -121,25 +128,114 @@ impl FFmpegUtils {
 
     /// Start FFmpeg recording process
     ///
+    /// Records hour-long mp4 files named `recording_%Y%m%d_%H%M%S.mp4`; see
+    /// `start_segmented_recording` for other lengths, containers and names.
+    ///
     /// # Arguments
     /// * `url` - RTSP URL to record
     /// * `output_directory` - Directory to save recordings
     /// * `with_audio` - Whether to include audio
+    /// * `monitor` - Reads ffmpeg's progress and diagnostics (see
+    ///   `StatsRegistry::monitor`); without one, ffmpeg reports no progress
     ///
     /// # Returns
-    /// Child process handle
+    /// Child process handle with its stderr piped, and the attached monitor
     pub fn start_recording(
         url: &str,
         output_directory: &str,
         with_audio: bool,
-    ) -> Result<Child, RtspSyncError> {
-        let output_file = format!("{}/recording_%Y%m%d_%H%M%S.mp4", output_directory);
+        monitor: Option<ProgressMonitor>,
+    ) -> Result<(Child, Option<ProgressMonitor>), RtspSyncError> {
+        let recording = RecordingConfig {
+            align_to_wall_clock: false,
+            ..Default::default()
+        };
+        Self::start_segmented_recording(
+            url,
+            output_directory,
+            "recording",
+            3600,
+            with_audio,
+            &recording,
+            monitor,
+        )
+    }
 
-        let mut command = Command::new("ffmpeg");
+    /// Start FFmpeg recording into files of `segment_duration` seconds
+    ///
+    /// # Arguments
+    /// * `url` - RTSP URL to record
+    /// * `output_directory` - Directory to save recordings
+    /// * `camera` - Camera id, replaces `{camera}` in the file name
+    /// * `segment_duration` - Length of each file in seconds
+    /// * `with_audio` - Whether to include audio
+    /// * `recording` - Container, audio codec, file name and alignment
+    /// * `monitor` - Reads ffmpeg's progress and diagnostics, as for `start_recording`
+    ///
+    /// With `align_to_wall_clock` files are split at multiples of
+    /// `segment_duration` since the Unix epoch (see `delay_until_boundary`),
+    /// also after a restart, so cameras recording together split together.
+    /// The split offset is fixed at start, so restart the recorder when the
+    /// local UTC offset changes.
+    ///
+    /// # Returns
+    /// Child process handle and the attached monitor, as for `start_recording`
+    pub fn start_segmented_recording(
+        url: &str,
+        output_directory: &str,
+        camera: &str,
+        segment_duration: u64,
+        with_audio: bool,
+        recording: &RecordingConfig,
+        monitor: Option<ProgressMonitor>,
+    ) -> Result<(Child, Option<ProgressMonitor>), RtspSyncError> {
+        let mut command = Self::recording_command(
+            url,
+            output_directory,
+            camera,
+            segment_duration,
+            with_audio,
+            recording,
+            monitor.is_some(),
+        )?;
+        spawn_ffmpeg(&mut command, monitor)
+            .map_err(|e| RtspSyncError::FFmpegError(format!("Failed to start FFmpeg: {}", e)))
+    }
+
+    /// FFmpeg command used by `start_segmented_recording` without a monitor, not yet spawned
+    pub fn segmented_recording_command(
+        url: &str,
+        output_directory: &str,
+        camera: &str,
+        segment_duration: u64,
+        with_audio: bool,
+        recording: &RecordingConfig,
+    ) -> Result<Command, RtspSyncError> {
+        Self::recording_command(
+            url,
+            output_directory,
+            camera,
+            segment_duration,
+            with_audio,
+            recording,
+            false,
+        )
+    }
+
+    fn recording_command(
+        url: &str,
+        output_directory: &str,
+        camera: &str,
+        segment_duration: u64,
+        with_audio: bool,
+        recording: &RecordingConfig,
+        progress: bool,
+    ) -> Result<Command, RtspSyncError> {
+        let output_file = recording.output_pattern(output_directory, camera)?;
+        let segment_duration = segment_duration.max(1);
+
+        let mut command = ffmpeg_command(progress);
         command
-            .arg("-y")
-            .arg("-loglevel")
-            .arg("warning")
             .arg("-rtsp_transport")
             .arg("tcp")
             .arg("-use_wallclock_as_timestamps")
@@ -147,40 +243,22 @@ impl FFmpegUtils {
             .arg("-i")
             .arg(url)
             .arg("-c:v")
-            .arg("copy")
-            .arg("-f")
-            .arg("segment")
-            .arg("-segment_time")
-            .arg("3600")
-            .arg("-segment_format")
-            .arg("mp4")
-            .arg("-reset_timestamps")
-            .arg("1")
-            .arg("-strftime")
-            .arg("1")
-            .arg("-movflags")
-            .arg("+faststart");
+            .arg("copy");
 
         if with_audio {
-            command
-                .arg("-c:a")
-                .arg("aac")
-                .arg("-b:a")
-                .arg("64k")
-                .arg("-aac_coder")
-                .arg("twoloop");
+            command.arg("-c:a").arg(&recording.audio_codec);
+            if recording.audio_codec != "copy" {
+                command.arg("-b:a").arg(&recording.audio_bitrate);
+            }
+            if recording.audio_codec == "aac" {
+                command.arg("-aac_coder").arg("twoloop");
+            }
         } else {
             command.arg("-an");
         }
 
-        command.arg(&output_file);
-
-        command
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(|e| RtspSyncError::FFmpegError(format!("Failed to start FFmpeg: {}", e)))
+        segment_output_args(&mut command, segment_duration, recording, &output_file);
+        Ok(command)
     }
 
     /// Start HLS streaming for a single stream
@@ -193,6 +271,7 @@ impl FFmpegUtils {
     /// * `playlist_size` - Number of segments to keep
     /// * `with_audio` - Whether to include audio
     /// * `sync_mode` - Whether to use sync flags (-copyts, etc.)
+    /// * `monitor` - Reads ffmpeg's progress and diagnostics, as for `start_recording`
     pub fn start_hls_stream(
         url: &str,
         output_dir: &str,
@@ -201,17 +280,15 @@ impl FFmpegUtils {
         playlist_size: u64,
         with_audio: bool,
         sync_mode: bool,
-    ) -> Result<Child, RtspSyncError> {
+        monitor: Option<ProgressMonitor>,
+    ) -> Result<(Child, Option<ProgressMonitor>), RtspSyncError> {
         let segment_filename = format!("{}_segment_%03d.ts", stream_name);
         let playlist_filename = format!("{}_playlist.m3u8", stream_name);
         let segment_path = format!("{}/{}", output_dir, segment_filename);
         let playlist_path = format!("{}/{}", output_dir, playlist_filename);
 
-        let mut command = Command::new("ffmpeg");
+        let mut command = ffmpeg_command(monitor.is_some());
         command
-            .arg("-y")
-            .arg("-loglevel")
-            .arg("warning")
             .arg("-rtsp_transport")
             .arg("tcp")
             .arg("-i")
@@ -236,24 +313,239 @@ impl FFmpegUtils {
         }
 
         // HLS options
+        hls_output_args(
+            &mut command,
+            segment_duration,
+            playlist_size,
+            &segment_path,
+            &playlist_path,
+            false,
+        );
+
+        spawn_ffmpeg(&mut command, monitor)
+            .map_err(|e| RtspSyncError::FFmpegError(format!("Failed to start HLS stream: {}", e)))
+    }
+
+    /// Whether `url` delivers a video stream within `timeout`
+    ///
+    /// ffprobe is killed at the timeout, so an unreachable camera can't hold
+    /// up the caller for the operating system's connect timeout.
+    pub fn probe_video(url: &str, timeout: Duration) -> bool {
+        let child = Command::new("ffprobe")
+            .args([
+                "-v",
+                "quiet",
+                "-rtsp_transport",
+                "tcp",
+                "-select_streams",
+                "v:0",
+                "-show_entries",
+                "stream=codec_type",
+                "-of",
+                "csv=p=0",
+                "-i",
+                url,
+            ])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
+            return false;
+        };
+
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    let mut output = String::new();
+                    if let Some(mut stdout) = child.stdout.take() {
+                        let _ = std::io::Read::read_to_string(&mut stdout, &mut output);
+                    }
+                    return status.success() && output.contains("video");
+                }
+                Ok(None) if std::time::Instant::now() < deadline => {
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                _ => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return false;
+                }
+            }
+        }
+    }
+
+    /// Whether `stdbuf` is available to line-buffer ffprobe's output
+    pub fn line_buffering_available() -> bool {
+        Command::new("stdbuf")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    /// Start ffprobe printing one line per video packet of `url`
+    ///
+    /// Lines are in compact format (`packet|key=value|...`) and include the
+    /// packet timestamps and side data; see `PacketTiming::parse`. ffprobe
+    /// block-buffers stdout when it is a pipe, so with `line_buffered` it is
+    /// run through `stdbuf -oL` and every line arrives as the packet is read.
+    ///
+    /// stdin is not connected, so `wait_or_stop` kills the child on shutdown.
+    pub fn start_packet_probe(url: &str, line_buffered: bool) -> Result<Child, RtspSyncError> {
+        let mut command = if line_buffered {
+            let mut command = Command::new("stdbuf");
+            command.arg("-oL").arg("ffprobe");
+            command
+        } else {
+            Command::new("ffprobe")
+        };
         command
-            .arg("-f")
-            .arg("hls")
-            .arg("-hls_time")
-            .arg(segment_duration.to_string())
-            .arg("-hls_list_size")
-            .arg(playlist_size.to_string())
-            .arg("-hls_flags")
-            .arg("delete_segments")
-            .arg("-hls_segment_filename")
-            .arg(&segment_path)
-            .arg(&playlist_path);
+            .args([
+                "-v",
+                "quiet",
+                "-rtsp_transport",
+                "tcp",
+                "-select_streams",
+                "v:0",
+                "-show_packets",
+                "-print_format",
+                "compact",
+                "-i",
+            ])
+            .arg(url);
 
         command
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
+            .stderr(Stdio::null())
             .spawn()
-            .map_err(|e| RtspSyncError::FFmpegError(format!("Failed to start HLS stream: {}", e)))
+            .map_err(|e| RtspSyncError::FFmpegError(format!("Failed to start ffprobe: {}", e)))
+    }
+
+    /// Wait for an FFmpeg process, stopping it gracefully on shutdown
+    ///
+    /// # Returns
+    /// * `Some(status)` if the process exited on its own
+    /// * `None` if it was stopped because `shutdown` was triggered
+    pub fn wait_or_stop(
+        child: &mut Child,
+        shutdown: &ShutdownToken,
+    ) -> Result<Option<ExitStatus>, RtspSyncError> {
+        Self::wait_or_stop_polling(child, shutdown, || {})
     }
+
+    /// Like `wait_or_stop`, calling `poll` every half second while the process runs
+    pub fn wait_or_stop_polling(
+        child: &mut Child,
+        shutdown: &ShutdownToken,
+        mut poll: impl FnMut(),
+    ) -> Result<Option<ExitStatus>, RtspSyncError> {
+        loop {
+            if let Some(status) = child
+                .try_wait()
+                .map_err(|e| RtspSyncError::FFmpegError(format!("FFmpeg process failed: {}", e)))?
+            {
+                return Ok(Some(status));
+            }
+            if shutdown.sleep(Duration::from_millis(500)) {
+                stop_child(child, DEFAULT_STOP_TIMEOUT).map_err(|e| {
+                    RtspSyncError::FFmpegError(format!("Failed to stop FFmpeg: {}", e))
+                })?;
+                return Ok(None);
+            }
+            poll();
+        }
+    }
+}
+
+/// Current offset of local time from UTC in seconds
+///
+/// `-segment_clocktime_offset` is derived from it when ffmpeg starts, so an
+/// aligned recorder has to be restarted when it changes (daylight saving).
+pub(crate) fn utc_offset_secs() -> i32 {
+    Local::now().offset().local_minus_utc()
+}
+
+/// `-segment_clocktime_offset` that splits on epoch-based boundaries
+///
+/// ffmpeg splits at multiples of the duration since local midnight, delayed
+/// by the offset; delaying by the UTC offset (in seconds) moves the splits
+/// onto the boundaries used by synchronized HLS.
+pub fn segment_clocktime_offset(segment_duration: u64, utc_offset_secs: i32) -> i64 {
+    i64::from(utc_offset_secs).rem_euclid(segment_duration.max(1) as i64)
+}
+
+/// Append the segment muxer options for `recording`'s container and alignment
+pub(crate) fn segment_output_args(
+    command: &mut Command,
+    segment_duration: u64,
+    recording: &RecordingConfig,
+    output_file: &str,
+) {
+    command
+        .arg("-f")
+        .arg("segment")
+        .arg("-segment_time")
+        .arg(segment_duration.to_string())
+        .arg("-segment_format")
+        .arg(recording.container.segment_format())
+        .arg("-reset_timestamps")
+        .arg("1")
+        .arg("-strftime")
+        .arg("1");
+
+    if recording.align_to_wall_clock {
+        let utc_offset = utc_offset_secs();
+        command
+            .arg("-segment_atclocktime")
+            .arg("1")
+            .arg("-segment_clocktime_offset")
+            .arg(segment_clocktime_offset(segment_duration, utc_offset).to_string());
+    }
+
+    if recording.container == RecordingContainer::Mp4 {
+        command
+            .arg("-segment_format_options")
+            .arg("movflags=+faststart");
+    }
+
+    command.arg(output_file);
+}
+
+/// `ffmpeg -y -loglevel warning -nostats`, writing `-progress` to stdout
+/// only when `progress` is set, i.e. when a `ProgressMonitor` will read it
+pub(crate) fn ffmpeg_command(progress: bool) -> Command {
+    let mut command = Command::new("ffmpeg");
+    command.arg("-y").arg("-loglevel").arg("warning");
+    if progress {
+        command.arg("-progress").arg("pipe:1");
+    }
+    command.arg("-nostats");
+    command
+}
+
+/// Spawn an ffmpeg `command` from `ffmpeg_command` and attach `monitor`
+///
+/// stdin stays open so the child can be asked to quit (see `wait_or_stop`).
+/// stdout is only piped when a monitor reads it, so an unread pipe can't
+/// fill up and block ffmpeg.
+pub(crate) fn spawn_ffmpeg(
+    command: &mut Command,
+    monitor: Option<ProgressMonitor>,
+) -> std::io::Result<(Child, Option<ProgressMonitor>)> {
+    let stdout = if monitor.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    };
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let monitor = monitor.map(|monitor| monitor.attach(&mut child));
+    Ok((child, monitor))
 }
@@ -1,125 +1,436 @@
 //! Latency monitoring for RTSP streams
 //!
-//! Monitors PTS (Presentation Time Stamp) and calculates latency
-//! for multiple RTSP streams.
+//! Keeps one ffprobe packet reader open per stream and compares each video
+//! packet's timestamp with the local time it arrived:
+//! - jitter: RFC 3550 interarrival jitter of the transit time
+//! - drift: slope of the transit time over the window
+//! - gaps: timestamp steps longer than `gap_factor` frame intervals
+//!
+//! Packet timestamps only say when a frame was captured relative to the
+//! stream start. An absolute latency needs the sender's clock, which RTSP
+//! servers publish in RTCP sender reports; ffprobe exposes it per packet as
+//! the wallclock of the "Producer Reference Time" side data. Without it
+//! `TimeInfo::latency` is `None`. `LatencyConfidence` records which of the
+//! numbers can be trusted.
+//!
+//! Every interval the statistics go to the sinks in `LatencyMonitorConfig`
+//! (see `rtsp_sync::metrics`).
 
-use crate::rtsp_sync::types::TimeInfo;
-use chrono::Local;
-use opencv::{prelude::*, videoio};
+use crate::rtsp::shutdown::ShutdownToken;
+use crate::rtsp::supervisor::SupervisorConfig;
+use crate::rtsp::url::RtspUrl;
+use crate::rtsp_sync::ffmpeg_utils::FFmpegUtils;
+use crate::rtsp_sync::metrics::{MetricsCallback, MetricsExporter};
+use crate::rtsp_sync::types::{LatencyConfidence, LatencyMonitorConfig, TimeInfo};
+use chrono::{DateTime, Local};
 use prettytable::{Table, format, row};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader};
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::Duration;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Frame intervals kept for the median frame interval
+const INTERVAL_HISTORY: usize = 100;
+/// Frame intervals needed before gaps are counted
+const MIN_INTERVALS: usize = 10;
+/// A stream without packets for this long (or 3 frame intervals) is not receiving
+const MIN_STALE_AFTER: Duration = Duration::from_secs(2);
+
+/// Timestamps of one video packet, from ffprobe's compact output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PacketTiming {
+    /// Decode timestamp (presentation timestamp if missing) in seconds
+    pub time: f64,
+    /// Sender capture time in microseconds since the Unix epoch (RTCP NTP)
+    pub wallclock_us: Option<i64>,
+}
+
+impl PacketTiming {
+    /// Parse one `packet|key=value|...` line
+    ///
+    /// Returns `None` for other lines and packets without a timestamp.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.trim().split('|');
+        if fields.next()? != "packet" {
+            return None;
+        }
+
+        let (mut dts, mut pts, mut wallclock) = (None, None, None);
+        for field in fields {
+            let Some((key, value)) = field.split_once('=') else {
+                continue;
+            };
+            // Nested side data keys may carry a section prefix
+            let key = key.rsplit(['.', ':']).next().unwrap_or(key);
+            match key {
+                "dts_time" if dts.is_none() => dts = value.parse::<f64>().ok(),
+                "pts_time" if pts.is_none() => pts = value.parse::<f64>().ok(),
+                "wallclock" if wallclock.is_none() => wallclock = value.parse::<i64>().ok(),
+                _ => {}
+            }
+        }
+
+        let time = dts.or(pts).filter(|time| time.is_finite())?;
+        Some(Self {
+            time,
+            wallclock_us: wallclock.filter(|wallclock| *wallclock > 0),
+        })
+    }
+}
+
+/// Packet timing statistics of one stream
+///
+/// Feed every packet to `record` as it arrives; `info` summarizes them.
+#[derive(Debug, Clone)]
+pub struct StreamTiming {
+    window: Duration,
+    min_samples: usize,
+    gap_factor: f64,
+    per_packet_timing: bool,
+    first_received: Option<Instant>,
+    /// (arrival, transit) in seconds since the first packet, inside the window
+    transits: VecDeque<(f64, f64)>,
+    /// Recent positive timestamp steps in seconds
+    intervals: VecDeque<f64>,
+    /// Timestamp and transit of the previous packet
+    previous: Option<(f64, f64)>,
+    jitter: f64,
+    frame_gaps: u64,
+    longest_gap: f64,
+    samples: u64,
+    packets_total: u64,
+    frame_gaps_total: u64,
+    reconnects: u64,
+    latency_ms: Option<i64>,
+    last_time: f64,
+    last_received: Option<(Instant, DateTime<Local>)>,
+}
+
+impl StreamTiming {
+    pub fn new(config: &LatencyMonitorConfig) -> Self {
+        Self {
+            window: config.window(),
+            min_samples: config.min_samples.max(2),
+            gap_factor: config.gap_factor.max(1.0),
+            per_packet_timing: true,
+            first_received: None,
+            transits: VecDeque::new(),
+            intervals: VecDeque::new(),
+            previous: None,
+            jitter: 0.0,
+            frame_gaps: 0,
+            longest_gap: 0.0,
+            samples: 0,
+            packets_total: 0,
+            frame_gaps_total: 0,
+            reconnects: 0,
+            latency_ms: None,
+            last_time: 0.0,
+            last_received: None,
+        }
+    }
+
+    /// Whether packets are recorded as they arrive rather than in buffered batches
+    pub fn with_per_packet_timing(mut self, per_packet_timing: bool) -> Self {
+        self.per_packet_timing = per_packet_timing;
+        self
+    }
+
+    /// Packets recorded on the current connection
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Start over on a new connection, whose timestamps begin again
+    ///
+    /// Totals and the newest packet are kept, so `info` reports the stream as not receiving
+    /// until packets arrive again.
+    pub fn reconnected(&mut self) {
+        self.reconnects += 1;
+        self.first_received = None;
+        self.transits.clear();
+        self.intervals.clear();
+        self.previous = None;
+        self.jitter = 0.0;
+        self.frame_gaps = 0;
+        self.longest_gap = 0.0;
+        self.samples = 0;
+        self.latency_ms = None;
+    }
+
+    /// Add a packet received at `received` (`received_at` in local time)
+    pub fn record(
+        &mut self,
+        packet: PacketTiming,
+        received: Instant,
+        received_at: DateTime<Local>,
+    ) {
+        let first = *self.first_received.get_or_insert(received);
+        let arrival = received.saturating_duration_since(first).as_secs_f64();
+        let transit = arrival - packet.time;
+
+        if let Some((previous_time, previous_transit)) = self.previous {
+            let step = packet.time - previous_time;
+            if step < 0.0 {
+                // Timestamps jumped back (stream restarted): start a new window
+                self.transits.clear();
+                self.intervals.clear();
+            } else if step > 0.0 {
+                // Packets of the same frame (step 0) don't count for jitter
+                self.jitter += ((transit - previous_transit).abs() - self.jitter) / 16.0;
+                if self.intervals.len() >= MIN_INTERVALS
+                    && let Some(interval) = self.frame_interval()
+                    && step > interval * self.gap_factor
+                {
+                    self.frame_gaps += 1;
+                    self.frame_gaps_total += 1;
+                }
+                self.longest_gap = self.longest_gap.max(step);
+                self.intervals.push_back(step);
+                if self.intervals.len() > INTERVAL_HISTORY {
+                    self.intervals.pop_front();
+                }
+            }
+        }
+
+        self.transits.push_back((arrival, transit));
+        let window = self.window.as_secs_f64();
+        while let Some(&(oldest, _)) = self.transits.front()
+            && arrival - oldest > window
+        {
+            self.transits.pop_front();
+        }
+
+        self.previous = Some((packet.time, transit));
+        self.samples += 1;
+        self.packets_total += 1;
+        self.latency_ms = packet
+            .wallclock_us
+            .map(|wallclock| (received_at.timestamp_micros() - wallclock) / 1000);
+        self.last_time = packet.time;
+        self.last_received = Some((received, received_at));
+    }
+
+    /// Median timestamp step in seconds
+    fn frame_interval(&self) -> Option<f64> {
+        let mut intervals: Vec<f64> = self.intervals.iter().copied().collect();
+        intervals.sort_by(f64::total_cmp);
+        intervals.get(intervals.len() / 2).copied()
+    }
+
+    /// Least-squares slope of transit over arrival time (seconds per second)
+    fn drift(&self) -> Option<f64> {
+        if self.transits.len() < 2 {
+            return None;
+        }
+        let count = self.transits.len() as f64;
+        let mean_arrival = self.transits.iter().map(|(x, _)| x).sum::<f64>() / count;
+        let mean_transit = self.transits.iter().map(|(_, y)| y).sum::<f64>() / count;
+        let (covariance, variance) =
+            self.transits
+                .iter()
+                .fold((0.0, 0.0), |(covariance, variance), (x, y)| {
+                    let dx = x - mean_arrival;
+                    (covariance + dx * (y - mean_transit), variance + dx * dx)
+                });
+        (variance > 0.0).then(|| covariance / variance)
+    }
+
+    /// Statistics as of `now`
+    ///
+    /// Before the first packet the counters are zero and `receiving` is false.
+    pub fn info(&self, stream_url: &str, now: Instant) -> TimeInfo {
+        let interval = self.frame_interval();
+        let stale_after = interval
+            .map(|interval| Duration::from_secs_f64(interval * 3.0))
+            .unwrap_or_default()
+            .max(MIN_STALE_AFTER);
+
+        TimeInfo {
+            stream_url: stream_url.to_string(),
+            pts: (self.last_time * 1000.0).round() as i64,
+            local_time: self.last_received.map(|(_, local_time)| local_time),
+            latency: self.latency_ms,
+            jitter_ms: self.jitter * 1000.0,
+            drift_ms_per_min: self.drift().map(|slope| slope * 60_000.0),
+            frame_interval_ms: interval.map(|interval| interval * 1000.0),
+            frame_gaps: self.frame_gaps,
+            longest_gap_ms: (self.longest_gap * 1000.0).round() as i64,
+            samples: self.samples,
+            packets_total: self.packets_total,
+            frame_gaps_total: self.frame_gaps_total,
+            reconnects: self.reconnects,
+            confidence: LatencyConfidence {
+                ntp_anchored: self.latency_ms.is_some(),
+                per_packet_timing: self.per_packet_timing,
+                warmed_up: self.transits.len() >= self.min_samples,
+                receiving: self.last_received.is_some_and(|(received, _)| {
+                    now.saturating_duration_since(received) < stale_after
+                }),
+            },
+        }
+    }
+}
+
+/// One persistent connection and its statistics
+struct StreamReader {
+    url: RtspUrl,
+    timing: Arc<Mutex<StreamTiming>>,
+    handle: JoinHandle<()>,
+}
 
 /// Monitor RTSP stream latency and PTS drift
 pub struct LatencyMonitor {
-    rtsp_url_list: Vec<String>,
+    rtsp_url_list: Vec<RtspUrl>,
     latency_data: Arc<Mutex<HashMap<String, TimeInfo>>>,
-    monitor_interval_ms: u64,
-    display_pts: bool,
+    config: LatencyMonitorConfig,
+    callbacks: Vec<MetricsCallback>,
 }
 
 impl LatencyMonitor {
     /// Create a new latency monitor with default settings
-    pub fn new(rtsp_url_list: Vec<String>) -> Self {
-        LatencyMonitor {
-            rtsp_url_list,
-            latency_data: Arc::new(Mutex::new(HashMap::new())),
-            monitor_interval_ms: 10000,
-            display_pts: true,
-        }
+    pub fn new<U: Into<RtspUrl>>(rtsp_url_list: Vec<U>) -> Self {
+        Self::with_config(rtsp_url_list, 10000, true)
     }
 
     /// Create a new latency monitor with custom configuration
-    pub fn with_config(
-        rtsp_url_list: Vec<String>,
+    ///
+    /// Latency data is keyed by the redacted URL.
+    pub fn with_config<U: Into<RtspUrl>>(
+        rtsp_url_list: Vec<U>,
         monitor_interval_ms: u64,
         display_pts: bool,
     ) -> Self {
-        LatencyMonitor {
+        Self::from_config(
             rtsp_url_list,
+            LatencyMonitorConfig {
+                monitor_interval_ms,
+                display_pts,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Create a new latency monitor from a `LatencyMonitorConfig`
+    pub fn from_config<U: Into<RtspUrl>>(
+        rtsp_url_list: Vec<U>,
+        config: LatencyMonitorConfig,
+    ) -> Self {
+        LatencyMonitor {
+            rtsp_url_list: rtsp_url_list.into_iter().map(Into::into).collect(),
             latency_data: Arc::new(Mutex::new(HashMap::new())),
-            monitor_interval_ms,
-            display_pts,
+            config,
+            callbacks: Vec::new(),
         }
     }
 
+    /// Call `callback` with every interval's statistics, sorted by stream
+    pub fn with_callback(mut self, callback: impl Fn(&[TimeInfo]) + Send + Sync + 'static) -> Self {
+        self.callbacks.push(Arc::new(callback));
+        self
+    }
+
     /// Run the latency monitor (blocking)
     pub fn run(&self) {
+        self.run_until(&ShutdownToken::new());
+    }
+
+    /// Run the latency monitor until `shutdown` is triggered (blocking)
+    pub fn run_until(&self, shutdown: &ShutdownToken) {
         println!("📊 Starting latency monitor...");
         println!("   Streams: {}", self.rtsp_url_list.len());
-        println!("   Interval: {}ms", self.monitor_interval_ms);
+        println!("   Interval: {}ms", self.config.monitor_interval_ms);
 
-        loop {
-            self.monitor_latency();
-            self.display_table();
-            thread::sleep(Duration::from_millis(self.monitor_interval_ms));
+        let sinks = &self.config.sinks;
+        let mut exporter =
+            MetricsExporter::new(sinks, self.callbacks.clone()).serve(sinks, shutdown);
+        let readers = self.start_readers(shutdown);
+        while !shutdown.sleep(self.interval()) {
+            let samples = self.collect(&readers);
+            exporter.publish(Local::now(), &samples);
+            if sinks.console {
+                self.display_table();
+            }
+        }
+        for reader in readers {
+            let _ = reader.handle.join();
         }
+        exporter.finish();
+        println!("🛑 Latency monitor stopped");
     }
 
-    /// Run the latency monitor for a single iteration (non-blocking)
+    /// Measure every stream for one monitoring interval, then disconnect
+    ///
+    /// The result goes to the console, the metrics file and the callbacks,
+    /// but no Prometheus endpoint is started.
     pub fn run_once(&self) -> HashMap<String, TimeInfo> {
-        self.monitor_latency();
-        self.display_table();
+        let mut exporter = MetricsExporter::new(&self.config.sinks, self.callbacks.clone());
+        let stop = ShutdownToken::new();
+        let readers = self.start_readers(&stop);
+        thread::sleep(self.interval());
+        let samples = self.collect(&readers);
+        stop.trigger();
+        for reader in readers {
+            let _ = reader.handle.join();
+        }
+        exporter.publish(Local::now(), &samples);
+        if self.config.sinks.console {
+            self.display_table();
+        }
         self.latency_data.lock().unwrap().clone()
     }
 
-    /// Monitor latency for all streams
-    fn monitor_latency(&self) {
-        let mut data = self.latency_data.lock().unwrap();
-
-        for url in &self.rtsp_url_list {
-            match self.get_pts_and_local_time(url) {
-                Ok(time_info) => {
-                    data.insert(url.clone(), time_info);
-                }
-                Err(e) => {
-                    eprintln!("❌ Failed to get PTS for {}: {}", url, e);
-                    data.remove(url);
-                }
-            }
-        }
+    fn interval(&self) -> Duration {
+        Duration::from_millis(self.config.monitor_interval_ms)
     }
 
-    /// Get PTS and local time for a stream
-    fn get_pts_and_local_time(&self, url: &str) -> Result<TimeInfo, String> {
-        // Open a temporary capture to inspect the latest frame
-        let mut cap = videoio::VideoCapture::from_file(url, videoio::CAP_ANY as i32)
-            .map_err(|e| format!("Failed to open stream: {}", e))?;
-
-        if !cap.is_opened().map_err(|e| e.to_string())? {
-            return Err("Stream not opened".to_string());
+    /// Open one packet reader per stream; they run until `shutdown`
+    fn start_readers(&self, shutdown: &ShutdownToken) -> Vec<StreamReader> {
+        let per_packet_timing = FFmpegUtils::line_buffering_available();
+        if !per_packet_timing {
+            println!("⚠️  stdbuf not found, packet arrival times are approximate");
         }
 
-        let mut frame = opencv::core::Mat::default();
-        if !cap.read(&mut frame).map_err(|e| e.to_string())? {
-            return Err("Failed to read frame".to_string());
-        }
-
-        // Get PTS (Presentation Time Stamp) in milliseconds
-        let pts_msec = cap
-            .get(videoio::CAP_PROP_POS_MSEC)
-            .map_err(|e| e.to_string())?;
-
-        // Current wall clock time
-        let now = Local::now();
-        let local_time_ms = now.timestamp_millis();
-
-        // Convert PTS to milliseconds (integer)
-        let pts_ms = pts_msec as i64;
+        self.rtsp_url_list
+            .iter()
+            .map(|url| {
+                let timing = Arc::new(Mutex::new(
+                    StreamTiming::new(&self.config).with_per_packet_timing(per_packet_timing),
+                ));
+                let handle = {
+                    let url = url.clone();
+                    let timing = Arc::clone(&timing);
+                    let shutdown = shutdown.clone();
+                    thread::spawn(move || read_packets(url, timing, per_packet_timing, shutdown))
+                };
+                StreamReader {
+                    url: url.clone(),
+                    timing,
+                    handle,
+                }
+            })
+            .collect()
+    }
 
-        // Calculate latency (Wall Clock - PTS)
-        // Note: This assumes PTS is somewhat synchronized with wall clock,
-        // which depends on the RTSP source implementation.
-        let latency_ms = local_time_ms - pts_ms;
+    /// Copy the readers' current statistics into `latency_data`
+    ///
+    /// Returns the statistics of every stream, including the ones that never
+    /// connected, so a dead camera shows up as not `receiving` instead of
+    /// disappearing.
+    fn collect(&self, readers: &[StreamReader]) -> Vec<TimeInfo> {
+        let now = Instant::now();
+        let mut data = self.latency_data.lock().unwrap();
+        let mut samples = Vec::new();
 
-        Ok(TimeInfo {
-            stream_url: url.to_string(),
-            pts: pts_ms,
-            local_time: now,
-            latency: latency_ms,
-        })
+        for reader in readers {
+            let key = reader.url.to_string();
+            let info = reader.timing.lock().unwrap().info(&key, now);
+            samples.push(info.clone());
+            data.insert(key, info);
+        }
+        samples.sort_by(|a, b| a.stream_url.cmp(&b.stream_url));
+        samples
     }
 
     /// Display latency data in a table
@@ -134,46 +445,65 @@ impl LatencyMonitor {
         let mut table = Table::new();
         table.set_format(*format::consts::FORMAT_BOX_CHARS);
 
-        if self.display_pts {
+        if self.config.display_pts {
             table.set_titles(row![
                 b->"Stream URL",
                 b->"PTS (s)",
-                b->"Local Time (s)",
-                b->"Latency (s)"
+                b->"Latency (s)",
+                b->"Jitter (ms)",
+                b->"Drift (ms/min)",
+                b->"Gaps",
+                b->"Notes"
+            ]);
+        } else {
+            table.set_titles(row![
+                b->"Stream URL",
+                b->"Latency (s)",
+                b->"Jitter (ms)",
+                b->"Drift (ms/min)",
+                b->"Gaps",
+                b->"Notes"
             ]);
+        }
 
-            for (url, info) in data.iter() {
-                let pts_secs = info.pts as f64 / 1000.0;
-                let local_time_secs = info.local_time.timestamp_millis() as f64 / 1000.0;
-                let latency_secs = info.latency as f64 / 1000.0;
+        let mut urls: Vec<&String> = data.keys().collect();
+        urls.sort();
+        for url in urls {
+            let info = &data[url];
 
-                // Truncate URL for display
-                let display_url = if url.len() > 50 {
-                    format!("...{}", &url[url.len() - 47..])
-                } else {
-                    url.clone()
-                };
+            // Truncate URL for display
+            let display_url = if url.len() > 50 {
+                format!("...{}", &url[url.len() - 47..])
+            } else {
+                url.clone()
+            };
+            let latency = match info.latency {
+                Some(latency) => format!("{:.3}", latency as f64 / 1000.0),
+                None => "-".to_string(),
+            };
+            let drift = match info.drift_ms_per_min {
+                Some(drift) => format!("{:+.2}", drift),
+                None => "-".to_string(),
+            };
+            let jitter = format!("{:.2}", info.jitter_ms);
+            let gaps = format!("{} (max {}ms)", info.frame_gaps, info.longest_gap_ms);
+            let notes = match info.local_time {
+                Some(_) => confidence_notes(&info.confidence),
+                None => "no packets yet".to_string(),
+            };
 
+            if self.config.display_pts {
                 table.add_row(row![
                     display_url,
-                    format!("{:.3}", pts_secs),
-                    format!("{:.3}", local_time_secs),
-                    format!("{:.3}", latency_secs)
+                    format!("{:.3}", info.pts as f64 / 1000.0),
+                    latency,
+                    jitter,
+                    drift,
+                    gaps,
+                    notes
                 ]);
-            }
-        } else {
-            table.set_titles(row![b->"Stream URL", b->"Latency (s)"]);
-
-            for (url, info) in data.iter() {
-                let latency_secs = info.latency as f64 / 1000.0;
-
-                let display_url = if url.len() > 50 {
-                    format!("...{}", &url[url.len() - 47..])
-                } else {
-                    url.clone()
-                };
-
-                table.add_row(row![display_url, format!("{:.3}", latency_secs)]);
+            } else {
+                table.add_row(row![display_url, latency, jitter, drift, gaps, notes]);
             }
         }
 
@@ -190,3 +520,84 @@ impl LatencyMonitor {
         self.latency_data.lock().unwrap().clone()
     }
 }
+
+/// Short description of what limits the numbers of one stream
+fn confidence_notes(confidence: &LatencyConfidence) -> String {
+    let mut notes = Vec::new();
+    notes.push(if confidence.ntp_anchored {
+        "RTCP time"
+    } else {
+        "no RTCP time"
+    });
+    if !confidence.per_packet_timing {
+        notes.push("batched");
+    }
+    if !confidence.warmed_up {
+        notes.push("warming up");
+    }
+    if !confidence.receiving {
+        notes.push("stale");
+    }
+    notes.join(", ")
+}
+
+/// Keep a packet reader open for `url` until `shutdown`, reconnecting with backoff
+fn read_packets(
+    url: RtspUrl,
+    timing: Arc<Mutex<StreamTiming>>,
+    line_buffered: bool,
+    shutdown: ShutdownToken,
+) {
+    let backoff = SupervisorConfig::default();
+    let mut failures = 0;
+    let mut connected_before = false;
+
+    while !shutdown.is_triggered() {
+        if connected_before {
+            timing.lock().unwrap().reconnected();
+        }
+        connected_before = true;
+
+        let reason = match FFmpegUtils::start_packet_probe(&url.connection_url(), line_buffered) {
+            Ok(mut child) => {
+                let reader = child.stdout.take().map(|stdout| {
+                    let timing = Arc::clone(&timing);
+                    thread::spawn(move || {
+                        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                            if let Some(packet) = PacketTiming::parse(&line) {
+                                let mut timing = timing.lock().unwrap();
+                                timing.record(packet, Instant::now(), Local::now());
+                            }
+                        }
+                    })
+                });
+                let result = FFmpegUtils::wait_or_stop(&mut child, &shutdown);
+                if let Some(reader) = reader {
+                    let _ = reader.join();
+                }
+                match result {
+                    Ok(None) => break,
+                    Ok(Some(status)) => format!("ffprobe exited with status: {}", status),
+                    Err(e) => e.to_string(),
+                }
+            }
+            Err(e) => e.to_string(),
+        };
+
+        // A connection that delivered packets starts the backoff over
+        if timing.lock().unwrap().samples() > 0 {
+            failures = 0;
+        }
+        failures += 1;
+        let delay = backoff.backoff_delay(failures, 0.5);
+        eprintln!(
+            "❌ Lost {}: {}, reconnecting in {:.1}s",
+            url,
+            url.scrub(&reason),
+            delay.as_secs_f64()
+        );
+        if shutdown.sleep(delay) {
+            break;
+        }
+    }
+}
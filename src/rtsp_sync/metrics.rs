@@ -0,0 +1,464 @@
+//! Export of latency and stream health metrics
+//!
+//! `LatencyMonitor` hands every interval's `TimeInfo`s to the sinks chosen
+//! in `MetricsSinks`:
+//! - console: the table printed to the terminal
+//! - prometheus: `GET /metrics` on a local address, in Prometheus text format
+//! - file: one appended row per stream and interval, as CSV or JSON lines
+//!
+//! Applications can also register a callback with `LatencyMonitor::with_callback`.
+
+use crate::rtsp::shutdown::ShutdownToken;
+use crate::rtsp_sync::types::TimeInfo;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Content type of the Prometheus text exposition format
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+/// How often the server checks for shutdown while idle
+const ACCEPT_POLL: Duration = Duration::from_millis(200);
+/// Slow clients are dropped after this long
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Called with every interval's statistics, sorted by stream
+pub type MetricsCallback = Arc<dyn Fn(&[TimeInfo]) + Send + Sync>;
+
+/// Layout of the metrics file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricsFileFormat {
+    /// Comma-separated values with a header row
+    #[default]
+    Csv,
+    /// One JSON object per line
+    #[serde(rename = "jsonl")]
+    JsonLines,
+}
+
+/// Where latency metrics go
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsSinks {
+    /// Clear the terminal and print a table every interval
+    pub console: bool,
+    /// Serve Prometheus metrics on this address, e.g. `127.0.0.1:9464`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prometheus: Option<String>,
+    /// Append every interval's statistics to this file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub file_format: MetricsFileFormat,
+}
+
+impl Default for MetricsSinks {
+    fn default() -> Self {
+        Self {
+            console: true,
+            prometheus: None,
+            file: None,
+            file_format: MetricsFileFormat::default(),
+        }
+    }
+}
+
+/// One Prometheus metric taken from each `TimeInfo`
+struct Metric {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    value: fn(&TimeInfo) -> Option<f64>,
+}
+
+const METRICS: &[Metric] = &[
+    Metric {
+        name: "rtsp_latency_seconds",
+        kind: "gauge",
+        help: "Sender capture time to local receive time (only with RTCP NTP time)",
+        value: |info| info.latency.map(|latency| latency as f64 / 1000.0),
+    },
+    Metric {
+        name: "rtsp_jitter_seconds",
+        kind: "gauge",
+        help: "RFC 3550 interarrival jitter",
+        value: |info| Some(info.jitter_ms / 1000.0),
+    },
+    Metric {
+        name: "rtsp_clock_drift_ratio",
+        kind: "gauge",
+        help: "Drift of arrival time against stream timestamps, in seconds per second",
+        value: |info| info.drift_ms_per_min.map(|drift| drift / 60_000.0),
+    },
+    Metric {
+        name: "rtsp_frame_interval_seconds",
+        kind: "gauge",
+        help: "Median timestamp step between frames",
+        value: |info| info.frame_interval_ms.map(|interval| interval / 1000.0),
+    },
+    Metric {
+        name: "rtsp_longest_gap_seconds",
+        kind: "gauge",
+        help: "Longest timestamp step on the current connection",
+        value: |info| Some(info.longest_gap_ms as f64 / 1000.0),
+    },
+    Metric {
+        name: "rtsp_pts_seconds",
+        kind: "gauge",
+        help: "Timestamp of the newest packet since the stream started",
+        value: |info| Some(info.pts as f64 / 1000.0),
+    },
+    Metric {
+        name: "rtsp_last_packet_timestamp_seconds",
+        kind: "gauge",
+        help: "Unix time the newest packet was received",
+        value: |info| {
+            info.local_time
+                .map(|time| time.timestamp_millis() as f64 / 1000.0)
+        },
+    },
+    Metric {
+        name: "rtsp_packets_total",
+        kind: "counter",
+        help: "Video packets received",
+        value: |info| Some(info.packets_total as f64),
+    },
+    Metric {
+        name: "rtsp_frame_gaps_total",
+        kind: "counter",
+        help: "Timestamp steps longer than gap_factor frame intervals",
+        value: |info| Some(info.frame_gaps_total as f64),
+    },
+    Metric {
+        name: "rtsp_reconnects_total",
+        kind: "counter",
+        help: "Times the connection was reopened",
+        value: |info| Some(info.reconnects as f64),
+    },
+];
+
+/// Render `samples` in the Prometheus text exposition format
+///
+/// Every metric is labelled with `stream` (the redacted URL). The
+/// confidence flags are exported as `rtsp_latency_confidence` with a `flag`
+/// label, 1 when set.
+pub fn prometheus_text(samples: &[TimeInfo]) -> String {
+    let mut text = String::new();
+
+    for metric in METRICS {
+        let _ = writeln!(text, "# HELP {} {}", metric.name, metric.help);
+        let _ = writeln!(text, "# TYPE {} {}", metric.name, metric.kind);
+        for info in samples {
+            if let Some(value) = (metric.value)(info) {
+                let _ = writeln!(
+                    text,
+                    "{}{{stream=\"{}\"}} {}",
+                    metric.name,
+                    escape_label(&info.stream_url),
+                    value
+                );
+            }
+        }
+    }
+
+    let name = "rtsp_latency_confidence";
+    let _ = writeln!(text, "# HELP {} Which latency values can be trusted", name);
+    let _ = writeln!(text, "# TYPE {} gauge", name);
+    for info in samples {
+        let confidence = &info.confidence;
+        for (flag, set) in [
+            ("ntp_anchored", confidence.ntp_anchored),
+            ("per_packet_timing", confidence.per_packet_timing),
+            ("warmed_up", confidence.warmed_up),
+            ("receiving", confidence.receiving),
+        ] {
+            let _ = writeln!(
+                text,
+                "{}{{stream=\"{}\",flag=\"{}\"}} {}",
+                name,
+                escape_label(&info.stream_url),
+                flag,
+                u8::from(set)
+            );
+        }
+    }
+    text
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Column names of the CSV metrics file
+pub const CSV_HEADER: &str = "timestamp,stream_url,pts_ms,latency_ms,jitter_ms,\
+drift_ms_per_min,frame_interval_ms,frame_gaps,frame_gaps_total,longest_gap_ms,\
+samples,packets_total,reconnects,ntp_anchored,per_packet_timing,warmed_up,receiving";
+
+/// One CSV row for `info`, collected at `at`; unknown values are left empty
+pub fn csv_row(at: DateTime<Local>, info: &TimeInfo) -> String {
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    let confidence = &info.confidence;
+    [
+        at.to_rfc3339(),
+        csv_field(&info.stream_url),
+        info.pts.to_string(),
+        optional(info.latency.map(|latency| latency.to_string())),
+        format!("{:.3}", info.jitter_ms),
+        optional(info.drift_ms_per_min.map(|drift| format!("{:.3}", drift))),
+        optional(
+            info.frame_interval_ms
+                .map(|interval| format!("{:.3}", interval)),
+        ),
+        info.frame_gaps.to_string(),
+        info.frame_gaps_total.to_string(),
+        info.longest_gap_ms.to_string(),
+        info.samples.to_string(),
+        info.packets_total.to_string(),
+        info.reconnects.to_string(),
+        confidence.ntp_anchored.to_string(),
+        confidence.per_packet_timing.to_string(),
+        confidence.warmed_up.to_string(),
+        confidence.receiving.to_string(),
+    ]
+    .join(",")
+}
+
+/// Quote a CSV field containing separators or quotes
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// JSON-lines entry: the collection time and the statistics
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    timestamp: DateTime<Local>,
+    #[serde(flatten)]
+    info: &'a TimeInfo,
+}
+
+/// Append-only metrics file
+#[derive(Debug)]
+pub struct MetricsFile {
+    path: PathBuf,
+    format: MetricsFileFormat,
+    file: File,
+}
+
+impl MetricsFile {
+    /// Open (or create) `path`; a new CSV file starts with the header row
+    pub fn open(path: impl Into<PathBuf>, format: MetricsFileFormat) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if format == MetricsFileFormat::Csv && file.metadata()?.len() == 0 {
+            writeln!(file, "{}", CSV_HEADER)?;
+        }
+        Ok(Self { path, format, file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one line per stream, collected at `at`
+    pub fn append(&mut self, at: DateTime<Local>, samples: &[TimeInfo]) -> io::Result<()> {
+        let mut lines = String::new();
+        for info in samples {
+            match self.format {
+                MetricsFileFormat::Csv => lines.push_str(&csv_row(at, info)),
+                MetricsFileFormat::JsonLines => lines.push_str(
+                    &serde_json::to_string(&JsonLine {
+                        timestamp: at,
+                        info,
+                    })
+                    .map_err(io::Error::other)?,
+                ),
+            }
+            lines.push('\n');
+        }
+        self.file.write_all(lines.as_bytes())
+    }
+}
+
+/// Minimal HTTP server for `GET /metrics`
+#[derive(Debug)]
+pub struct MetricsServer {
+    address: SocketAddr,
+    body: Arc<Mutex<String>>,
+    handle: JoinHandle<()>,
+}
+
+impl MetricsServer {
+    /// Listen on `address` and serve the latest `update` until `shutdown`
+    pub fn start(address: &str, shutdown: &ShutdownToken) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+        let body = Arc::new(Mutex::new(String::new()));
+
+        let handle = {
+            let body = Arc::clone(&body);
+            let shutdown = shutdown.clone();
+            thread::spawn(move || {
+                loop {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            let body = body.lock().unwrap().clone();
+                            if let Err(e) = respond(stream, &body) {
+                                eprintln!("⚠️  Metrics request failed: {}", e);
+                            }
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                            if shutdown.sleep(ACCEPT_POLL) {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            eprintln!("⚠️  Metrics server error: {}", e);
+                            if shutdown.sleep(ACCEPT_POLL) {
+                                break;
+                            }
+                        }
+                    }
+                }
+            })
+        };
+
+        Ok(Self {
+            address,
+            body,
+            handle,
+        })
+    }
+
+    /// Address the server is bound to (useful with port 0)
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Replace the metrics served from now on
+    pub fn update(&self, body: String) {
+        *self.body.lock().unwrap() = body;
+    }
+
+    /// Wait for the server to stop after shutdown
+    pub fn join(self) {
+        let _ = self.handle.join();
+    }
+}
+
+/// Answer one request: the metrics for `GET /metrics`, 404 otherwise
+fn respond(mut stream: TcpStream, body: &str) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+    // Read the whole request head so closing the socket doesn't reset it
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", PROMETHEUS_CONTENT_TYPE, body),
+        _ => ("404 Not Found", "text/plain; charset=utf-8", "Not found\n"),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// The file and Prometheus sinks plus callbacks, fed once per interval
+pub struct MetricsExporter {
+    file: Option<MetricsFile>,
+    server: Option<MetricsServer>,
+    callbacks: Vec<MetricsCallback>,
+}
+
+impl MetricsExporter {
+    /// Open the file sink; a sink that fails to open is reported and skipped
+    pub fn new(sinks: &MetricsSinks, callbacks: Vec<MetricsCallback>) -> Self {
+        let file = sinks.file.as_ref().and_then(|path| {
+            MetricsFile::open(path, sinks.file_format)
+                .inspect(|_| println!("   Metrics file: {}", path))
+                .inspect_err(|e| eprintln!("❌ Failed to open metrics file {}: {}", path, e))
+                .ok()
+        });
+        Self {
+            file,
+            server: None,
+            callbacks,
+        }
+    }
+
+    /// Also start the Prometheus endpoint, if configured, until `shutdown`
+    pub fn serve(mut self, sinks: &MetricsSinks, shutdown: &ShutdownToken) -> Self {
+        if let Some(address) = &sinks.prometheus {
+            match MetricsServer::start(address, shutdown) {
+                Ok(server) => {
+                    println!(
+                        "   Prometheus metrics: http://{}/metrics",
+                        server.local_addr()
+                    );
+                    self.server = Some(server);
+                }
+                Err(e) => eprintln!("❌ Failed to serve metrics on {}: {}", address, e),
+            }
+        }
+        self
+    }
+
+    /// Hand one interval's statistics, collected at `at`, to every sink
+    pub fn publish(&mut self, at: DateTime<Local>, samples: &[TimeInfo]) {
+        if let Some(file) = &mut self.file
+            && let Err(e) = file.append(at, samples)
+        {
+            eprintln!(
+                "❌ Failed to write metrics to {}: {}",
+                file.path().display(),
+                e
+            );
+        }
+        if let Some(server) = &self.server {
+            server.update(prometheus_text(samples));
+        }
+        for callback in &self.callbacks {
+            callback(samples);
+        }
+    }
+
+    /// Wait for the Prometheus endpoint to stop
+    pub fn finish(self) {
+        if let Some(server) = self.server {
+            server.join();
+        }
+    }
+}
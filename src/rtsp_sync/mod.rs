@@ -2,22 +2,41 @@
 //!
 //! Provides synchronized RTSP stream capture with support for:
 //! - Wall-clock aligned HLS streaming
-//! - Latency monitoring with PTS tracking
+//! - Latency monitoring with jitter, drift and gap tracking on persistent connections
 //! - Multi-stream recording
-//! - Mode-based stream processing (Preview, Latency, Recording, Sync)
+//! - Synchronization checks of the HLS playlists
+//! - Multi-camera mosaic (grid) output
+//! - Mode-based stream processing (Preview, Latency, Recording, Sync, Verify, Mosaic)
 
 pub mod ffmpeg_utils;
 pub mod latency;
+pub mod metrics;
+pub mod mosaic;
 pub mod processor;
 pub mod synchronizer;
 pub mod types;
+pub mod verify;
 
 // Re-export commonly used items
-pub use ffmpeg_utils::FFmpegUtils;
-pub use latency::LatencyMonitor;
+pub use crate::rtsp::shutdown::{ShutdownToken, stop_child};
+pub use ffmpeg_utils::{FFmpegUtils, segment_clocktime_offset};
+pub use latency::{LatencyMonitor, PacketTiming, StreamTiming};
+pub use metrics::{
+    CSV_HEADER, MetricsCallback, MetricsExporter, MetricsFile, MetricsFileFormat, MetricsServer,
+    MetricsSinks, csv_row, prometheus_text,
+};
+pub use mosaic::{
+    Mosaic, MosaicConfig, MosaicLayout, MosaicOutput, MosaicTile, TileSource, drawtext_safe,
+};
 pub use processor::StreamProcessor;
-pub use synchronizer::{spawn_single_hls_stream, start_ffmpeg_hls, start_ffmpeg_sync_hls};
+pub use synchronizer::{
+    delay_until_boundary, spawn_single_hls_stream, start_ffmpeg_hls, start_ffmpeg_sync_hls,
+};
 pub use types::{
-    HLSSyncConfig, LatencyMonitorConfig, LogMessage, Mode, RtspSyncConfig, RtspSyncError,
-    StreamMetadata, TimeInfo,
+    HLSSyncConfig, LatencyConfidence, LatencyMonitorConfig, LogMessage, Mode, RecordingConfig,
+    RecordingContainer, RtspSyncConfig, RtspSyncError, StreamMetadata, TimeInfo,
+};
+pub use verify::{
+    PLAYLIST_SUFFIX, SegmentOffset, SegmentTiming, StreamOffsets, StreamTimeline, SyncReport,
+    VerifyConfig, find_playlists, parse_program_date_time, verify_hls_sync,
 };
@@ -0,0 +1,710 @@
+//! Multi-camera mosaic
+//!
+//! One ffmpeg process reads every camera, scales each into its tile, draws
+//! the camera label and the local time on it and stacks the tiles with
+//! `xstack` into a single re-encoded video, written as live HLS (next to the
+//! camera streams) or as segmented MP4.
+//!
+//! Cameras are probed before each start. One that doesn't answer gets a
+//! "NO SIGNAL" tile instead of an input, so it can't hold up the others.
+//! While the mosaic runs, the cameras that are down are probed again every
+//! `recheck_secs` (each probe is one more RTSP session to that camera), and
+//! ffmpeg is restarted with the new set of inputs when one comes back. Live
+//! inputs are not probed: they carry a read timeout, after which ffmpeg ends
+//! that input, and `xstack` runs with `shortest=1`, so the whole graph ends
+//! and the restart gives the silent camera its placeholder. A mosaic whose
+//! output and progress stop moving for the supervisor's stall timeout is
+//! restarted as well.
+
+use crate::rtsp::progress::StatsRegistry;
+use crate::rtsp::shutdown::ShutdownToken;
+use crate::rtsp::supervisor::Supervisor;
+use crate::rtsp::url::RtspUrl;
+use crate::rtsp::watchdog::{OutputSnapshot, StallWatchdog};
+use crate::rtsp_sync::ffmpeg_utils::{FFmpegUtils, segment_output_args, utc_offset_secs};
+use crate::rtsp_sync::processor::newest_file_time;
+use crate::rtsp_sync::synchronizer::{delay_until_boundary, hls_output_args};
+use crate::rtsp_sync::types::{RecordingConfig, RecordingContainer, RtspSyncConfig, RtspSyncError};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::process::{Child, Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Arrangement of the tiles
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MosaicLayout {
+    /// The smallest grid with at least as many columns as rows that fits every camera
+    #[default]
+    Auto,
+    #[serde(rename = "2x2")]
+    Grid2x2,
+    #[serde(rename = "3x3")]
+    Grid3x3,
+    /// `columns` x `rows` tiles
+    Grid { columns: u32, rows: u32 },
+    /// Tiles placed on a grid of cells, one per camera in `rtsp_url_list` order
+    Custom(Vec<MosaicTile>),
+}
+
+/// Position and size of a tile, in cells of `tile_width` x `tile_height`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MosaicTile {
+    pub x: u32,
+    pub y: u32,
+    #[serde(default = "one_cell")]
+    pub width: u32,
+    #[serde(default = "one_cell")]
+    pub height: u32,
+}
+
+fn one_cell() -> u32 {
+    1
+}
+
+impl MosaicTile {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Whether the two tiles share at least one cell
+    pub fn overlaps(&self, other: &MosaicTile) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+}
+
+impl MosaicLayout {
+    /// Tiles for `cameras` cameras
+    ///
+    /// Grids return every cell, row by row, so tiles after the last camera
+    /// stay empty. Fails if the layout has fewer tiles than cameras, or if
+    /// custom tiles overlap.
+    pub fn tiles(&self, cameras: usize) -> Result<Vec<MosaicTile>, RtspSyncError> {
+        let grid = |columns: u32, rows: u32| {
+            (0..rows)
+                .flat_map(|y| (0..columns).map(move |x| MosaicTile::new(x, y, 1, 1)))
+                .collect::<Vec<_>>()
+        };
+        let tiles = match self {
+            MosaicLayout::Auto => {
+                let columns = (cameras.max(1) as f64).sqrt().ceil() as u32;
+                let rows = (cameras.max(1) as u32).div_ceil(columns);
+                grid(columns, rows)
+            }
+            MosaicLayout::Grid2x2 => grid(2, 2),
+            MosaicLayout::Grid3x3 => grid(3, 3),
+            MosaicLayout::Grid { columns, rows } => grid(*columns, *rows),
+            MosaicLayout::Custom(tiles) => {
+                if tiles.iter().any(|tile| tile.width == 0 || tile.height == 0) {
+                    return Err(RtspSyncError::ConfigError(
+                        "Mosaic tiles need a width and height of at least one cell".to_string(),
+                    ));
+                }
+                for (index, tile) in tiles.iter().enumerate() {
+                    if let Some(other) = tiles[index + 1..].iter().find(|o| tile.overlaps(o)) {
+                        return Err(RtspSyncError::ConfigError(format!(
+                            "Mosaic tiles overlap: {:?} and {:?}",
+                            tile, other
+                        )));
+                    }
+                }
+                tiles.clone()
+            }
+        };
+        if tiles.len() < cameras {
+            return Err(RtspSyncError::ConfigError(format!(
+                "Mosaic layout has {} tile(s) for {} camera(s)",
+                tiles.len(),
+                cameras
+            )));
+        }
+        Ok(tiles)
+    }
+}
+
+/// Where the mosaic goes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MosaicOutput {
+    /// Live HLS in `<hls.root_directory>/<name>`, with the `hls` segment settings
+    #[default]
+    Hls,
+    /// MP4 files of `saved_time_duration` in `<output_directory>/<name>`,
+    /// named and aligned like recordings
+    Mp4,
+}
+
+/// Mosaic mode settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MosaicConfig {
+    pub layout: MosaicLayout,
+    /// Size of one grid cell in pixels
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Output frame rate
+    pub fps: u32,
+    pub output: MosaicOutput,
+    /// Directory and file name prefix of the output
+    pub name: String,
+    /// FFmpeg video encoder, preset and bitrate
+    pub video_codec: String,
+    pub preset: String,
+    pub bitrate: String,
+    /// Draw the camera id in the top left corner of each tile
+    pub labels: bool,
+    /// Draw the local time in the bottom left corner of each tile
+    pub timestamps: bool,
+    /// Font for the labels; ffmpeg's default font if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_file: Option<String>,
+    /// How long a camera gets to answer a probe
+    pub probe_timeout_secs: u64,
+    /// How long ffmpeg waits for data from a camera before giving up on it
+    /// (`-timeout` for RTSP, which needs ffmpeg 5 or later, `-rw_timeout`
+    /// for other network inputs); 0 waits forever
+    pub read_timeout_secs: u64,
+    /// Seconds between probes of the cameras that are down while the mosaic runs
+    pub recheck_secs: u64,
+}
+
+impl Default for MosaicConfig {
+    fn default() -> Self {
+        Self {
+            layout: MosaicLayout::Auto,
+            tile_width: 640,
+            tile_height: 360,
+            fps: 15,
+            output: MosaicOutput::Hls,
+            name: "mosaic".to_string(),
+            video_codec: "libx264".to_string(),
+            preset: "veryfast".to_string(),
+            bitrate: "4M".to_string(),
+            labels: true,
+            timestamps: true,
+            font_file: None,
+            probe_timeout_secs: 5,
+            read_timeout_secs: 10,
+            recheck_secs: 10,
+        }
+    }
+}
+
+/// What a tile shows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TileSource {
+    /// ffmpeg input `input`
+    Camera { input: usize, label: String },
+    /// A camera that didn't answer
+    NoSignal { label: String },
+    /// A cell without a camera
+    Empty,
+}
+
+impl MosaicConfig {
+    /// Size of the whole mosaic in pixels
+    pub fn canvas_size(&self, tiles: &[MosaicTile]) -> (u32, u32) {
+        let columns = tiles.iter().map(|t| t.x + t.width).max().unwrap_or(1);
+        let rows = tiles.iter().map(|t| t.y + t.height).max().unwrap_or(1);
+        (columns * self.tile_width, rows * self.tile_height)
+    }
+
+    /// `-filter_complex` graph drawing `sources` into `tiles`, output `[mosaic]`
+    pub fn filter_graph(&self, tiles: &[MosaicTile], sources: &[TileSource]) -> String {
+        let mut chains = Vec::new();
+        let mut positions = Vec::new();
+        for (index, tile) in tiles.iter().enumerate() {
+            let width = tile.width * self.tile_width;
+            let height = tile.height * self.tile_height;
+            let background = format!("color=c=0x202020:s={}x{}:r={}", width, height, self.fps);
+            let mut chain = match sources.get(index).unwrap_or(&TileSource::Empty) {
+                TileSource::Camera { input, label } => format!(
+                    "[{}:v]fps={},scale={}:{}:force_original_aspect_ratio=decrease,\
+                     pad={}:{}:(ow-iw)/2:(oh-ih)/2:color=black,setsar=1{}",
+                    input,
+                    self.fps,
+                    width,
+                    height,
+                    width,
+                    height,
+                    self.overlays(label, height)
+                ),
+                TileSource::NoSignal { label } => format!(
+                    "{},{}{}",
+                    background,
+                    self.drawtext(
+                        "NO SIGNAL",
+                        height / 8,
+                        "(w-text_w)/2",
+                        "(h-text_h)/2",
+                        false
+                    ),
+                    self.overlays(label, height)
+                ),
+                TileSource::Empty => format!("color=c=black:s={}x{}:r={}", width, height, self.fps),
+            };
+            chain.push_str(&format!("[tile{}]", index));
+            chains.push(chain);
+            positions.push(format!(
+                "{}_{}",
+                tile.x * self.tile_width,
+                tile.y * self.tile_height
+            ));
+        }
+
+        let inputs: String = (0..tiles.len()).map(|i| format!("[tile{}]", i)).collect();
+        if tiles.len() == 1 {
+            chains.push(format!("{}null[mosaic]", inputs));
+        } else {
+            // Custom layouts may leave gaps, which xstack paints black; tiles
+            // never overlap (see `tiles`), so the areas add up. `shortest`
+            // ends the mosaic when a camera input times out.
+            let (width, height) = self.canvas_size(tiles);
+            let covered: u32 = tiles
+                .iter()
+                .map(|t| t.width * self.tile_width * t.height * self.tile_height)
+                .sum();
+            let fill = if covered < width * height {
+                ":fill=black"
+            } else {
+                ""
+            };
+            chains.push(format!(
+                "{}xstack=inputs={}:layout={}{}:shortest=1[mosaic]",
+                inputs,
+                tiles.len(),
+                positions.join("|"),
+                fill
+            ));
+        }
+        chains.join(";")
+    }
+
+    /// `,drawtext=...` for the label and timestamp of a tile `height` pixels high
+    fn overlays(&self, label: &str, height: u32) -> String {
+        let size = (height / 18).max(12);
+        let mut overlays = String::new();
+        if self.labels {
+            overlays.push(',');
+            overlays.push_str(&self.drawtext(&drawtext_safe(label), size, "8", "8", true));
+        }
+        if self.timestamps {
+            overlays.push(',');
+            overlays.push_str(&self.drawtext(
+                "%{localtime\\:%Y-%m-%d %X}",
+                size,
+                "8",
+                "h-text_h-8",
+                true,
+            ));
+        }
+        overlays
+    }
+
+    fn drawtext(&self, text: &str, size: u32, x: &str, y: &str, boxed: bool) -> String {
+        let font = match &self.font_file {
+            Some(path) => format!("fontfile='{}':", path),
+            None => String::new(),
+        };
+        let background = if boxed {
+            ":box=1:boxcolor=black@0.5:boxborderw=4"
+        } else {
+            ""
+        };
+        format!(
+            "drawtext={}text='{}':fontcolor=white:fontsize={}{}:x={}:y={}",
+            font, text, size, background, x, y
+        )
+    }
+}
+
+/// `text` with characters that are special in filter graphs replaced by `_`
+pub fn drawtext_safe(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\'' | '\\' | ':' | '%' | ',' | ';' | '[' | ']' | '=' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Which of `cameras` answer within `timeout`, probed in parallel
+fn probe_cameras(cameras: &[RtspUrl], timeout: Duration) -> Vec<bool> {
+    thread::scope(|scope| {
+        let probes: Vec<_> = cameras
+            .iter()
+            .map(|url| {
+                let url = url.connection_url();
+                scope.spawn(move || FFmpegUtils::probe_video(&url, timeout))
+            })
+            .collect();
+        probes
+            .into_iter()
+            .map(|probe| probe.join().unwrap_or(false))
+            .collect()
+    })
+}
+
+/// Newest file in `directory`, for the stall watchdog
+fn newest_output(directory: &str) -> Option<OutputSnapshot> {
+    std::fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| OutputSnapshot::of(&entry.path()))
+        .filter(|snapshot| snapshot.path.is_file())
+        .max_by_key(|snapshot| snapshot.modified)
+}
+
+/// Composes the cameras of an `RtspSyncConfig` into one video
+pub struct Mosaic {
+    cameras: Vec<RtspUrl>,
+    config: MosaicConfig,
+    tiles: Vec<MosaicTile>,
+    /// Destination directory, and the output path or pattern inside it
+    directory: String,
+    output_path: String,
+    segment_path: String,
+    segment_duration: u64,
+    playlist_size: u64,
+    recording: RecordingConfig,
+    supervisor: Supervisor,
+}
+
+impl Mosaic {
+    /// Check the layout and name and work out the output paths
+    pub fn new(cameras: Vec<RtspUrl>, config: &RtspSyncConfig) -> Result<Self, RtspSyncError> {
+        let mosaic = config.mosaic.clone();
+        if mosaic.name.is_empty() || mosaic.name.contains(['/', '\\']) {
+            return Err(RtspSyncError::ConfigError(format!(
+                "Mosaic name '{}' must be a plain file name",
+                mosaic.name
+            )));
+        }
+        if mosaic.tile_width == 0 || mosaic.tile_height == 0 || mosaic.fps == 0 {
+            return Err(RtspSyncError::ConfigError(
+                "Mosaic tile size and fps must not be zero".to_string(),
+            ));
+        }
+        let tiles = mosaic.layout.tiles(cameras.len())?;
+
+        // Recordings' naming and alignment, always as MP4
+        let recording = RecordingConfig {
+            container: RecordingContainer::Mp4,
+            ..config.recording.clone()
+        };
+        let (directory, output_path, segment_path, segment_duration) = match mosaic.output {
+            MosaicOutput::Hls => {
+                let directory = format!("{}/{}", config.hls.root_directory, mosaic.name);
+                (
+                    directory.clone(),
+                    format!("{}/{}_playlist.m3u8", directory, mosaic.name),
+                    format!("{}/{}_segment_%03d.ts", directory, mosaic.name),
+                    config.hls.segment_duration.max(1),
+                )
+            }
+            MosaicOutput::Mp4 => {
+                let directory = format!("{}/{}", config.output_directory, mosaic.name);
+                let pattern = recording.output_pattern(&directory, &mosaic.name)?;
+                (
+                    directory,
+                    pattern,
+                    String::new(),
+                    config.saved_time_duration.max(1),
+                )
+            }
+        };
+
+        Ok(Self {
+            supervisor: Supervisor::new(mosaic.name.clone(), config.supervisor.clone()),
+            cameras,
+            config: mosaic,
+            tiles,
+            directory,
+            output_path,
+            segment_path,
+            segment_duration,
+            playlist_size: config.hls.playlist_size,
+            recording,
+        })
+    }
+
+    /// Output directory of the mosaic
+    pub fn directory(&self) -> &str {
+        &self.directory
+    }
+
+    /// Tile contents when `available[i]` tells whether camera `i` answered
+    pub fn tile_sources(&self, available: &[bool]) -> Vec<TileSource> {
+        let mut input = 0;
+        (0..self.tiles.len())
+            .map(|index| match self.cameras.get(index) {
+                Some(url) if available.get(index).copied().unwrap_or(false) => {
+                    input += 1;
+                    TileSource::Camera {
+                        input: input - 1,
+                        label: url.camera_id(),
+                    }
+                }
+                Some(url) => TileSource::NoSignal {
+                    label: url.camera_id(),
+                },
+                None => TileSource::Empty,
+            })
+            .collect()
+    }
+
+    /// FFmpeg command for the mosaic, reading the cameras marked in `available`
+    pub fn command(&self, available: &[bool]) -> Command {
+        let sources = self.tile_sources(available);
+
+        let mut command = Command::new("ffmpeg");
+        command
+            .arg("-y")
+            .arg("-loglevel")
+            .arg("warning")
+            .arg("-progress")
+            .arg("pipe:1")
+            .arg("-nostats");
+        for (url, _) in self
+            .cameras
+            .iter()
+            .zip(available)
+            .filter(|(_, available)| **available)
+        {
+            if self.config.read_timeout_secs > 0 && !url.is_local() {
+                let option = if url.scheme().starts_with("rtsp") {
+                    "-timeout"
+                } else {
+                    "-rw_timeout"
+                };
+                command
+                    .arg(option)
+                    .arg((self.config.read_timeout_secs * 1_000_000).to_string());
+            }
+            command
+                .arg("-rtsp_transport")
+                .arg("tcp")
+                .arg("-use_wallclock_as_timestamps")
+                .arg("1")
+                .arg("-thread_queue_size")
+                .arg("512")
+                .arg("-i")
+                .arg(url.connection_url());
+        }
+
+        // Keyframes on segment boundaries and at least every two seconds
+        let fps = self.config.fps;
+        command
+            .arg("-filter_complex")
+            .arg(self.config.filter_graph(&self.tiles, &sources))
+            .arg("-map")
+            .arg("[mosaic]")
+            .arg("-an")
+            .arg("-c:v")
+            .arg(&self.config.video_codec)
+            .arg("-preset")
+            .arg(&self.config.preset)
+            .arg("-b:v")
+            .arg(&self.config.bitrate)
+            .arg("-pix_fmt")
+            .arg("yuv420p")
+            .arg("-r")
+            .arg(fps.to_string())
+            .arg("-g")
+            .arg((fps * 2).to_string())
+            .arg("-force_key_frames")
+            .arg(format!("expr:gte(t,n_forced*{})", self.segment_duration));
+
+        match self.config.output {
+            MosaicOutput::Hls => hls_output_args(
+                &mut command,
+                self.segment_duration,
+                self.playlist_size,
+                &self.segment_path,
+                &self.output_path,
+                // Restarts continue the playlist, so players don't start over
+                true,
+            ),
+            MosaicOutput::Mp4 => segment_output_args(
+                &mut command,
+                self.segment_duration,
+                &self.recording,
+                &self.output_path,
+            ),
+        }
+        command
+    }
+
+    /// Compose until `shutdown`, restarting ffmpeg with backoff when it exits
+    /// or stalls
+    ///
+    /// Starts on the next segment boundary, like synchronized HLS (MP4 output
+    /// only when `recording.align_to_wall_clock` is set).
+    pub fn run(
+        &mut self,
+        shutdown: &ShutdownToken,
+        stats: &StatsRegistry,
+    ) -> Result<(), RtspSyncError> {
+        std::fs::create_dir_all(&self.directory)?;
+        let (width, height) = self.config.canvas_size(&self.tiles);
+        println!(
+            "🧩 Mosaic of {} camera(s), {}x{} in {} tile(s) → {}",
+            self.cameras.len(),
+            width,
+            height,
+            self.tiles.len(),
+            self.output_path
+        );
+
+        if self.config.output == MosaicOutput::Hls || self.recording.align_to_wall_clock {
+            let delay = delay_until_boundary(Utc::now(), self.segment_duration);
+            println!(
+                "   Waiting {:.1}s for the next {}s boundary...",
+                delay.as_secs_f64(),
+                self.segment_duration
+            );
+            if shutdown.sleep(delay) {
+                println!("🛑 Shutdown requested before the mosaic started");
+                return Ok(());
+            }
+        }
+
+        let timeout = Duration::from_secs(self.config.probe_timeout_secs.max(1));
+        let mut watchdog = StallWatchdog::new(self.supervisor.config().stall_timeout());
+        loop {
+            let available = probe_cameras(&self.cameras, timeout);
+            for (url, _) in self.cameras.iter().zip(&available).filter(|(_, a)| !**a) {
+                eprintln!("⚠️  No signal from {}, showing a placeholder", url);
+            }
+
+            let reason = match self.spawn(&available) {
+                Ok(mut child) => {
+                    self.supervisor.started();
+                    watchdog.reset(Instant::now());
+                    let monitor = stats
+                        .monitor_inputs(&self.config.name, &self.cameras)
+                        .attach(&mut child);
+                    let restart = ShutdownToken::new();
+                    let watcher = self.watch_availability(&available, &restart);
+                    let directory = self.directory.clone();
+                    let supervisor = &mut self.supervisor;
+                    let mut stalled = false;
+                    // MP4 splits follow the UTC offset at start; restart when it changes (DST)
+                    let realign = self.config.output == MosaicOutput::Mp4
+                        && self.recording.align_to_wall_clock;
+                    let utc_offset = utc_offset_secs();
+                    let mut offset_changed = false;
+                    let result = FFmpegUtils::wait_or_stop_polling(&mut child, &restart, || {
+                        if shutdown.is_triggered() {
+                            restart.trigger();
+                        }
+                        if realign && utc_offset_secs() != utc_offset {
+                            offset_changed = true;
+                            restart.trigger();
+                        }
+                        supervisor.observe_output(newest_file_time(&directory));
+                        if watchdog.observe(
+                            Instant::now(),
+                            newest_output(&directory),
+                            monitor.last_advance(),
+                        ) {
+                            stalled = true;
+                            restart.trigger();
+                        }
+                    });
+                    restart.trigger();
+                    let _ = watcher.join();
+                    let last_error = monitor.finish().last_error;
+                    match result {
+                        Ok(None) if shutdown.is_triggered() => break,
+                        Ok(None) if stalled => {
+                            let reason = format!(
+                                "Mosaic stalled: no new output or progress for {}s",
+                                watchdog.idle(Instant::now()).as_secs()
+                            );
+                            eprintln!("⚠️ {}, restarting...", reason);
+                            let delay = self.supervisor.stalled(reason);
+                            if shutdown.sleep(delay) {
+                                break;
+                            }
+                            continue;
+                        }
+                        Ok(None) if offset_changed => {
+                            println!("🔁 UTC offset changed, restarting to keep files aligned");
+                            continue;
+                        }
+                        Ok(None) => {
+                            println!("🔁 A camera came back, rebuilding the mosaic");
+                            continue;
+                        }
+                        Ok(Some(status)) if status.success() => {
+                            "Mosaic ended: a camera stopped sending".to_string()
+                        }
+                        Ok(Some(status)) => match last_error {
+                            Some(line) => {
+                                format!("Mosaic exited with status: {} ({})", status, line)
+                            }
+                            None => format!("Mosaic exited with status: {}", status),
+                        },
+                        Err(e) => format!("Mosaic failed: {}", e),
+                    }
+                }
+                Err(e) => format!("Failed to start mosaic: {}", e),
+            };
+
+            eprintln!("❌ {}", reason);
+            let delay = self.supervisor.failed(reason);
+            println!("   Restarting mosaic in {:.1}s", delay.as_secs_f64());
+            if shutdown.sleep(delay) {
+                break;
+            }
+        }
+
+        self.supervisor.stopped(None);
+        println!("🛑 Mosaic stopped");
+        Ok(())
+    }
+
+    fn spawn(&self, available: &[bool]) -> Result<Child, RtspSyncError> {
+        // stdin stays open so the child can be asked to quit (see `wait_or_stop`)
+        self.command(available)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| RtspSyncError::FFmpegError(format!("Failed to start FFmpeg: {}", e)))
+    }
+
+    /// Trigger `restart` when a camera that is down in `available` answers again
+    ///
+    /// Cameras that are up aren't probed; a drop there ends ffmpeg itself.
+    fn watch_availability(&self, available: &[bool], restart: &ShutdownToken) -> JoinHandle<()> {
+        let down: Vec<RtspUrl> = self
+            .cameras
+            .iter()
+            .zip(available)
+            .filter(|(_, available)| !**available)
+            .map(|(url, _)| url.clone())
+            .collect();
+        let restart = restart.clone();
+        let interval = Duration::from_secs(self.config.recheck_secs.max(1));
+        let timeout = Duration::from_secs(self.config.probe_timeout_secs.max(1));
+
+        thread::spawn(move || {
+            while !restart.sleep(interval) {
+                if !down.is_empty() && probe_cameras(&down, timeout).contains(&true) {
+                    restart.trigger();
+                }
+            }
+        })
+    }
+}
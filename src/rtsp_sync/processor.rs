@@ -3,56 +3,96 @@
 //! Provides centralized orchestration for stream processing
 //! based on different operation modes.
 
-use crate::rtsp_sync::ffmpeg_utils::FFmpegUtils;
+use crate::rtsp::progress::{FfmpegStats, StatsRegistry};
+use crate::rtsp::retention::RetentionManager;
+use crate::rtsp::shutdown::ShutdownToken;
+use crate::rtsp::supervisor::Supervisor;
+use crate::rtsp::url::RtspUrl;
+use crate::rtsp_sync::ffmpeg_utils::{FFmpegUtils, utc_offset_secs};
 use crate::rtsp_sync::latency::LatencyMonitor;
-use crate::rtsp_sync::synchronizer::{start_ffmpeg_hls, start_ffmpeg_sync_hls};
+use crate::rtsp_sync::mosaic::Mosaic;
+use crate::rtsp_sync::synchronizer::{
+    delay_until_boundary, start_ffmpeg_hls, start_ffmpeg_sync_hls,
+};
 use crate::rtsp_sync::types::{LogMessage, Mode, RtspSyncConfig, RtspSyncError, StreamMetadata};
-use chrono::Local;
+use crate::rtsp_sync::verify::verify_hls_sync;
+use chrono::{Local, Utc};
 use prettytable::{Table, format, row};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
+use std::time::SystemTime;
 
 /// Stream processor for orchestrating RTSP stream operations
 pub struct StreamProcessor {
     config: Arc<RtspSyncConfig>,
     log_messages: Arc<Mutex<Vec<LogMessage>>>,
+    shutdown: ShutdownToken,
+    stats: StatsRegistry,
 }
 
 impl StreamProcessor {
     /// Create a new stream processor
     pub fn new(config: RtspSyncConfig) -> Self {
+        let stats =
+            StatsRegistry::new().with_log(config.ffmpeg_log.clone(), &config.output_directory);
         StreamProcessor {
             config: Arc::new(config),
             log_messages: Arc::new(Mutex::new(Vec::new())),
+            shutdown: ShutdownToken::new(),
+            stats,
         }
     }
 
+    /// Stop all streams gracefully when `shutdown` is triggered
+    ///
+    /// Use `ShutdownToken::from_signals()` to react to Ctrl-C and SIGTERM.
+    pub fn with_shutdown(mut self, shutdown: ShutdownToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
     /// Get reference to the configuration
     pub fn config(&self) -> &RtspSyncConfig {
         &self.config
     }
 
+    /// Live ffmpeg statistics of every stream; clones share the same data
+    pub fn stats(&self) -> StatsRegistry {
+        self.stats.clone()
+    }
+
+    /// Current ffmpeg statistics of every stream
+    pub fn camera_stats(&self) -> Vec<FfmpegStats> {
+        self.stats.snapshot()
+    }
+
     /// Process streams based on the configured mode
     pub fn process_streams(&self) -> Result<(), RtspSyncError> {
         // Create required directories
         self.config.create_directories()?;
+        let camera_urls = self.config.camera_urls()?;
 
-        // Start HLS if enabled
-        if self.config.hls.enabled {
+        // Start HLS if enabled (verify mode only reads what another run wrote,
+        // mosaic mode reads the cameras itself)
+        if self.config.hls.enabled && !matches!(self.config.mode, Mode::Verify | Mode::Mosaic) {
             let hls_result = match self.config.mode {
                 Mode::Sync => start_ffmpeg_sync_hls(
-                    self.config.rtsp_url_list.clone(),
+                    camera_urls.clone(),
                     self.config.hls.root_directory.clone(),
                     self.config.hls.segment_duration,
                     self.config.hls.playlist_size,
                     self.config.audio,
+                    &self.shutdown,
+                    &self.stats,
                 ),
                 _ => start_ffmpeg_hls(
-                    self.config.rtsp_url_list.clone(),
+                    camera_urls.clone(),
                     self.config.hls.root_directory.clone(),
                     self.config.hls.segment_duration,
                     self.config.hls.playlist_size,
                     self.config.audio,
+                    &self.shutdown,
+                    &self.stats,
                 ),
             };
 
@@ -78,8 +118,8 @@ impl StreamProcessor {
                     "📺 Starting preview mode for {} streams",
                     self.config.rtsp_url_list.len()
                 );
-                for url in &self.config.rtsp_url_list {
-                    match FFmpegUtils::get_stream_metadata(url) {
+                for url in &camera_urls {
+                    match FFmpegUtils::get_stream_metadata(&url.connection_url()) {
                         Ok(metadata) => {
                             self.display_stream_metadata(&metadata);
                         }
@@ -91,46 +131,109 @@ impl StreamProcessor {
             }
             Mode::Latency => {
                 println!("📊 Running in latency monitoring mode...");
-                let monitor = LatencyMonitor::with_config(
-                    self.config.rtsp_url_list.clone(),
-                    self.config.latency_monitor.monitor_interval_ms,
-                    self.config.latency_monitor.display_pts,
+                let monitor = LatencyMonitor::from_config(
+                    camera_urls.clone(),
+                    self.config.latency_monitor.clone(),
                 );
-                monitor.run();
+                monitor.run_until(&self.shutdown);
             }
             Mode::Recording => {
                 println!(
                     "🎬 Starting recording mode for {} streams",
                     self.config.rtsp_url_list.len()
                 );
+                // Reject a bad file name before waiting for the boundary
+                for url in &camera_urls {
+                    self.config
+                        .recording
+                        .output_pattern(&self.config.output_directory, &url.camera_id())?;
+                }
+                if self.config.recording.align_to_wall_clock {
+                    let delay = delay_until_boundary(Utc::now(), self.config.saved_time_duration);
+                    println!(
+                        "   Waiting {:.1}s for the next {}s boundary...",
+                        delay.as_secs_f64(),
+                        self.config.saved_time_duration
+                    );
+                    if self.shutdown.sleep(delay) {
+                        println!("🛑 Shutdown requested before recording started");
+                        return Ok(());
+                    }
+                }
                 let mut handles = vec![];
-                for url in &self.config.rtsp_url_list {
+                for url in &camera_urls {
                     handles.push(self.spawn_stream_handler(url.clone()));
                 }
+                // Runs until the recordings have finished (or been stopped)
+                let retention_stop = ShutdownToken::new();
+                let retention = self.spawn_retention(&camera_urls, &retention_stop);
                 self.monitor_streams(&handles);
+                if let Some(retention) = retention {
+                    retention_stop.trigger();
+                    let _ = retention.join();
+                }
             }
             Mode::Sync => {
                 // Sync mode is handled by HLS initialization above
                 println!("🔄 Sync mode - HLS streaming with synchronized timestamps");
             }
+            Mode::Verify => {
+                println!(
+                    "🔍 Verifying HLS synchronization in {}",
+                    self.config.hls.root_directory
+                );
+                // The mosaic's HLS output shares the root but isn't a camera
+                let report = verify_hls_sync(
+                    &self.config.hls.root_directory,
+                    self.config.verify.tolerance_ms,
+                    &[self.config.mosaic.name.as_str()],
+                )?;
+                report.print();
+                if let Some(path) = &self.config.verify.report_path {
+                    report.write_json(path)?;
+                    println!("📝 Report written to {}", path);
+                }
+                if !report.in_sync {
+                    let out_of_tolerance = report
+                        .streams
+                        .iter()
+                        .filter(|stream| stream.out_of_tolerance > 0)
+                        .count();
+                    return Err(RtspSyncError::OutOfSync(format!(
+                        "{} stream(s) outside {} ms, {} problem(s)",
+                        out_of_tolerance,
+                        report.tolerance_ms,
+                        report.problems.len()
+                    )));
+                }
+            }
+            Mode::Mosaic => {
+                let mut mosaic = Mosaic::new(camera_urls.clone(), &self.config)?;
+                mosaic.run(&self.shutdown, &self.stats)?;
+            }
         }
 
         Ok(())
     }
 
-    /// Spawn a thread to handle a single stream
-    fn spawn_stream_handler(&self, url: String) -> JoinHandle<()> {
+    /// Spawn a thread that records a single stream
+    ///
+    /// The recorder is restarted with backoff whenever it exits, until
+    /// shutdown.
+    fn spawn_stream_handler(&self, url: RtspUrl) -> JoinHandle<()> {
         let config = Arc::clone(&self.config);
         let log_messages = Arc::clone(&self.log_messages);
+        let shutdown = self.shutdown.clone();
+        let stats = self.stats.clone();
 
         thread::spawn(move || {
-            // Create a unique directory for this stream recording
-            let sanitized_url = url
-                .replace("://", "_")
-                .replace('/', "_")
-                .replace('@', "_")
-                .replace(':', "_");
-            let stream_dir = format!("{}/{}", config.output_directory, sanitized_url);
+            // Create a unique directory for this stream recording (no credentials in the name)
+            let camera = url.camera_id();
+            let stream_dir = format!("{}/{}", config.output_directory, camera);
+            let connection_url = url.connection_url();
+            let source = url.clone();
+            let url = url.to_string();
+            let mut supervisor = Supervisor::new(camera.clone(), config.supervisor.clone());
 
             if let Err(e) = std::fs::create_dir_all(&stream_dir) {
                 Self::log_error(
@@ -143,28 +246,99 @@ impl StreamProcessor {
 
             Self::log_info(&log_messages, &url, "Starting stream recording...");
 
-            match FFmpegUtils::start_recording(&url, &stream_dir, config.audio) {
-                Ok(mut child) => {
-                    Self::log_info(&log_messages, &url, "Recording started successfully");
-                    if let Err(e) = child.wait() {
-                        Self::log_error(
-                            &log_messages,
-                            &url,
-                            &format!("Recording process failed: {}", e),
-                        );
+            loop {
+                let reason = match FFmpegUtils::start_segmented_recording(
+                    &connection_url,
+                    &stream_dir,
+                    &camera,
+                    config.saved_time_duration,
+                    config.audio,
+                    &config.recording,
+                    Some(stats.monitor(&source)),
+                ) {
+                    Ok((mut child, monitor)) => {
+                        supervisor.started();
+                        Self::log_info(&log_messages, &url, "Recording started successfully");
+                        // Splits follow the UTC offset at start; restart when it changes (DST)
+                        let utc_offset = utc_offset_secs();
+                        let restart = ShutdownToken::new();
+                        let result =
+                            FFmpegUtils::wait_or_stop_polling(&mut child, &restart, || {
+                                if shutdown.is_triggered()
+                                    || (config.recording.align_to_wall_clock
+                                        && utc_offset_secs() != utc_offset)
+                                {
+                                    restart.trigger();
+                                }
+                                supervisor.observe_output(newest_file_time(&stream_dir))
+                            });
+                        let last_error = monitor.and_then(|monitor| monitor.finish().last_error);
+                        match result {
+                            Ok(None) if shutdown.is_triggered() => {
+                                Self::log_info(&log_messages, &url, "Recording stopped");
+                                break;
+                            }
+                            Ok(None) => {
+                                Self::log_info(
+                                    &log_messages,
+                                    &url,
+                                    "UTC offset changed, restarting to keep files aligned",
+                                );
+                                continue;
+                            }
+                            Ok(Some(status)) => match last_error {
+                                Some(line) => format!(
+                                    "Recording process exited with status: {} ({})",
+                                    status, line
+                                ),
+                                None => format!("Recording process exited with status: {}", status),
+                            },
+                            Err(e) => format!("Recording process failed: {}", e),
+                        }
                     }
-                }
-                Err(e) => {
-                    Self::log_error(
-                        &log_messages,
-                        &url,
-                        &format!("Failed to start recording: {}", e),
-                    );
+                    Err(e) => format!("Failed to start recording: {}", e),
+                };
+
+                Self::log_error(&log_messages, &url, &reason);
+                let delay = supervisor.failed(reason);
+                Self::log_info(
+                    &log_messages,
+                    &url,
+                    format!("Restarting recording in {:.1}s", delay.as_secs_f64()),
+                );
+                if shutdown.sleep(delay) {
+                    Self::log_info(&log_messages, &url, "Recording stopped");
+                    break;
                 }
             }
+            supervisor.stopped(None);
         })
     }
 
+    /// Start deleting old recordings in the background, if retention is configured
+    fn spawn_retention(
+        &self,
+        camera_urls: &[RtspUrl],
+        stop: &ShutdownToken,
+    ) -> Option<JoinHandle<()>> {
+        let retention = &self.config.retention;
+        if !retention.is_enabled() {
+            return None;
+        }
+
+        let mut manager = RetentionManager::new(
+            retention,
+            retention.audit_path(&self.config.output_directory),
+        );
+        for url in camera_urls {
+            let camera_id = url.camera_id();
+            let directory = format!("{}/{}", self.config.output_directory, camera_id);
+            let policy = retention.policy_for(&camera_id);
+            manager.add_camera(camera_id, directory, policy);
+        }
+        Some(manager.spawn(stop.clone()))
+    }
+
     /// Monitor stream handler threads
     fn monitor_streams(&self, handles: &[JoinHandle<()>]) {
         let mut last_log_count = 0;
@@ -235,3 +409,13 @@ impl StreamProcessor {
         log_messages.lock().unwrap().push(msg);
     }
 }
+
+/// Modification time of the newest file in `directory`
+pub(crate) fn newest_file_time(directory: &str) -> Option<SystemTime> {
+    std::fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| entry.ok()?.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .filter_map(|metadata| metadata.modified().ok())
+        .max()
+}
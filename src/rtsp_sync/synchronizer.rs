@@ -3,10 +3,13 @@
 //! Provides functions to start synchronized HLS streaming
 //! with wall-clock aligned segment boundaries.
 
+use crate::rtsp::progress::{ProgressMonitor, StatsRegistry};
+use crate::rtsp::shutdown::ShutdownToken;
+use crate::rtsp::url::RtspUrl;
+use crate::rtsp_sync::ffmpeg_utils::{FFmpegUtils, ffmpeg_command, spawn_ffmpeg};
 use crate::rtsp_sync::types::RtspSyncError;
-use chrono::Utc;
-use regex::Regex;
-use std::process::{Child, Command, Stdio};
+use chrono::{DateTime, Utc};
+use std::process::{Child, Command};
 use std::thread;
 use std::time::Duration;
 
@@ -22,32 +25,37 @@ use std::time::Duration;
 /// * `segment_duration` - Duration of each HLS segment in seconds
 /// * `playlist_size` - Number of segments to keep in playlist
 /// * `with_audio` - Whether to include audio in the stream
+/// * `shutdown` - Stops the streams (gracefully) when triggered
+/// * `stats` - Receives each stream's ffmpeg progress and errors
 ///
 /// # Returns
 /// * `Ok(())` on success
 /// * `Err(RtspSyncError)` on failure
 pub fn start_ffmpeg_sync_hls(
-    rtsp_urls: Vec<String>,
+    rtsp_urls: Vec<RtspUrl>,
     output_dir: String,
     segment_duration: u64,
     playlist_size: u64,
     with_audio: bool,
+    shutdown: &ShutdownToken,
+    stats: &StatsRegistry,
 ) -> Result<(), RtspSyncError> {
     // 1. Calculate delay until the next segment boundary
     let now = Utc::now();
-    let timestamp = now.timestamp();
-
-    // We want to start at a multiple of segment_duration
-    // e.g., if duration is 15s, start at :00, :15, :30, :45
-    let remainder = timestamp % segment_duration as i64;
-    let delay_secs = segment_duration as i64 - remainder;
+    let delay = delay_until_boundary(now, segment_duration);
 
     println!("⏱️  Synchronizing streams...");
     println!("   Current time: {}", now);
     println!("   Segment duration: {}s", segment_duration);
-    println!("   Waiting {}s for synchronized start...", delay_secs);
-
-    thread::sleep(Duration::from_secs(delay_secs as u64));
+    println!(
+        "   Waiting {:.1}s for synchronized start...",
+        delay.as_secs_f64()
+    );
+
+    if shutdown.sleep(delay) {
+        println!("🛑 Shutdown requested before streams started");
+        return Ok(());
+    }
 
     let start_time = Utc::now();
     println!("🚀 Starting all streams at: {}", start_time);
@@ -60,9 +68,22 @@ pub fn start_ffmpeg_sync_hls(
         playlist_size,
         with_audio,
         true, // sync mode
+        shutdown,
+        stats,
     )
 }
 
+/// Time from `now` until the next multiple of `segment_duration` seconds
+///
+/// Boundaries are counted from the Unix epoch, e.g. with 15s segments
+/// streams start at :00, :15, :30 or :45. Exactly on a boundary this waits
+/// for the next one.
+pub fn delay_until_boundary(now: DateTime<Utc>, segment_duration: u64) -> Duration {
+    let period_ms = segment_duration.max(1) as i64 * 1000;
+    let into_period = now.timestamp_millis().rem_euclid(period_ms);
+    Duration::from_millis((period_ms - into_period) as u64)
+}
+
 /// Start HLS streaming without synchronization
 ///
 /// Starts HLS streaming immediately without waiting for segment boundary.
@@ -73,12 +94,16 @@ pub fn start_ffmpeg_sync_hls(
 /// * `segment_duration` - Duration of each HLS segment in seconds
 /// * `playlist_size` - Number of segments to keep in playlist
 /// * `with_audio` - Whether to include audio in the stream
+/// * `shutdown` - Stops the streams (gracefully) when triggered
+/// * `stats` - Receives each stream's ffmpeg progress and errors
 pub fn start_ffmpeg_hls(
-    rtsp_urls: Vec<String>,
+    rtsp_urls: Vec<RtspUrl>,
     output_dir: String,
     segment_duration: u64,
     playlist_size: u64,
     with_audio: bool,
+    shutdown: &ShutdownToken,
+    stats: &StatsRegistry,
 ) -> Result<(), RtspSyncError> {
     println!("🎬 Starting HLS streaming (non-sync mode)...");
 
@@ -89,27 +114,32 @@ pub fn start_ffmpeg_hls(
         playlist_size,
         with_audio,
         false, // non-sync mode
+        shutdown,
+        stats,
     )
 }
 
 /// Spawn FFmpeg HLS processes for all URLs
 fn spawn_hls_processes(
-    rtsp_urls: Vec<String>,
+    rtsp_urls: Vec<RtspUrl>,
     output_dir: String,
     segment_duration: u64,
     playlist_size: u64,
     with_audio: bool,
     sync_mode: bool,
+    shutdown: &ShutdownToken,
+    stats: &StatsRegistry,
 ) -> Result<(), RtspSyncError> {
     let mut handles = Vec::new();
 
     for url in rtsp_urls {
         let output_dir = output_dir.clone();
+        let shutdown = shutdown.clone();
+        let stats = stats.clone();
 
         let handle = thread::spawn(move || -> Result<(), RtspSyncError> {
-            // Sanitize stream name from URL
-            let re = Regex::new(r"[^\w-]").unwrap();
-            let stream_name = re.replace_all(&url, "_").to_string();
+            // Stream name from the camera id, so credentials stay out of paths
+            let stream_name = url.camera_id();
 
             // Create per-stream directory
             let stream_dir = format!("{}/{}", output_dir, stream_name);
@@ -124,15 +154,12 @@ fn spawn_hls_processes(
             println!("   Starting stream: {}", url);
             println!("   Output: {}", stream_dir);
 
-            let mut command = Command::new("ffmpeg");
+            let mut command = ffmpeg_command(true);
             command
-                .arg("-y")
-                .arg("-loglevel")
-                .arg("warning")
                 .arg("-rtsp_transport")
                 .arg("tcp")
                 .arg("-i")
-                .arg(&url)
+                .arg(url.connection_url())
                 .arg("-c:v")
                 .arg("copy");
 
@@ -153,38 +180,32 @@ fn spawn_hls_processes(
             }
 
             // HLS options
-            command
-                .arg("-f")
-                .arg("hls")
-                .arg("-hls_time")
-                .arg(segment_duration.to_string())
-                .arg("-hls_list_size")
-                .arg(playlist_size.to_string())
-                .arg("-hls_flags")
-                .arg("delete_segments")
-                .arg("-hls_segment_filename")
-                .arg(&segment_path)
-                .arg(&playlist_path);
-
-            let mut child = command
-                .stdin(Stdio::null())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()
+            hls_output_args(
+                &mut command,
+                segment_duration,
+                playlist_size,
+                &segment_path,
+                &playlist_path,
+                false,
+            );
+
+            let (mut child, monitor) = spawn_ffmpeg(&mut command, Some(stats.monitor(&url)))
                 .map_err(|e| {
                     RtspSyncError::FFmpegError(format!("Failed to start FFmpeg: {}", e))
                 })?;
 
-            // Wait for process
-            let status = child
-                .wait()
-                .map_err(|e| RtspSyncError::FFmpegError(format!("FFmpeg process failed: {}", e)))?;
+            // Wait for process (stopped gracefully on shutdown)
+            let Some(status) = FFmpegUtils::wait_or_stop(&mut child, &shutdown)? else {
+                println!("   Stopped stream: {}", url);
+                return Ok(());
+            };
 
             if !status.success() {
-                return Err(RtspSyncError::FFmpegError(format!(
-                    "FFmpeg exited with status: {}",
-                    status
-                )));
+                let reason = match monitor.and_then(|monitor| monitor.finish().last_error) {
+                    Some(line) => format!("FFmpeg exited with status: {} ({})", status, line),
+                    None => format!("FFmpeg exited with status: {}", status),
+                };
+                return Err(RtspSyncError::FFmpegError(reason));
             }
 
             Ok(())
@@ -210,9 +231,46 @@ fn spawn_hls_processes(
     Ok(())
 }
 
+/// Append the HLS muxer options shared by every HLS writer
+///
+/// Segments carry `EXT-X-PROGRAM-DATE-TIME` so verify mode can compare
+/// streams, and old segments are deleted as the playlist rolls. With
+/// `append`, a restarted writer continues the existing playlist (sequence
+/// numbers and segment names go on, marked by a discontinuity) instead of
+/// starting it over.
+pub(crate) fn hls_output_args(
+    command: &mut Command,
+    segment_duration: u64,
+    playlist_size: u64,
+    segment_path: &str,
+    playlist_path: &str,
+    append: bool,
+) {
+    let flags = if append {
+        "delete_segments+program_date_time+append_list+discont_start"
+    } else {
+        "delete_segments+program_date_time"
+    };
+    command
+        .arg("-f")
+        .arg("hls")
+        .arg("-hls_time")
+        .arg(segment_duration.to_string())
+        .arg("-hls_list_size")
+        .arg(playlist_size.to_string())
+        .arg("-hls_flags")
+        .arg(flags)
+        .arg("-hls_segment_filename")
+        .arg(segment_path)
+        .arg(playlist_path);
+}
+
 /// Start a single FFmpeg HLS stream and return the process handle
 ///
-/// This is useful when you need to manage the process lifecycle manually.
+/// This is useful when you need to manage the process lifecycle manually;
+/// stop it with `stop_child` so the current segment is finalized. ffmpeg
+/// only reports progress when `monitor` is given (see `StatsRegistry::monitor`),
+/// and the monitor is returned attached to the child.
 pub fn spawn_single_hls_stream(
     url: &str,
     output_dir: &str,
@@ -220,9 +278,9 @@ pub fn spawn_single_hls_stream(
     playlist_size: u64,
     with_audio: bool,
     sync_mode: bool,
-) -> Result<Child, RtspSyncError> {
-    let re = Regex::new(r"[^\w-]").unwrap();
-    let stream_name = re.replace_all(url, "_").to_string();
+    monitor: Option<ProgressMonitor>,
+) -> Result<(Child, Option<ProgressMonitor>), RtspSyncError> {
+    let stream_name = RtspUrl::parse(url).camera_id();
 
     let stream_dir = format!("{}/{}", output_dir, stream_name);
     std::fs::create_dir_all(&stream_dir)?;
@@ -232,11 +290,8 @@ pub fn spawn_single_hls_stream(
     let segment_path = format!("{}/{}", stream_dir, segment_filename);
     let playlist_path = format!("{}/{}", stream_dir, playlist_filename);
 
-    let mut command = Command::new("ffmpeg");
+    let mut command = ffmpeg_command(monitor.is_some());
     command
-        .arg("-y")
-        .arg("-loglevel")
-        .arg("warning")
         .arg("-rtsp_transport")
         .arg("tcp")
         .arg("-i")
@@ -258,23 +313,16 @@ pub fn spawn_single_hls_stream(
             .arg("-start_at_zero");
     }
 
-    command
-        .arg("-f")
-        .arg("hls")
-        .arg("-hls_time")
-        .arg(segment_duration.to_string())
-        .arg("-hls_list_size")
-        .arg(playlist_size.to_string())
-        .arg("-hls_flags")
-        .arg("delete_segments")
-        .arg("-hls_segment_filename")
-        .arg(&segment_path)
-        .arg(&playlist_path);
+    hls_output_args(
+        &mut command,
+        segment_duration,
+        playlist_size,
+        &segment_path,
+        &playlist_path,
+        false,
+    );
 
-    command
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
+    // stdin stays open so the child can be stopped with `stop_child`
+    spawn_ffmpeg(&mut command, monitor)
         .map_err(|e| RtspSyncError::FFmpegError(format!("Failed to start FFmpeg: {}", e)))
 }
@@ -3,8 +3,17 @@
 //! Contains data models for stream synchronization, latency monitoring,
 //! and configuration.
 
+use crate::rtsp::ffmpeg_log::FfmpegLogConfig;
+use crate::rtsp::retention::RetentionConfig;
+use crate::rtsp::supervisor::SupervisorConfig;
+use crate::rtsp::url::{CredentialSource, RtspUrl};
+use crate::rtsp_sync::metrics::MetricsSinks;
+use crate::rtsp_sync::mosaic::MosaicConfig;
+use crate::rtsp_sync::verify::VerifyConfig;
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
 
 /// Operation mode for the RTSP sync processor
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -19,12 +28,16 @@ pub enum Mode {
     Recording,
     /// Sync mode - synchronized HLS streaming
     Sync,
+    /// Verify mode - check that the HLS playlists of all streams line up
+    Verify,
+    /// Mosaic mode - compose all streams into one grid video
+    Mosaic,
 }
 
 /// Stream metadata information
 #[derive(Debug, Clone, Default)]
 pub struct StreamMetadata {
-    /// RTSP URL of the stream
+    /// RTSP URL of the stream (credentials redacted)
     pub url: String,
     /// Connection status
     pub status: String,
@@ -38,17 +51,52 @@ pub struct StreamMetadata {
     pub bitrate: String,
 }
 
+/// Which of the numbers in a `TimeInfo` can be trusted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LatencyConfidence {
+    /// `latency` is sender capture time (RTCP sender report NTP) to local receive time
+    pub ntp_anchored: bool,
+    /// Packets were timestamped as they arrived, not in buffered batches
+    pub per_packet_timing: bool,
+    /// The window holds enough packets for jitter and drift to be meaningful
+    pub warmed_up: bool,
+    /// Packets are still arriving
+    pub receiving: bool,
+}
+
 /// Time information for latency monitoring
-#[derive(Debug, Clone)]
+///
+/// Measured on a persistent connection by comparing packet timestamps with
+/// the local time each packet was received.
+#[derive(Debug, Clone, Serialize)]
 pub struct TimeInfo {
-    /// RTSP URL of the stream
+    /// RTSP URL of the stream (credentials redacted)
     pub stream_url: String,
-    /// Presentation Time Stamp in milliseconds
+    /// Timestamp of the newest packet in milliseconds since the stream started
     pub pts: i64,
-    /// Local system time
-    pub local_time: DateTime<Local>,
-    /// Calculated latency in milliseconds
-    pub latency: i64,
+    /// Local time the newest packet was received, `None` before the first one
+    pub local_time: Option<DateTime<Local>>,
+    /// Capture-to-receive latency in milliseconds, only known with RTCP NTP time
+    pub latency: Option<i64>,
+    /// Interarrival jitter in milliseconds (RFC 3550 estimator)
+    pub jitter_ms: f64,
+    /// Drift of the stream clock against the local clock, in milliseconds per minute
+    pub drift_ms_per_min: Option<f64>,
+    /// Typical timestamp step between frames in milliseconds
+    pub frame_interval_ms: Option<f64>,
+    /// Timestamp steps longer than `gap_factor` frame intervals
+    pub frame_gaps: u64,
+    /// Longest timestamp step in milliseconds
+    pub longest_gap_ms: i64,
+    /// Packets received on the current connection
+    pub samples: u64,
+    /// Packets received on all connections
+    pub packets_total: u64,
+    /// Frame gaps on all connections
+    pub frame_gaps_total: u64,
+    /// Times the connection was reopened
+    pub reconnects: u64,
+    pub confidence: LatencyConfidence,
 }
 
 /// Log message for stream processing
@@ -56,7 +104,7 @@ pub struct TimeInfo {
 pub struct LogMessage {
     /// Timestamp of the log
     pub timestamp: String,
-    /// Stream URL associated with the log
+    /// Stream URL associated with the log (credentials redacted)
     pub stream_url: String,
     /// Log message content
     pub message: String,
@@ -90,11 +138,20 @@ impl Default for HLSSyncConfig {
 
 /// Latency monitor configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LatencyMonitorConfig {
     /// Monitoring interval in milliseconds
     pub monitor_interval_ms: u64,
     /// Whether to display PTS information
     pub display_pts: bool,
+    /// Seconds of packets used for drift and the frame interval
+    pub window_secs: u64,
+    /// Packets needed in the window before jitter and drift are trusted
+    pub min_samples: usize,
+    /// A timestamp step longer than this many frame intervals counts as a gap
+    pub gap_factor: f64,
+    /// Console table, Prometheus endpoint and metrics file
+    pub sinks: MetricsSinks,
 }
 
 impl Default for LatencyMonitorConfig {
@@ -102,8 +159,103 @@ impl Default for LatencyMonitorConfig {
         Self {
             monitor_interval_ms: 5000,
             display_pts: true,
+            window_secs: 60,
+            min_samples: 100,
+            gap_factor: 2.0,
+            sinks: MetricsSinks::default(),
+        }
+    }
+}
+
+impl LatencyMonitorConfig {
+    pub fn window(&self) -> Duration {
+        Duration::from_secs(self.window_secs.max(1))
+    }
+}
+
+/// Container of recorded files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingContainer {
+    #[default]
+    Mp4,
+    Mkv,
+    Ts,
+}
+
+impl RecordingContainer {
+    /// File extension of recorded segments
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordingContainer::Mp4 => "mp4",
+            RecordingContainer::Mkv => "mkv",
+            RecordingContainer::Ts => "ts",
         }
     }
+
+    /// FFmpeg muxer name for `-segment_format`
+    pub fn segment_format(&self) -> &'static str {
+        match self {
+            RecordingContainer::Mp4 => "mp4",
+            RecordingContainer::Mkv => "matroska",
+            RecordingContainer::Ts => "mpegts",
+        }
+    }
+}
+
+/// Recording mode settings (the file length is `saved_time_duration`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    pub container: RecordingContainer,
+    /// FFmpeg audio encoder used when `audio` is on, or `copy`
+    pub audio_codec: String,
+    /// Audio bitrate for re-encoded audio
+    pub audio_bitrate: String,
+    /// File name without extension: `{camera}` plus strftime fields
+    pub file_name: String,
+    /// Start recording, and split files, on multiples of the file length
+    /// (the same boundaries as synchronized HLS), so cameras line up
+    /// file-for-file
+    pub align_to_wall_clock: bool,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            container: RecordingContainer::Mp4,
+            audio_codec: "aac".to_string(),
+            audio_bitrate: "64k".to_string(),
+            file_name: "recording_%Y%m%d_%H%M%S".to_string(),
+            align_to_wall_clock: true,
+        }
+    }
+}
+
+impl RecordingConfig {
+    /// Output path for ffmpeg's `-strftime` naming of `camera`'s files in `directory`
+    pub fn output_pattern(&self, directory: &str, camera: &str) -> Result<String, RtspSyncError> {
+        let name = self.file_name.replace("{camera}", camera);
+        if !name.contains('%') {
+            return Err(RtspSyncError::ConfigError(format!(
+                "Recording file name '{}' needs a time field such as %H%M%S, \
+                 or every file overwrites the previous one",
+                self.file_name
+            )));
+        }
+        if name.contains(['/', '\\']) {
+            return Err(RtspSyncError::ConfigError(format!(
+                "Recording file name '{}' must not contain directories",
+                self.file_name
+            )));
+        }
+        Ok(format!(
+            "{}/{}.{}",
+            directory,
+            name,
+            self.container.extension()
+        ))
+    }
 }
 
 /// Main configuration for RTSP sync
@@ -131,6 +283,30 @@ pub struct RtspSyncConfig {
     /// Latency monitor configuration
     #[serde(default)]
     pub latency_monitor: LatencyMonitorConfig,
+    /// Camera names keyed by URL (as listed in `rtsp_url_list`), used for directory names
+    #[serde(default)]
+    pub camera_aliases: HashMap<String, String>,
+    /// Credentials for URLs that don't embed them
+    #[serde(default)]
+    pub credentials: CredentialSource,
+    /// Age, size and free-disk limits for recordings (recording mode)
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Rotating per-camera log of ffmpeg errors and progress statistics
+    #[serde(default)]
+    pub ffmpeg_log: FfmpegLogConfig,
+    /// Container, audio codec, file naming and alignment (recording mode)
+    #[serde(default)]
+    pub recording: RecordingConfig,
+    /// Restart backoff for recorders (recording mode)
+    #[serde(default)]
+    pub supervisor: SupervisorConfig,
+    /// Offset tolerance and report file (verify mode)
+    #[serde(default)]
+    pub verify: VerifyConfig,
+    /// Layout, tile size, encoding and output (mosaic mode)
+    #[serde(default)]
+    pub mosaic: MosaicConfig,
 }
 
 impl Default for RtspSyncConfig {
@@ -149,6 +325,14 @@ impl Default for RtspSyncConfig {
             fps: 30.0,
             hls: HLSSyncConfig::default(),
             latency_monitor: LatencyMonitorConfig::default(),
+            camera_aliases: HashMap::new(),
+            credentials: CredentialSource::default(),
+            retention: RetentionConfig::default(),
+            ffmpeg_log: FfmpegLogConfig::default(),
+            recording: RecordingConfig::default(),
+            supervisor: SupervisorConfig::default(),
+            verify: VerifyConfig::default(),
+            mosaic: MosaicConfig::default(),
         }
     }
 }
@@ -168,6 +352,17 @@ impl RtspSyncConfig {
         std::fs::write(path, json)
     }
 
+    /// Parse `rtsp_url_list`, applying camera aliases and credentials
+    pub fn camera_urls(&self) -> Result<Vec<RtspUrl>, RtspSyncError> {
+        self.rtsp_url_list
+            .iter()
+            .map(|url| {
+                RtspUrl::resolve(url, &self.camera_aliases, &self.credentials)
+                    .map_err(|e| RtspSyncError::ConfigError(format!("Invalid credentials: {}", e)))
+            })
+            .collect()
+    }
+
     /// Create required directories
     pub fn create_directories(&self) -> std::io::Result<()> {
         std::fs::create_dir_all(&self.output_directory)?;
@@ -197,6 +392,8 @@ pub enum RtspSyncError {
     OpenCVError(String),
     /// Configuration error
     ConfigError(String),
+    /// Verify mode found streams out of sync, or couldn't compare them
+    OutOfSync(String),
 }
 
 impl std::fmt::Display for RtspSyncError {
@@ -206,6 +403,7 @@ impl std::fmt::Display for RtspSyncError {
             RtspSyncError::FFmpegError(msg) => write!(f, "FFmpeg Error: {}", msg),
             RtspSyncError::OpenCVError(msg) => write!(f, "OpenCV Error: {}", msg),
             RtspSyncError::ConfigError(msg) => write!(f, "Config Error: {}", msg),
+            RtspSyncError::OutOfSync(msg) => write!(f, "Out of Sync: {}", msg),
         }
     }
 }
@@ -0,0 +1,446 @@
+//! Synchronization check for multi-camera HLS output
+//!
+//! Reads every `<stream>/<stream>_playlist.m3u8` below the HLS root and
+//! takes each segment's start time from `EXT-X-PROGRAM-DATE-TIME`; a segment
+//! without the tag starts where the previous one ended, unless a
+//! discontinuity lies between them. Each segment of the reference stream
+//! (the first one by name) is paired with the segment of every other stream
+//! that starts closest to it, and pairs further apart than the tolerance are
+//! flagged.
+
+use crate::rtsp_sync::types::RtspSyncError;
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use prettytable::{Table, format, row};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Suffix of the playlists written by the HLS streamers
+pub const PLAYLIST_SUFFIX: &str = "_playlist.m3u8";
+
+/// Verify mode settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VerifyConfig {
+    /// Largest accepted start offset between matching segments, in milliseconds
+    pub tolerance_ms: u64,
+    /// Also write the report as JSON to this file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report_path: Option<String>,
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        Self {
+            tolerance_ms: 500,
+            report_path: None,
+        }
+    }
+}
+
+/// One segment of a playlist
+#[derive(Debug, Clone, Serialize)]
+pub struct SegmentTiming {
+    pub uri: String,
+    /// Media sequence number
+    pub sequence: u64,
+    /// Wall-clock start, `None` before the first `EXT-X-PROGRAM-DATE-TIME`
+    pub start: Option<DateTime<Utc>>,
+    /// Duration in seconds (`EXTINF`)
+    pub duration: f64,
+}
+
+/// Segments of one stream's playlist
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamTimeline {
+    pub stream: String,
+    pub segments: Vec<SegmentTiming>,
+}
+
+impl StreamTimeline {
+    /// Parse a media playlist's text
+    pub fn parse(stream: &str, text: &str) -> Result<Self, RtspSyncError> {
+        let playlist = m3u8_rs::parse_media_playlist_res(text.as_bytes()).map_err(|e| {
+            RtspSyncError::IoError(format!("Invalid playlist for {}: {:?}", stream, e))
+        })?;
+
+        // Read from the text: older ffmpeg writes the offset as `+0000`,
+        // which RFC 3339 parsing rejects
+        let mut program_date_times = Vec::new();
+        let mut pending = None;
+        for line in text.lines().map(str::trim) {
+            if let Some(value) = line.strip_prefix("#EXT-X-PROGRAM-DATE-TIME:") {
+                pending = parse_program_date_time(value);
+            } else if !line.is_empty() && !line.starts_with('#') {
+                program_date_times.push(pending.take());
+            }
+        }
+        if program_date_times.len() != playlist.segments.len() {
+            program_date_times.clear();
+        }
+
+        let mut segments: Vec<SegmentTiming> = Vec::with_capacity(playlist.segments.len());
+        for (index, segment) in playlist.segments.iter().enumerate() {
+            let continued =
+                match segments.last() {
+                    Some(previous) if !segment.discontinuity => previous.start.map(|start| {
+                        start
+                            + chrono::Duration::milliseconds(
+                                (previous.duration * 1000.0).round() as i64
+                            )
+                    }),
+                    _ => None,
+                };
+            segments.push(SegmentTiming {
+                uri: segment.uri.clone(),
+                sequence: playlist.media_sequence + index as u64,
+                start: program_date_times
+                    .get(index)
+                    .copied()
+                    .flatten()
+                    .or(continued),
+                duration: f64::from(segment.duration),
+            });
+        }
+
+        Ok(Self {
+            stream: stream.to_string(),
+            segments,
+        })
+    }
+
+    /// Parse the playlist file at `path`
+    pub fn from_file(stream: &str, path: &Path) -> Result<Self, RtspSyncError> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(stream, &text)
+    }
+
+    /// Whether any segment has a wall-clock start
+    pub fn has_program_date_time(&self) -> bool {
+        self.segments.iter().any(|segment| segment.start.is_some())
+    }
+
+    /// First start and last end of the segments with a wall-clock start
+    fn span(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let mut timed = self
+            .segments
+            .iter()
+            .filter_map(|segment| segment.start.map(|start| (start, segment.duration)));
+        let first = timed.next()?;
+        let (last, duration) = timed.next_back().unwrap_or(first);
+        Some((
+            first.0,
+            last + chrono::Duration::milliseconds((duration * 1000.0).round() as i64),
+        ))
+    }
+}
+
+/// Parse an `EXT-X-PROGRAM-DATE-TIME` value (RFC 3339, or ffmpeg's `+hhmm` offset)
+pub fn parse_program_date_time(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::<FixedOffset>::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f%z"))
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// `(stream, playlist)` for every `<root>/<stream>/<stream>_playlist.m3u8`, by name
+pub fn find_playlists(root: &Path) -> std::io::Result<Vec<(String, PathBuf)>> {
+    let mut playlists = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        let Some(stream) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let playlist = path.join(format!("{}{}", stream, PLAYLIST_SUFFIX));
+        if playlist.is_file() {
+            playlists.push((stream.to_string(), playlist));
+        }
+    }
+    playlists.sort();
+    Ok(playlists)
+}
+
+/// Start offset of one segment against the matching reference segment
+#[derive(Debug, Clone, Serialize)]
+pub struct SegmentOffset {
+    pub reference_sequence: u64,
+    pub sequence: u64,
+    pub reference_start: DateTime<Utc>,
+    /// Positive when this stream's segment starts later
+    pub offset_ms: i64,
+    pub within_tolerance: bool,
+}
+
+/// Offsets of one stream against the reference stream
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamOffsets {
+    pub stream: String,
+    pub segments: usize,
+    pub mean_offset_ms: Option<f64>,
+    /// Largest offset by magnitude
+    pub max_offset_ms: Option<i64>,
+    /// Change of the offset over time, in milliseconds per minute
+    pub drift_ms_per_min: Option<f64>,
+    pub out_of_tolerance: usize,
+    pub offsets: Vec<SegmentOffset>,
+}
+
+impl StreamOffsets {
+    fn new(stream: &str, segments: usize, offsets: Vec<SegmentOffset>) -> Self {
+        let count = offsets.len() as f64;
+        let mean_offset_ms = (!offsets.is_empty())
+            .then(|| offsets.iter().map(|o| o.offset_ms as f64).sum::<f64>() / count);
+        let max_offset_ms = offsets
+            .iter()
+            .map(|o| o.offset_ms)
+            .max_by_key(|offset| offset.abs());
+
+        // Least-squares slope of the offset over the reference start
+        let drift_ms_per_min = offsets.first().and_then(|first| {
+            let points: Vec<(f64, f64)> = offsets
+                .iter()
+                .map(|o| {
+                    let minutes = (o.reference_start - first.reference_start).num_milliseconds()
+                        as f64
+                        / 60_000.0;
+                    (minutes, o.offset_ms as f64)
+                })
+                .collect();
+            let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+            let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+            let covariance: f64 = points
+                .iter()
+                .map(|(x, y)| (x - mean_x) * (y - mean_y))
+                .sum();
+            let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+            (points.len() >= 2 && variance > 0.0).then(|| covariance / variance)
+        });
+
+        Self {
+            stream: stream.to_string(),
+            segments,
+            mean_offset_ms,
+            max_offset_ms,
+            drift_ms_per_min,
+            out_of_tolerance: offsets.iter().filter(|o| !o.within_tolerance).count(),
+            offsets,
+        }
+    }
+}
+
+/// Result of comparing the playlists
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncReport {
+    pub generated_at: DateTime<Local>,
+    pub tolerance_ms: u64,
+    /// Stream the others are compared with
+    pub reference: Option<String>,
+    pub streams: Vec<StreamOffsets>,
+    /// Streams that could not be compared, and why
+    pub problems: Vec<String>,
+    /// At least two streams were compared and every offset is within tolerance
+    pub in_sync: bool,
+}
+
+impl SyncReport {
+    /// Compare `timelines` against the first one with program date times
+    pub fn compare(timelines: &[StreamTimeline], tolerance_ms: u64) -> Self {
+        let mut problems = Vec::new();
+        let mut timed = Vec::new();
+        for timeline in timelines {
+            if timeline.has_program_date_time() {
+                timed.push(timeline);
+            } else {
+                problems.push(format!(
+                    "{}: no EXT-X-PROGRAM-DATE-TIME in the playlist (needs hls_flags program_date_time)",
+                    timeline.stream
+                ));
+            }
+        }
+
+        let mut streams = Vec::new();
+        let reference = timed.first().copied();
+        if let Some(reference) = reference {
+            for timeline in &timed[1..] {
+                let offsets = Self::offsets(reference, timeline, tolerance_ms);
+                if offsets.is_empty() {
+                    problems.push(format!(
+                        "{}: no segments overlap with {}",
+                        timeline.stream, reference.stream
+                    ));
+                }
+                streams.push(StreamOffsets::new(
+                    &timeline.stream,
+                    timeline.segments.len(),
+                    offsets,
+                ));
+            }
+        }
+        if streams.is_empty() {
+            problems.push("Fewer than two streams with program date times to compare".to_string());
+        }
+
+        let in_sync = problems.is_empty() && streams.iter().all(|s| s.out_of_tolerance == 0);
+        Self {
+            generated_at: Local::now(),
+            tolerance_ms,
+            reference: reference.map(|timeline| timeline.stream.clone()),
+            streams,
+            problems,
+            in_sync,
+        }
+    }
+
+    /// Pair every reference segment inside `other`'s span with its closest segment
+    fn offsets(
+        reference: &StreamTimeline,
+        other: &StreamTimeline,
+        tolerance_ms: u64,
+    ) -> Vec<SegmentOffset> {
+        let Some((first, end)) = other.span() else {
+            return Vec::new();
+        };
+        let tolerance = chrono::Duration::milliseconds(tolerance_ms as i64);
+        let mut offsets = Vec::new();
+        for segment in &reference.segments {
+            let Some(reference_start) = segment.start else {
+                continue;
+            };
+            if reference_start < first - tolerance || reference_start >= end {
+                continue;
+            }
+            let closest = other
+                .segments
+                .iter()
+                .filter_map(|candidate| {
+                    candidate.start.map(|start| {
+                        (
+                            candidate.sequence,
+                            (start - reference_start).num_milliseconds(),
+                        )
+                    })
+                })
+                .min_by_key(|(_, offset)| offset.abs());
+            if let Some((sequence, offset_ms)) = closest {
+                offsets.push(SegmentOffset {
+                    reference_sequence: segment.sequence,
+                    sequence,
+                    reference_start,
+                    offset_ms,
+                    within_tolerance: offset_ms.unsigned_abs() <= tolerance_ms,
+                });
+            }
+        }
+        offsets
+    }
+
+    /// Report as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, RtspSyncError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| RtspSyncError::IoError(format!("Failed to serialize report: {}", e)))
+    }
+
+    /// Write the JSON report to `path`
+    pub fn write_json(&self, path: &str) -> Result<(), RtspSyncError> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Print a summary table and the problems found
+    pub fn print(&self) {
+        println!("🔍 HLS synchronization report");
+        if let Some(reference) = &self.reference {
+            println!("   Reference stream: {}", reference);
+        }
+        println!("   Tolerance: {}ms", self.tolerance_ms);
+
+        if !self.streams.is_empty() {
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_BOX_CHARS);
+            table.set_titles(row![
+                b->"Stream",
+                b->"Segments",
+                b->"Compared",
+                b->"Mean offset (ms)",
+                b->"Max offset (ms)",
+                b->"Drift (ms/min)",
+                b->"Status"
+            ]);
+            for stream in &self.streams {
+                let mean = match stream.mean_offset_ms {
+                    Some(mean) => format!("{:+.1}", mean),
+                    None => "-".to_string(),
+                };
+                let max = match stream.max_offset_ms {
+                    Some(max) => format!("{:+}", max),
+                    None => "-".to_string(),
+                };
+                let drift = match stream.drift_ms_per_min {
+                    Some(drift) => format!("{:+.2}", drift),
+                    None => "-".to_string(),
+                };
+                let status = if stream.offsets.is_empty() {
+                    "no overlap".to_string()
+                } else if stream.out_of_tolerance == 0 {
+                    "in sync".to_string()
+                } else {
+                    format!("{} segment(s) off", stream.out_of_tolerance)
+                };
+                table.add_row(row![
+                    stream.stream,
+                    stream.segments,
+                    stream.offsets.len(),
+                    mean,
+                    max,
+                    drift,
+                    status
+                ]);
+            }
+            table.printstd();
+        }
+
+        for problem in &self.problems {
+            eprintln!("⚠️  {}", problem);
+        }
+        if self.in_sync {
+            println!("✅ All streams within {}ms", self.tolerance_ms);
+        } else {
+            println!("❌ Streams are not in sync");
+        }
+    }
+}
+
+/// Compare the playlists of every stream below `root_directory`
+///
+/// Streams named in `exclude` (such as the mosaic, which isn't a camera) are
+/// left out. A playlist that can't be read or parsed is listed under
+/// `problems` rather than failing the whole check.
+pub fn verify_hls_sync(
+    root_directory: &str,
+    tolerance_ms: u64,
+    exclude: &[&str],
+) -> Result<SyncReport, RtspSyncError> {
+    let playlists = find_playlists(Path::new(root_directory))
+        .map_err(|e| RtspSyncError::IoError(format!("Failed to read {}: {}", root_directory, e)))?;
+
+    let mut timelines = Vec::new();
+    let mut problems = Vec::new();
+    for (stream, path) in playlists {
+        if exclude.contains(&stream.as_str()) {
+            continue;
+        }
+        match StreamTimeline::from_file(&stream, &path) {
+            Ok(timeline) => timelines.push(timeline),
+            Err(e) => problems.push(format!("{}: {}", stream, e)),
+        }
+    }
+
+    let mut report = SyncReport::compare(&timelines, tolerance_ms);
+    if !problems.is_empty() {
+        report.in_sync = false;
+        problems.append(&mut report.problems);
+        report.problems = problems;
+    }
+    Ok(report)
+}
@@ -1,9 +1,23 @@
+use media_core::ShutdownToken;
+use media_core::process::handlers::FileHandler;
+use media_core::process::manifest::MANIFEST_FILE_NAME;
+use media_core::process::timeout::run_with_deadline;
+use media_core::process::watch::FolderWatcher;
+use media_core::process::workers::resolution::{FrameAdapter, FramePlan};
+use media_core::process::workers::scanner::VideoScanner;
 use media_core::process::{
-    ProcessConfig, VideoExtractionConfig, create_video_processor, generate_default_config,
+    ConversionOptions, Deadline, FileFormat, ImageFormat, JobManifest, JobStatus, MediaKind,
+    ProcessConfig, ProcessError, ProcessingMode, ProcessingOptions, Processor, ResizeOptions,
+    ResolutionPolicy, SniffedFormat, StreamOutput, StreamProcessOptions, VideoEncodingConfig,
+    VideoExtractionConfig, VideoFormat, WatchOptions, create_processor_with_mode,
+    create_processor_with_options, create_video_processor, generate_default_config,
 };
+use opencv::core::{Rect, Size};
 use std::fs;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
 use tempfile::tempdir;
 
 /// Test programmatic config generation
@@ -73,6 +87,9 @@ fn test_video_extraction() {
         video_creation_mode: Some("skip".to_string()), // Test the new mode
         processing_mode: Some("sequential".to_string()),
         hardware_acceleration: Default::default(),
+        encoding: Default::default(),
+        resolution_policy: Default::default(),
+        scan: Default::default(),
     };
 
     let config_json = serde_json::to_string(&video_config).unwrap();
@@ -137,6 +154,9 @@ fn test_video_creation() {
         video_creation_mode: Some("direct".to_string()), // Test creation mode
         processing_mode: Some("sequential".to_string()),
         hardware_acceleration: Default::default(),
+        encoding: Default::default(),
+        resolution_policy: Default::default(),
+        scan: Default::default(),
     };
 
     let config_json = serde_json::to_string(&video_config).unwrap();
@@ -164,3 +184,856 @@ fn test_video_creation() {
 
     println!("✅ Created video file at {:?}", expected_video);
 }
+
+/// Test conversion output naming and ffmpeg argument building
+#[test]
+fn test_conversion_args() {
+    println!("=== Test: FileHandler Conversion Args ===");
+
+    let options = ProcessingOptions::default();
+    let conversion = ConversionOptions {
+        target_video_format: Some(VideoFormat::Mp4),
+        video_bitrate: Some("2M".to_string()),
+        resize: Some(ResizeOptions {
+            width: Some(1280),
+            height: None,
+        }),
+        ..Default::default()
+    };
+
+    // Video: extension follows the target format, and ffmpeg re-encodes
+    let video_format = FileFormat::Video(VideoFormat::Mkv);
+    let output =
+        FileHandler::resolve_output_path(Path::new("out/clip.mkv"), &video_format, &conversion);
+    assert_eq!(output, PathBuf::from("out/clip.mp4"));
+
+    let args = FileHandler::build_conversion_args(
+        Path::new("in/clip.mkv"),
+        &output,
+        &video_format,
+        &options,
+        &conversion,
+    )
+    .expect("Video conversion should produce ffmpeg args");
+    assert!(args.windows(2).any(|w| w == ["-c:v", "libx264"]));
+    assert!(args.windows(2).any(|w| w == ["-b:v", "2M"]));
+    assert!(args.windows(2).any(|w| w == ["-vf", "scale=1280:-2"]));
+    assert!(
+        args.contains(&"-n".to_string()),
+        "overwrite_existing is off"
+    );
+    assert_eq!(args.last().unwrap(), "out/clip.mp4");
+
+    // Image: no image target configured, but resize still applies
+    let image_format = FileFormat::Image(ImageFormat::Png);
+    let image_output =
+        FileHandler::resolve_output_path(Path::new("out/photo.png"), &image_format, &conversion);
+    assert_eq!(image_output, PathBuf::from("out/photo.png"));
+    assert!(
+        FileHandler::build_conversion_args(
+            Path::new("in/photo.png"),
+            &image_output,
+            &image_format,
+            &options,
+            &conversion,
+        )
+        .is_some()
+    );
+
+    // Nothing configured: files are copied through unchanged
+    assert!(
+        FileHandler::build_conversion_args(
+            Path::new("in/clip.mp4"),
+            Path::new("out/clip.mp4"),
+            &FileFormat::Video(VideoFormat::Mp4),
+            &options,
+            &ConversionOptions::default(),
+        )
+        .is_none()
+    );
+
+    println!("✅ Conversion args pass");
+}
+
+/// Test bounded stream processing against a local file URL
+#[test]
+fn test_stream_process() {
+    println!("=== Test: Stream Processing (file URL) ===");
+
+    let input_video = Path::new("data/test.mp4");
+    if !input_video.exists() {
+        println!("⚠️ Skipping test: data/test.mp4 not found");
+        return;
+    }
+    let input_url = format!(
+        "file://{}",
+        fs::canonicalize(input_video).unwrap().to_str().unwrap()
+    );
+
+    // Frames: stop after a fixed number of frames
+    let output_root = tempdir().expect("Failed to create temp output dir");
+    let output_path_str = output_root.path().to_str().unwrap();
+    let config = ProcessConfig {
+        input_path: input_url.clone(),
+        output_path: output_path_str.to_string(),
+        processing_mode: ProcessingMode::StreamProcess,
+        video_config: None,
+        stream: StreamProcessOptions {
+            output: StreamOutput::Frames,
+            max_duration_seconds: None,
+            max_frames: Some(5),
+            frame_interval: 10,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let mut processor = Processor::new(config.clone()).expect("Failed to create processor");
+    processor
+        .process_from_source(&input_url, output_path_str)
+        .expect("Frame capture failed");
+
+    let stats = processor.get_stats();
+    assert_eq!(stats.frames_processed, 5);
+    assert!(stats.total_size_processed > 0, "No frame bytes recorded");
+
+    // A second capture into the same directory numbers on instead of overwriting
+    let mut processor = Processor::new(config).expect("Failed to create processor");
+    processor
+        .process_from_source(&input_url, output_path_str)
+        .expect("Second frame capture failed");
+    let frames = fs::read_dir(output_root.path())
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .flat_map(|camera| fs::read_dir(camera.path()).unwrap())
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("frame_"))
+        .count();
+    assert_eq!(frames, 10);
+
+    // Segments: stop after a short duration
+    let output_root = tempdir().expect("Failed to create temp output dir");
+    let output_path_str = output_root.path().to_str().unwrap();
+    let config = ProcessConfig {
+        input_path: input_url.clone(),
+        output_path: output_path_str.to_string(),
+        processing_mode: ProcessingMode::StreamProcess,
+        video_config: None,
+        stream: StreamProcessOptions {
+            output: StreamOutput::Segments,
+            max_duration_seconds: Some(2),
+            segment_duration_seconds: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let mut processor = Processor::new(config).expect("Failed to create processor");
+    processor
+        .process_from_source(&input_url, output_path_str)
+        .expect("Segment recording failed");
+
+    let stats = processor.get_stats();
+    assert_eq!(stats.files_processed, 1);
+    assert!(stats.frames_processed > 0, "No frames counted in segments");
+    assert!(stats.total_size_processed > 0, "No segment bytes recorded");
+
+    println!(
+        "✅ Stream processing pass ({} frames, {} bytes in segments)",
+        stats.frames_processed, stats.total_size_processed
+    );
+}
+
+/// Test that directory jobs resume from the job manifest
+#[test]
+fn test_job_manifest_resume() {
+    println!("=== Test: Job Manifest Resume ===");
+
+    let input_dir = tempdir().expect("Failed to create temp input dir");
+    let output_dir = tempdir().expect("Failed to create temp output dir");
+    fs::write(input_dir.path().join("a.txt"), "alpha").unwrap();
+    fs::write(input_dir.path().join("b.txt"), "beta").unwrap();
+    fs::write(input_dir.path().join("broken.xyz"), "unsupported").unwrap();
+
+    let input_str = input_dir.path().to_str().unwrap().to_string();
+    let output_str = output_dir.path().to_str().unwrap().to_string();
+    let run = || {
+        let mut processor = create_processor_with_mode(
+            input_str.clone(),
+            output_str.clone(),
+            ProcessingMode::DirectoryProcess,
+        )
+        .expect("Failed to create processor");
+        processor
+            .process_from_source(&input_str, &output_str)
+            .expect("Directory processing failed");
+        processor.get_stats().clone()
+    };
+
+    // First run: two documents copied, one unsupported file failed
+    let stats = run();
+    assert_eq!(stats.files_processed, 2);
+    assert_eq!(stats.files_failed, 1);
+
+    let manifest_path = output_dir.path().join(MANIFEST_FILE_NAME);
+    assert!(manifest_path.exists(), "Job manifest was not written");
+    let manifest = JobManifest::load_or_create(output_dir.path()).unwrap();
+    let entry = manifest
+        .entry(&input_dir.path().join("a.txt"))
+        .expect("a.txt missing from manifest");
+    assert_eq!(entry.status, JobStatus::Completed);
+    assert_eq!(entry.size, 5);
+    assert!(entry.hash.is_some());
+
+    // Second run: completed inputs are skipped, stats still cover the whole job
+    fs::remove_file(output_dir.path().join("a.txt")).unwrap();
+    let stats = run();
+    assert!(
+        !output_dir.path().join("a.txt").exists(),
+        "Completed input was processed again"
+    );
+    assert_eq!(stats.files_processed, 2);
+    assert_eq!(stats.files_failed, 1);
+
+    // Changed content is picked up again
+    fs::write(input_dir.path().join("a.txt"), "alpha, edited").unwrap();
+    run();
+    assert!(output_dir.path().join("a.txt").exists());
+
+    let manifest = JobManifest::load_or_create(output_dir.path()).unwrap();
+    let broken = manifest
+        .entry(&input_dir.path().join("broken.xyz"))
+        .unwrap();
+    assert_eq!(broken.status, JobStatus::Failed);
+    assert_eq!(
+        broken.attempts, 3,
+        "Failed inputs should be retried every run"
+    );
+
+    // A record moves to the file it was made from, keeping earlier attempts
+    let mut manifest = manifest;
+    let original = input_dir.path().join("original.xyz");
+    let moved = manifest
+        .move_entry(&input_dir.path().join("broken.xyz"), &original)
+        .expect("broken.xyz record not moved");
+    assert_eq!(moved.attempts, 3);
+    assert!(
+        manifest
+            .entry(&input_dir.path().join("broken.xyz"))
+            .is_none()
+    );
+    assert_eq!(manifest.entry(&original).unwrap().status, JobStatus::Failed);
+
+    println!("✅ Job manifest resume pass");
+}
+
+/// Test that time budgets kill child processes and stop jobs
+#[test]
+fn test_timeout_budget() {
+    println!("=== Test: Timeout Budget ===");
+
+    // A child that outlives its deadline is killed
+    let started = Instant::now();
+    let result = run_with_deadline(
+        Command::new("sleep").arg("10"),
+        &Deadline::after(Duration::from_millis(200)),
+        "sleep",
+    );
+    assert!(matches!(result, Err(ProcessError::Timeout(_))));
+    assert!(
+        started.elapsed() < Duration::from_secs(5),
+        "Child was not killed at the deadline"
+    );
+
+    // A child that finishes in time returns its output
+    let output = run_with_deadline(
+        Command::new("echo").arg("done"),
+        &Deadline::after(Duration::from_secs(10)),
+        "echo",
+    )
+    .expect("echo should finish in time");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "done");
+
+    // An exhausted job budget stops processing with a timeout error
+    let input_dir = tempdir().expect("Failed to create temp input dir");
+    let output_dir = tempdir().expect("Failed to create temp output dir");
+    let input_file = input_dir.path().join("a.txt");
+    fs::write(&input_file, "alpha").unwrap();
+
+    let input_str = input_file.to_str().unwrap().to_string();
+    let output_str = output_dir
+        .path()
+        .join("a.txt")
+        .to_str()
+        .unwrap()
+        .to_string();
+    let options = ProcessingOptions {
+        job_timeout_seconds: Some(0),
+        ..Default::default()
+    };
+    let mut processor =
+        create_processor_with_options(input_str.clone(), output_str.clone(), options)
+            .expect("Failed to create processor");
+    let result = processor.process_from_source(&input_str, &output_str);
+    assert!(matches!(result, Err(ProcessError::Timeout(_))));
+    assert!(!output_dir.path().join("a.txt").exists());
+
+    // A stream source that accepts but never answers is killed at the per-file budget
+    if Command::new("ffmpeg").arg("-version").output().is_err() {
+        println!("⚠️ Skipping stalled stream check: ffmpeg not found");
+    } else {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let stall_url = format!("rtsp://{}/stall", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let held: Vec<_> = listener.incoming().take(4).collect();
+            std::thread::sleep(Duration::from_secs(30));
+            drop(held);
+        });
+        let config = ProcessConfig {
+            input_path: stall_url.clone(),
+            output_path: output_dir.path().to_str().unwrap().to_string(),
+            processing_mode: ProcessingMode::StreamProcess,
+            video_config: None,
+            processing_options: ProcessingOptions {
+                timeout_seconds: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut processor = Processor::new(config).expect("Failed to create processor");
+        let started = Instant::now();
+        let result = processor.process_from_source(&stall_url, output_dir.path().to_str().unwrap());
+        assert!(
+            matches!(result, Err(ProcessError::Timeout(_))),
+            "Expected a timeout, got {:?}",
+            result
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    println!("✅ Timeout budget pass");
+}
+
+/// Test that parallel extraction can run repeatedly and records directory failures
+#[test]
+fn test_repeated_parallel_extraction() {
+    println!("=== Test: Repeated Parallel Extraction ===");
+
+    // A file that looks like a video but cannot be decoded
+    let input_dir = tempdir().expect("Failed to create temp input dir");
+    fs::write(input_dir.path().join("broken.mp4"), b"not a video").unwrap();
+
+    let output_root = tempdir().expect("Failed to create temp output dir");
+    let config_dir = tempdir().expect("Failed to create temp config dir");
+    let config_file_path = config_dir.path().join("parallel_config.json");
+
+    let video_config = VideoExtractionConfig {
+        input_directories: vec![input_dir.path().to_str().unwrap().to_string()],
+        output_directory: output_root.path().to_str().unwrap().to_string(),
+        output_prefix: "parallel".to_string(),
+        num_threads: Some(2),
+        output_fps: 30,
+        frame_interval: 10,
+        extraction_mode: "ffmpeg".to_string(),
+        create_summary_per_thread: Some(false),
+        video_creation_mode: Some("skip".to_string()),
+        processing_mode: Some("parallel".to_string()),
+        hardware_acceleration: Default::default(),
+        encoding: Default::default(),
+        resolution_policy: Default::default(),
+        scan: Default::default(),
+    };
+    fs::write(
+        &config_file_path,
+        serde_json::to_string(&video_config).unwrap(),
+    )
+    .expect("Failed to write config file");
+
+    // Each run builds its own pool, so a second run in the same process works
+    for run in 1..=2 {
+        let mut processor = create_video_processor().expect("Failed to create processor");
+        processor
+            .run_video_extraction(config_file_path.to_str().unwrap())
+            .unwrap_or_else(|e| panic!("Run {} failed: {}", run, e));
+
+        let stats = processor.get_stats();
+        assert_eq!(stats.files_failed, 1, "Parallel failure not recorded");
+        assert_eq!(stats.files_processed, 0);
+    }
+
+    println!("✅ Repeated parallel extraction pass");
+}
+
+/// Test video creation by piping frames into ffmpeg (Stream mode)
+#[test]
+fn test_video_streaming() {
+    println!("=== Test: Video Creation (Stream Mode) ===");
+
+    let input_video = Path::new("data/test.mp4");
+    if !input_video.exists() {
+        println!("⚠️ Skipping test: data/test.mp4 not found");
+        return;
+    }
+
+    let output_root = tempdir().expect("Failed to create temp output dir");
+    let config_dir = tempdir().expect("Failed to create temp config dir");
+    let config_file_path = config_dir.path().join("test_stream_config.json");
+
+    let video_config = VideoExtractionConfig {
+        input_directories: vec![input_video.to_str().unwrap().to_string()],
+        output_directory: output_root.path().to_str().unwrap().to_string(),
+        output_prefix: "test_stream".to_string(),
+        num_threads: Some(1),
+        output_fps: 30,
+        frame_interval: 100,
+        extraction_mode: "opencv".to_string(),
+        create_summary_per_thread: Some(false),
+        video_creation_mode: Some("stream".to_string()),
+        processing_mode: Some("sequential".to_string()),
+        hardware_acceleration: Default::default(),
+        encoding: VideoEncodingConfig {
+            crf: 28,
+            preset: Some("veryfast".to_string()),
+            ..Default::default()
+        },
+        resolution_policy: Default::default(),
+        scan: Default::default(),
+    };
+    fs::write(
+        &config_file_path,
+        serde_json::to_string(&video_config).unwrap(),
+    )
+    .expect("Failed to write config file");
+
+    let mut processor = create_video_processor().expect("Failed to create processor");
+    processor
+        .run_video_extraction(config_file_path.to_str().unwrap())
+        .expect("Stream video creation failed");
+
+    let expected_video = output_root.path().join("test_stream_data.mp4");
+    assert!(
+        expected_video.exists(),
+        "Output video 'test_stream_data.mp4' was not created"
+    );
+    assert!(
+        expected_video.metadata().unwrap().len() > 0,
+        "Output video is empty"
+    );
+
+    // No temp frame directory is left behind (or created)
+    let leftovers: Vec<_> = fs::read_dir(output_root.path())
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .collect();
+    assert!(leftovers.is_empty(), "Stream mode created directories");
+
+    println!("✅ Streamed video file at {:?}", expected_video);
+}
+
+/// Test the frame geometry planned for each mixed-resolution policy
+#[test]
+fn test_resolution_plan() {
+    println!("=== Test: Resolution Plan ===");
+
+    let target = Size::new(1920, 1080);
+    let source = Size::new(1280, 960);
+
+    assert_eq!(
+        FrameAdapter::plan(target, target, &ResolutionPolicy::Fail),
+        FramePlan::Unchanged
+    );
+    assert_eq!(
+        FrameAdapter::plan(source, target, &ResolutionPolicy::ScaleToFit),
+        FramePlan::Resize(target)
+    );
+    assert_eq!(
+        FrameAdapter::plan(source, target, &ResolutionPolicy::SkipMismatched),
+        FramePlan::Skip
+    );
+    assert_eq!(
+        FrameAdapter::plan(source, target, &ResolutionPolicy::Fail),
+        FramePlan::Fail
+    );
+
+    // 4:3 into 16:9 gets pillarboxed: 1440x1080 with 240px bars on each side
+    assert_eq!(
+        FrameAdapter::plan(source, target, &ResolutionPolicy::Letterbox),
+        FramePlan::Letterbox {
+            scaled: Size::new(1440, 1080),
+            top: 0,
+            bottom: 0,
+            left: 240,
+            right: 240,
+        }
+    );
+
+    // 4:3 cropped to 16:9: scale to 1920x1440 and keep the middle 1080 rows
+    assert_eq!(
+        FrameAdapter::plan(source, target, &ResolutionPolicy::Crop),
+        FramePlan::Crop {
+            scaled: Size::new(1920, 1440),
+            region: Rect::new(0, 180, 1920, 1080),
+        }
+    );
+
+    // The default keeps the old behavior of dropping mismatched frames
+    assert_eq!(
+        VideoExtractionConfig::default().resolution_policy,
+        ResolutionPolicy::SkipMismatched
+    );
+    let parsed: ResolutionPolicy = serde_json::from_str("\"scale_to_fit\"").unwrap();
+    assert_eq!(parsed, ResolutionPolicy::ScaleToFit);
+
+    println!("✅ Resolution plan test passed");
+}
+
+/// Test content-based format detection with extension fallback
+#[test]
+fn test_file_type_sniffing() {
+    println!("=== Test: File Type Sniffing ===");
+
+    let dir = tempdir().expect("Failed to create temp dir");
+
+    // MP4 with an upper-case extension
+    let mut mp4 = vec![0, 0, 0, 0x18];
+    mp4.extend_from_slice(b"ftypisom\0\0\0\0isomiso2");
+    let mp4_path = dir.path().join("CLIP.MP4");
+    fs::write(&mp4_path, &mp4).unwrap();
+
+    // MPEG-TS mislabeled as .mov
+    let mut ts = vec![0u8; 188 * 3];
+    for packet in ts.chunks_mut(188) {
+        packet[0] = 0x47;
+    }
+    let ts_path = dir.path().join("camera.mov");
+    fs::write(&ts_path, &ts).unwrap();
+
+    // PNG and unrecognized content
+    let png_path = dir.path().join("image.bin");
+    fs::write(&png_path, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+    let unknown_path = dir.path().join("old.MKV");
+    fs::write(&unknown_path, b"not a real header").unwrap();
+
+    assert_eq!(
+        FileHandler::detect_file_format(&mp4_path).unwrap(),
+        FileFormat::Video(VideoFormat::Mp4)
+    );
+    assert_eq!(
+        FileHandler::detect_file_format(&ts_path).unwrap(),
+        FileFormat::Video(VideoFormat::Ts)
+    );
+    assert_eq!(
+        FileHandler::detect_file_format(&png_path).unwrap(),
+        FileFormat::Image(ImageFormat::Png)
+    );
+    // Content not recognized: the extension decides
+    assert_eq!(
+        FileHandler::detect_file_format(&unknown_path).unwrap(),
+        FileFormat::Video(VideoFormat::Mkv)
+    );
+
+    let mut webm = vec![0x1A, 0x45, 0xDF, 0xA3, 0x9F, 0x42, 0x82, 0x84];
+    webm.extend_from_slice(b"webm");
+    assert_eq!(SniffedFormat::from_bytes(&webm), Some(SniffedFormat::Webm));
+    assert_eq!(
+        SniffedFormat::from_bytes(b"RIFF\0\0\0\0WAVEfmt "),
+        Some(SniffedFormat::Wav)
+    );
+    assert_eq!(
+        SniffedFormat::from_bytes(b"ID3\x04\0"),
+        Some(SniffedFormat::Mp3)
+    );
+
+    // ISO base media brands are mapped explicitly; unknown brands are not guessed
+    let ftyp = |brand: &[u8]| {
+        let mut bytes = vec![0, 0, 0, 0x18];
+        bytes.extend_from_slice(b"ftyp");
+        bytes.extend_from_slice(brand);
+        bytes.extend_from_slice(b"\0\0\0\0");
+        bytes
+    };
+    for (brand, expected) in [
+        (&b"isom"[..], Some(SniffedFormat::Mp4)),
+        (b"iso6", Some(SniffedFormat::Mp4)),
+        (b"avc1", Some(SniffedFormat::Mp4)),
+        (b"qt  ", Some(SniffedFormat::Mov)),
+        (b"M4A ", Some(SniffedFormat::M4a)),
+        (b"M4B ", Some(SniffedFormat::M4a)),
+        (b"heic", Some(SniffedFormat::Heif)),
+        (b"mif1", Some(SniffedFormat::Heif)),
+        (b"avif", Some(SniffedFormat::Heif)),
+        (b"zzzz", None),
+    ] {
+        assert_eq!(SniffedFormat::from_bytes(&ftyp(brand)), expected);
+    }
+    assert_eq!(SniffedFormat::M4a.media_kind(), MediaKind::Audio);
+    assert_eq!(SniffedFormat::Heif.media_kind(), MediaKind::Image);
+
+    // Ogg is video only with a Theora stream
+    let mut theora = b"OggS\0\x02".to_vec();
+    theora.extend_from_slice(&[0u8; 22]);
+    theora.extend_from_slice(b"\x80theora\x03\x02\x01");
+    assert_eq!(SniffedFormat::from_bytes(&theora), Some(SniffedFormat::Ogv));
+    let mut vorbis = b"OggS\0\x02".to_vec();
+    vorbis.extend_from_slice(&[0u8; 22]);
+    vorbis.extend_from_slice(b"\x01vorbis\0\0\0\0");
+    assert_eq!(SniffedFormat::from_bytes(&vorbis), Some(SniffedFormat::Ogg));
+    fs::write(dir.path().join("theora.ogg"), &theora).unwrap();
+    fs::write(dir.path().join("song.ogg"), &vorbis).unwrap();
+    // The extension marks Ogg video even when the Theora header isn't in view
+    let ogv_path = dir.path().join("late_theora.ogv");
+    fs::write(&ogv_path, &vorbis).unwrap();
+    assert_eq!(
+        FileHandler::detect_file_format(&ogv_path).unwrap(),
+        FileFormat::Video(VideoFormat::Ogv)
+    );
+
+    // Audio-only MP4 and still images are not videos
+    fs::write(dir.path().join("voice.m4a"), ftyp(b"M4A ")).unwrap();
+    fs::write(dir.path().join("photo.heic"), ftyp(b"heic")).unwrap();
+    fs::write(dir.path().join("photo_heic.bin"), ftyp(b"heic")).unwrap();
+    assert!(!VideoScanner::is_video_file(
+        &dir.path().join("photo_heic.bin")
+    ));
+
+    // The scanner picks up upper-case and mislabeled videos and Theora,
+    // but not the PNG, audio or HEIF files
+    let config = VideoExtractionConfig {
+        input_directories: vec![dir.path().to_str().unwrap().to_string()],
+        ..Default::default()
+    };
+    let scanned = VideoScanner::scan(&config).expect("Scan failed");
+    let mut found: Vec<String> = scanned
+        .values()
+        .flatten()
+        .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    found.sort();
+    assert_eq!(
+        found,
+        vec![
+            "CLIP.MP4",
+            "camera.mov",
+            "late_theora.ogv",
+            "old.MKV",
+            "theora.ogg"
+        ]
+    );
+
+    println!("✅ File type sniffing test passed");
+}
+
+/// Test recursive scanning with glob, size and time filters
+#[test]
+fn test_scanner_filters() {
+    println!("=== Test: Scanner Filters ===");
+
+    let root = tempdir().expect("Failed to create temp dir");
+    let write_video = |relative: &str, size: usize| {
+        let path = root.path().join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut data = vec![0, 0, 0, 0x18];
+        data.extend_from_slice(b"ftypisom\0\0\0\0isomiso2");
+        data.resize(size.max(data.len()), 0);
+        fs::write(&path, data).unwrap();
+    };
+    write_video("a.mp4", 2048);
+    write_video("a_preview.mp4", 2048);
+    write_video("small.mp4", 10);
+    write_video(".Trash/old.mp4", 2048);
+    write_video("sub/b.MOV", 2048);
+    write_video("sub/deep/c.mp4", 2048);
+    fs::write(root.path().join("sub/notes.txt"), "not a video").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(root.path(), root.path().join("sub/loop")).unwrap();
+
+    let root_str = root.path().to_str().unwrap().to_string();
+    let names = |scanned: &std::collections::HashMap<String, Vec<PathBuf>>, key: &str| {
+        let mut names: Vec<String> = scanned
+            .get(key)
+            .map(|files| {
+                files
+                    .iter()
+                    .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    };
+
+    // Default: one level, no filters
+    let config = VideoExtractionConfig {
+        input_directories: vec![root_str.clone()],
+        ..Default::default()
+    };
+    let scanned = VideoScanner::scan(&config).expect("Scan failed");
+    assert_eq!(scanned.len(), 1);
+    assert_eq!(
+        names(&scanned, &root_str),
+        vec!["a.mp4", "a_preview.mp4", "small.mp4"]
+    );
+
+    // Recursive, one subdirectory level, skipping previews, trash and small files
+    let mut config = VideoExtractionConfig {
+        input_directories: vec![root_str.clone()],
+        ..Default::default()
+    };
+    config.scan.recursive = true;
+    config.scan.max_depth = Some(1);
+    config.scan.exclude_globs = vec!["*_preview.mp4".to_string(), ".Trash".to_string()];
+    config.scan.min_size_bytes = Some(1000);
+    let scanned = VideoScanner::scan(&config).expect("Recursive scan failed");
+    let sub_key = root.path().join("sub").to_string_lossy().to_string();
+    assert_eq!(scanned.len(), 2, "Unexpected groups: {:?}", scanned.keys());
+    assert_eq!(names(&scanned, &root_str), vec!["a.mp4"]);
+    assert_eq!(names(&scanned, &sub_key), vec!["b.MOV"]);
+
+    // Unlimited depth reaches the leaf directory; the symlink loop is not followed twice
+    config.scan.max_depth = None;
+    config.scan.include_globs = vec!["sub/**".to_string()];
+    let scanned = VideoScanner::scan(&config).expect("Deep scan failed");
+    let deep_key = root.path().join("sub/deep").to_string_lossy().to_string();
+    assert_eq!(names(&scanned, &sub_key), vec!["b.MOV"]);
+    assert_eq!(names(&scanned, &deep_key), vec!["c.mp4"]);
+    assert_eq!(scanned.values().map(Vec::len).sum::<usize>(), 2);
+
+    // Modification time window
+    config.scan.modified_after = Some("2999-01-01".to_string());
+    assert!(VideoScanner::scan(&config).unwrap().is_empty());
+    config.scan.modified_after = Some("not a date".to_string());
+    assert!(matches!(
+        VideoScanner::scan(&config),
+        Err(ProcessError::ConfigurationError(_))
+    ));
+
+    // Scan options are read from the top level of the config JSON
+    let parsed: VideoExtractionConfig = serde_json::from_str(&format!(
+        r#"{{"input_directories": ["{}"], "output_directory": "out", "output_prefix": "p",
+            "num_threads": 1, "output_fps": 30, "frame_interval": 1, "extraction_mode": "opencv",
+            "create_summary_per_thread": false, "video_creation_mode": "skip",
+            "processing_mode": "sequential", "recursive": true, "exclude_globs": ["*_preview.mp4"]}}"#,
+        root_str
+    ))
+    .expect("Failed to parse config");
+    assert!(parsed.scan.recursive);
+    assert_eq!(parsed.scan.exclude_globs, vec!["*_preview.mp4"]);
+
+    println!("✅ Scanner filters test passed");
+}
+
+/// Test the watch-folder daemon on documents and an unsupported file
+#[test]
+fn test_watch_folder() {
+    println!("=== Test: Watch Folder ===");
+
+    let root = tempdir().expect("Failed to create temp dir");
+    let inbox = root.path().join("inbox");
+    let output = root.path().join("output");
+    fs::create_dir_all(&inbox).unwrap();
+
+    let config = ProcessConfig {
+        input_path: inbox.to_str().unwrap().to_string(),
+        output_path: output.to_str().unwrap().to_string(),
+        video_config: None,
+        watch: WatchOptions {
+            stable_seconds: 0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut watcher = FolderWatcher::new(config).expect("Failed to create watcher");
+
+    // A file still being written is not picked up
+    fs::write(inbox.join("notes.txt"), "part one").unwrap();
+    assert_eq!(watcher.poll_once().unwrap(), 0);
+    fs::write(inbox.join("notes.txt"), "part one, part two").unwrap();
+    assert_eq!(watcher.poll_once().unwrap(), 0);
+    fs::write(inbox.join("unknown.xyz"), "no handler").unwrap();
+    assert_eq!(watcher.poll_once().unwrap(), 1, "notes.txt should be ready");
+    assert_eq!(
+        watcher.poll_once().unwrap(),
+        1,
+        "unknown.xyz should be ready"
+    );
+    assert_eq!(watcher.poll_once().unwrap(), 0);
+
+    // Files sharing a stem are staged separately
+    fs::write(inbox.join("notes.csv"), "a,b").unwrap();
+    assert_eq!(watcher.poll_once().unwrap(), 0);
+    assert_eq!(watcher.poll_once().unwrap(), 1);
+    assert_eq!(fs::read_to_string(output.join("notes.csv")).unwrap(), "a,b");
+    assert!(inbox.join("done/notes.csv").exists());
+    assert_eq!(fs::read_dir(inbox.join(".processing")).unwrap().count(), 0);
+
+    assert!(inbox.join("done/notes.txt").exists());
+    assert!(inbox.join("failed/unknown.xyz").exists());
+    assert!(!inbox.join("notes.txt").exists());
+    assert_eq!(
+        fs::read_to_string(output.join("notes.txt")).unwrap(),
+        "part one, part two"
+    );
+
+    let status: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(watcher.status_path()).unwrap()).unwrap();
+    assert_eq!(status["files_done"], 2);
+    assert_eq!(status["files_failed"], 1);
+    assert_eq!(status["recent"].as_array().unwrap().len(), 3);
+    assert!(status["last_error"].as_str().is_some());
+
+    // `run` stops on shutdown without waiting out the 5s poll interval
+    let shutdown = ShutdownToken::new();
+    let trigger = shutdown.clone();
+    let stopper = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(300));
+        trigger.trigger();
+    });
+    let started = Instant::now();
+    watcher.run(&shutdown).expect("Watch run failed");
+    stopper.join().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    println!("✅ Watch folder test passed");
+}
+
+/// Test that inputs whose output already exists are skipped, not failed
+#[test]
+fn test_skip_existing_outputs() {
+    println!("=== Test: Skip Existing Outputs ===");
+
+    let input_dir = tempdir().expect("Failed to create temp input dir");
+    let output_dir = tempdir().expect("Failed to create temp output dir");
+    fs::write(input_dir.path().join("a.txt"), "alpha").unwrap();
+    fs::write(input_dir.path().join("b.txt"), "beta").unwrap();
+    fs::write(output_dir.path().join("a.txt"), "earlier output").unwrap();
+
+    let input_str = input_dir.path().to_str().unwrap().to_string();
+    let output_str = output_dir.path().to_str().unwrap().to_string();
+    let mut processor = create_processor_with_mode(
+        input_str.clone(),
+        output_str.clone(),
+        ProcessingMode::BatchFiles,
+    )
+    .expect("Failed to create processor");
+    processor
+        .process_from_source(&input_str, &output_str)
+        .expect("Batch processing failed");
+
+    let stats = processor.get_stats();
+    assert_eq!(stats.files_processed, 1);
+    assert_eq!(stats.files_skipped, 1);
+    assert_eq!(stats.files_failed, 0, "Existing output counted as failure");
+    assert_eq!(
+        fs::read_to_string(output_dir.path().join("a.txt")).unwrap(),
+        "earlier output",
+        "Existing output was overwritten"
+    );
+    assert_eq!(
+        fs::read_to_string(output_dir.path().join("b.txt")).unwrap(),
+        "beta"
+    );
+
+    println!("✅ Skip existing outputs pass");
+}